@@ -0,0 +1,114 @@
+//! Throughput of a client handling the fragments of relayed messages.
+//!
+//! Run with `cargo bench --features testing --bench packet_handling`, on
+//! two revisions to compare them.
+
+use std::time::{Duration, Instant};
+
+use assembler::HighLevelMessageFactory;
+use chat_client::{
+    testing::{MockNetwork, RunningNetwork},
+    ChatClientBuilder, ServerSelection,
+};
+use log::LevelFilter;
+use messages::{
+    client_commands::{ChatClientCommand, ChatClientEvent},
+    high_level_messages::{MessageContent, ServerMessage},
+};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{NodeType, Packet},
+};
+
+const CLIENT: NodeId = 2;
+const SENDER: NodeId = 1;
+const SERVER: NodeId = 20;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Messages relayed to the client per run.
+const MESSAGES: usize = 500;
+/// Bytes of chat text per message, a few dozen fragments.
+const MESSAGE_SIZE: usize = 4096;
+const RUNS: usize = 5;
+
+/// A client that registers by itself and logs nothing, so that only
+/// packet handling is measured.
+fn quiet_self_registering(builder: ChatClientBuilder) -> ChatClientBuilder {
+    builder
+        .with_auto_discovery(true)
+        .with_auto_register(ServerSelection::default())
+        .with_log_level(LevelFilter::Off)
+}
+
+/// A client registered to a server, behind a drone.
+fn registered_client() -> RunningNetwork {
+    let network = MockNetwork::new()
+        .with_configured_client(CLIENT, quiet_self_registering)
+        .with_drone(11, 0.0)
+        .with_server(SERVER)
+        .with_link(CLIENT, 11)
+        .with_link(11, SERVER)
+        .spawn();
+
+    network
+        .client(CLIENT)
+        .send(ChatClientCommand::StartChatClient);
+    network
+        .client(CLIENT)
+        .wait_for(TIMEOUT, |event| {
+            matches!(event, ChatClientEvent::SuccessfulRegistration(SERVER))
+        })
+        .expect("the client did not register");
+    network
+}
+
+/// The fragments of `MESSAGES` messages relayed by the server, as they
+/// reach the client.
+fn relayed_messages() -> Vec<Packet> {
+    let mut factory = HighLevelMessageFactory::new(SERVER, NodeType::Server);
+    let route = SourceRoutingHeader::with_first_hop(vec![SERVER, 11, CLIENT]);
+    let content = "x".repeat(MESSAGE_SIZE);
+    let mut fragments = Vec::new();
+    for _ in 0..MESSAGES {
+        fragments.extend(factory.get_message_from_message_content(
+            MessageContent::FromServer(ServerMessage::MessageReceived {
+                sender_id: SENDER,
+                content: content.clone(),
+            }),
+            &route,
+            CLIENT,
+        ));
+    }
+    for fragment in &mut fragments {
+        fragment.routing_header.hop_index = fragment.routing_header.hops.len() - 1;
+    }
+    fragments
+}
+
+fn main() {
+    for run in 1..=RUNS {
+        let network = registered_client();
+        let fragments = relayed_messages();
+        let count = fragments.len();
+
+        let started = Instant::now();
+        for packet in fragments {
+            network
+                .client(CLIENT)
+                .send(ChatClientCommand::InjectPacket(packet));
+        }
+        for _ in 0..MESSAGES {
+            network
+                .client(CLIENT)
+                .wait_for(TIMEOUT, |event| {
+                    matches!(event, ChatClientEvent::MessageReceived(SENDER, ..))
+                })
+                .expect("a message was not received");
+        }
+        let elapsed = started.elapsed();
+
+        let per_fragment = elapsed / u32::try_from(count).expect("too many fragments");
+        println!("run {run}: {count} fragments in {elapsed:.2?}, {per_fragment:.2?} per fragment");
+        let _ = network.shutdown();
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Instant,
+};
+
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Ack, Packet, PacketType},
+};
+
+use super::{config::AckMode, ChatClient};
+
+/// The Acks held for the fragments of one message.
+#[derive(Debug)]
+struct HeldAcks {
+    route: Vec<NodeId>,
+    fragment_indices: BTreeSet<u64>,
+    since: Instant,
+}
+
+/// Acks waiting to be sent when `AckMode::Batched` is set, keyed by the
+/// source and session id of the message they acknowledge.
+#[derive(Debug, Default)]
+pub(super) struct AckBatches {
+    held: HashMap<(NodeId, u64), HeldAcks>,
+}
+
+impl ChatClient {
+    /// Acknowledges a received fragment along `route`, the reverse of the
+    /// route it came from, now or as part of a batch.
+    pub(super) fn ack_fragment(
+        &mut self,
+        session_id: u64,
+        route: Vec<NodeId>,
+        fragment_index: u64,
+    ) {
+        let AckMode::Batched { max_fragments, .. } = self.config.ack_mode else {
+            self.send_ack(session_id, route, fragment_index);
+            return;
+        };
+        let Some(&source) = route.last() else {
+            return;
+        };
+
+        let held = self
+            .ack_batches
+            .held
+            .entry((source, session_id))
+            .or_insert_with(|| HeldAcks {
+                route,
+                fragment_indices: BTreeSet::new(),
+                since: Instant::now(),
+            });
+        held.fragment_indices.insert(fragment_index);
+        if held.fragment_indices.len() >= max_fragments {
+            self.flush_acks(source, session_id);
+        }
+    }
+
+    /// Sends the Acks held for a message right away.
+    pub(super) fn flush_acks(&mut self, source: NodeId, session_id: u64) {
+        let Some(held) = self.ack_batches.held.remove(&(source, session_id)) else {
+            return;
+        };
+        for fragment_index in held.fragment_indices {
+            self.send_ack(session_id, held.route.clone(), fragment_index);
+        }
+    }
+
+    /// Sends the batches held for longer than their delay.
+    pub(super) fn flush_due_acks(&mut self) {
+        let AckMode::Batched { max_delay, .. } = self.config.ack_mode else {
+            return;
+        };
+        let max_delay = self.scaled(max_delay);
+
+        let due: Vec<(NodeId, u64)> = self
+            .ack_batches
+            .held
+            .iter()
+            .filter(|(_, held)| held.since.elapsed() >= max_delay)
+            .map(|(&key, _)| key)
+            .collect();
+        for (source, session_id) in due {
+            self.flush_acks(source, session_id);
+        }
+    }
+
+    fn send_ack(&mut self, session_id: u64, route: Vec<NodeId>, fragment_index: u64) {
+        #[cfg(feature = "fault-injection")]
+        if self.ack_dropped() {
+            return;
+        }
+        let _ = self.forward_packet(Packet {
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: route,
+            },
+            session_id,
+            pack_type: PacketType::Ack(Ack { fragment_index }),
+        });
+    }
+}
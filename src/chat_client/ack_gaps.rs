@@ -0,0 +1,92 @@
+use std::{collections::HashMap, time::Duration};
+
+use colored::Colorize;
+use messages::client_commands::TraceStep;
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// How long a fragment may stay unacknowledged once a later fragment of
+/// the same message was acknowledged, before it is taken as silently
+/// dropped and sent again.
+const ACK_GAP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Fragments resent at most per tick when inferring their loss, so that a
+/// large message does not flood the network all at once.
+const ACK_GAP_WINDOW: usize = 16;
+
+/// The highest fragment index acknowledged for each message in flight,
+/// keyed by destination and session id.
+///
+/// Drones are expected to Nack the fragments they drop, but one that
+/// drops silently leaves the fragment waiting forever. Acks arriving for
+/// later fragments of the same message show that the route works, so the
+/// earlier fragments still waiting past [`ACK_GAP_TIMEOUT`] are resent.
+#[derive(Debug, Default)]
+pub(super) struct AckGaps {
+    highest_acked: HashMap<(NodeId, u64), u64>,
+}
+
+impl ChatClient {
+    pub(super) fn fragment_acked(&mut self, peer: NodeId, session_id: u64, fragment_index: u64) {
+        let highest = self
+            .ack_gaps
+            .highest_acked
+            .entry((peer, session_id))
+            .or_insert(fragment_index);
+        *highest = (*highest).max(fragment_index);
+    }
+
+    /// Resends the fragments left behind by the Acks of later fragments.
+    pub(super) fn retransmit_ack_gaps(&mut self) {
+        let cached = &self.packet_cache;
+        self.ack_gaps
+            .highest_acked
+            .retain(|&(peer, session_id), _| cached.peers_of(session_id).contains(&peer));
+        if self.is_flooding() {
+            return;
+        }
+
+        let timeout = self.scaled(ACK_GAP_TIMEOUT);
+        let mut lingering = Vec::new();
+        for (&(peer, session_id), &highest) in &self.ack_gaps.highest_acked {
+            if self.is_circuit_open(peer) {
+                continue;
+            }
+            lingering.extend(
+                self.packet_cache
+                    .lingering(peer, session_id, highest, timeout)
+                    .into_iter()
+                    .filter(|&(fragment_index, _)| {
+                        !self.retransmission_scheduled(peer, session_id, fragment_index)
+                    })
+                    .map(|(fragment_index, packet)| (peer, fragment_index, packet)),
+            );
+        }
+        lingering.sort_by_key(|(peer, fragment_index, packet)| {
+            (*peer, packet.session_id, *fragment_index)
+        });
+        lingering.truncate(ACK_GAP_WINDOW);
+
+        for (peer, fragment_index, packet) in lingering {
+            warn!(
+                self,
+                "{} [ {} ]: Fragment {} of session {} to [ Server {} ] was skipped by later Acks, resending it",
+                "!!!".yellow(),
+                self.log_tag,
+                fragment_index,
+                packet.session_id,
+                peer
+            );
+            self.trace(
+                packet.session_id,
+                TraceStep::Retransmitted {
+                    fragment_index,
+                    reroute: false,
+                },
+            );
+            self.stats.retransmissions += 1;
+            let _ = self.forward_packet(packet);
+        }
+    }
+}
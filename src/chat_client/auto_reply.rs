@@ -0,0 +1,61 @@
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, MessageDirection};
+use wg_2024::network::NodeId;
+
+use super::{config::AutoReply, payload::Payload, ChatClient};
+
+impl ChatClient {
+    /// Answers a chat message of `sender_id` when auto-reply is enabled.
+    pub(super) fn auto_reply(&mut self, sender_id: NodeId, content: &str) {
+        let reply = match &self.config.auto_reply {
+            None => return,
+            Some(AutoReply::Echo) => content.to_string(),
+            Some(AutoReply::Text(text)) => text.clone(),
+        };
+
+        let payload = Payload::AutoReply {
+            content: reply.clone(),
+        };
+        match self.send_payload(sender_id, &payload) {
+            Ok(()) => self
+                .history
+                .record(sender_id, MessageDirection::Sent, reply),
+            Err(e) => warn!(
+                self,
+                "{} [ {} ]: Could not reply to [ Client {} ]: {}",
+                "!!!".yellow(),
+                self.log_tag,
+                sender_id,
+                e
+            ),
+        }
+    }
+
+    /// Reads the automatic answer of a client, like any chat message but
+    /// without answering it.
+    pub(super) fn auto_reply_received(&mut self, sender_id: NodeId, content: String) {
+        info!(
+            self,
+            "{} [ {} ]: Automatic reply received from [ Client {} ]: {}",
+            "✓".green(),
+            self.log_tag,
+            sender_id,
+            content
+        );
+        let Some(timestamps) = self.conversation_received(sender_id, None, None, content.clone())
+        else {
+            return;
+        };
+        self.count_unread(sender_id);
+        self.bot_think();
+        self.history.record_at(
+            timestamps.normalized,
+            sender_id,
+            MessageDirection::Received,
+            content.clone(),
+        );
+        self.send_event(ChatClientEvent::MessageReceived(
+            sender_id, self.id, content, timestamps,
+        ));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::client_commands::TraceStep;
+use wg_2024::{network::NodeId, packet::Packet};
+
+use super::{time_scale::rescaled, ChatClient};
+
+/// Delay before the first retransmission of a dropped fragment, doubled
+/// for every further Nack of the same fragment.
+const BACKOFF_BASE: Duration = Duration::from_millis(50);
+
+/// Upper bound of the retransmission delay.
+const BACKOFF_MAX: Duration = Duration::from_secs(5);
+
+/// Consecutive failures toward a destination, without an Ack in between,
+/// after which sends toward it are paused.
+const CIRCUIT_BREAKER_THRESHOLD: u32 = 10;
+
+/// How long sends toward a destination stay paused once its circuit opens.
+const CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Failure bookkeeping of the paths toward one destination.
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// Retransmissions waiting for their backoff to expire and the circuit
+/// breakers of the destinations they are sent to.
+///
+/// A fragment has at most one pending retransmission: further Nacks for it
+/// are ignored until it has been resent, so a drone flooding the client
+/// with Nacks cannot trigger a retransmission storm.
+#[derive(Debug, Default)]
+pub(super) struct Backoff {
+    /// When each fragment is due, and whether it gets a new route then.
+    scheduled: HashMap<(NodeId, u64, u64), (Instant, Packet, bool)>,
+    circuits: HashMap<NodeId, Circuit>,
+}
+
+impl Backoff {
+    /// Stretches the time left before every retransmission and every
+    /// circuit closing by `ratio`.
+    pub(super) fn rescale(&mut self, now: Instant, ratio: f64) {
+        for (due, _, _) in self.scheduled.values_mut() {
+            *due = rescaled(*due, now, ratio);
+        }
+        for until in self
+            .circuits
+            .values_mut()
+            .filter_map(|circuit| circuit.open_until.as_mut())
+        {
+            *until = rescaled(*until, now, ratio);
+        }
+    }
+}
+
+/// Exponential backoff for the `nacks`-th Nack of a fragment, randomized by
+/// up to half of it in either direction so that retransmissions spread out.
+fn backoff_delay(nacks: u32) -> Duration {
+    let delay = BACKOFF_BASE
+        .saturating_mul(1 << nacks.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX);
+    delay.mul_f64(0.5 + rand::random::<f64>())
+}
+
+impl ChatClient {
+    pub(super) fn retransmission_scheduled(
+        &self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) -> bool {
+        self.backoff
+            .scheduled
+            .contains_key(&(peer, session_id, fragment_index))
+    }
+
+    /// Resends `packet`, its `nacks`-th Nack just received, once its backoff
+    /// has expired, along a new route if `reroute` is set.
+    pub(super) fn schedule_retransmission(
+        &mut self,
+        peer: NodeId,
+        fragment_index: u64,
+        packet: Packet,
+        nacks: u32,
+        reroute: bool,
+    ) {
+        let due = Instant::now() + self.scaled(backoff_delay(nacks));
+        self.backoff.scheduled.insert(
+            (peer, packet.session_id, fragment_index),
+            (due, packet, reroute),
+        );
+    }
+
+    /// Resends the fragments whose backoff expired, except toward
+    /// destinations whose circuit is open.
+    pub(super) fn retransmit_due(&mut self) {
+        let now = Instant::now();
+        self.close_expired_circuits(now);
+
+        let due: Vec<(NodeId, u64, u64)> = self
+            .backoff
+            .scheduled
+            .iter()
+            .filter(|(&(peer, _, _), (at, _, _))| *at <= now && !self.is_circuit_open(peer))
+            .map(|(&key, _)| key)
+            .collect();
+
+        for key in due {
+            let Some((_, packet, reroute)) = self.backoff.scheduled.remove(&key) else {
+                continue;
+            };
+            let (_, session_id, fragment_index) = key;
+            self.trace(
+                session_id,
+                TraceStep::Retransmitted {
+                    fragment_index,
+                    reroute,
+                },
+            );
+            if self.is_flooding() {
+                self.defer_until_flooded(packet);
+            } else if reroute {
+                self.reroute_packet(packet);
+            } else {
+                self.stats.retransmissions += 1;
+                let _ = self.forward_packet(packet);
+            }
+        }
+    }
+
+    /// Drops the pending retransmission of a fragment that got acknowledged.
+    pub(super) fn cancel_retransmission(
+        &mut self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) {
+        self.backoff
+            .scheduled
+            .remove(&(peer, session_id, fragment_index));
+    }
+
+    /// Drops the pending retransmissions of a message.
+    pub(super) fn cancel_scheduled(&mut self, peer: NodeId, session_id: u64) {
+        self.backoff
+            .scheduled
+            .retain(|&(to, session, _), _| to != peer || session != session_id);
+    }
+
+    pub(super) fn is_circuit_open(&self, destination: NodeId) -> bool {
+        self.backoff
+            .circuits
+            .get(&destination)
+            .is_some_and(|circuit| circuit.open_until.is_some())
+    }
+
+    pub(super) fn path_failed(&mut self, destination: NodeId) {
+        let cooldown = self.scaled(CIRCUIT_BREAKER_COOLDOWN);
+        let circuit = self.backoff.circuits.entry(destination).or_default();
+        circuit.failures += 1;
+
+        if circuit.open_until.is_none() && circuit.failures >= CIRCUIT_BREAKER_THRESHOLD {
+            circuit.open_until = Some(Instant::now() + cooldown);
+            warn!(
+                self,
+                "{} [ {} ]: Paths toward [ Node {} ] failed {} times in a row, pausing sends for {:?}",
+                "!!!".yellow(),
+                self.log_tag,
+                destination,
+                circuit.failures,
+                cooldown
+            );
+        }
+    }
+
+    pub(super) fn path_succeeded(&mut self, destination: NodeId) {
+        self.backoff.circuits.remove(&destination);
+    }
+
+    fn close_expired_circuits(&mut self, now: Instant) {
+        let expired: Vec<NodeId> = self
+            .backoff
+            .circuits
+            .iter()
+            .filter(|(_, circuit)| circuit.open_until.is_some_and(|until| until <= now))
+            .map(|(&destination, _)| destination)
+            .collect();
+
+        for destination in expired {
+            if let Some(circuit) = self.backoff.circuits.get_mut(&destination) {
+                // half-open: a single further failure opens it again
+                circuit.open_until = None;
+                circuit.failures = CIRCUIT_BREAKER_THRESHOLD - 1;
+            }
+            info!(
+                self,
+                "{} [ {} ]: Resuming sends toward [ Node {} ]",
+                "ℹ".blue(),
+                self.log_tag,
+                destination
+            );
+            self.fill_window(destination);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use colored::Colorize;
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+impl ChatClient {
+    /// Drops every message `client_id` sends from now on, and refuses to
+    /// send it any.
+    pub(super) fn block_client(&mut self, client_id: NodeId) {
+        if self.blocked_clients.insert(client_id) {
+            info!(
+                self,
+                "{} [ {} ]: Blocked [ Client {} ]",
+                "✓".green(),
+                self.log_tag,
+                client_id
+            );
+        }
+    }
+
+    pub(super) fn unblock_client(&mut self, client_id: NodeId) {
+        if self.blocked_clients.remove(&client_id) {
+            info!(
+                self,
+                "{} [ {} ]: Unblocked [ Client {} ]",
+                "✓".green(),
+                self.log_tag,
+                client_id
+            );
+        } else {
+            warn!(
+                self,
+                "{} [ {} ]: [ Client {} ] is not blocked",
+                "!!!".yellow(),
+                self.log_tag,
+                client_id
+            );
+        }
+    }
+
+    pub(super) fn is_blocked(&self, client_id: NodeId) -> bool {
+        self.blocked_clients.contains(&client_id)
+    }
+}
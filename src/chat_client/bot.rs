@@ -0,0 +1,180 @@
+use std::time::Instant;
+
+use colored::Colorize;
+use rand::Rng;
+use wg_2024::network::NodeId;
+
+use super::{
+    config::{MessageSize, PeerSelection},
+    time_scale::rescaled,
+    ChatClient,
+};
+
+/// The state of the traffic generated in bot mode.
+///
+/// The configured `BotProfile` says what to send, this only keeps when to.
+#[derive(Debug, Default)]
+pub(super) struct Bot {
+    /// Whether `ChatClientCommand::StopBot` paused the bot.
+    paused: bool,
+    /// When the next message is due, `None` while the bot is not sending.
+    next_at: Option<Instant>,
+    /// The end of the current think time.
+    quiet_until: Option<Instant>,
+    next_round_robin: usize,
+    sent: u64,
+}
+
+impl Bot {
+    /// Stretches the time left before the next message and the end of the
+    /// think time by `ratio`.
+    pub(super) fn rescale(&mut self, now: Instant, ratio: f64) {
+        for deadline in [&mut self.next_at, &mut self.quiet_until]
+            .into_iter()
+            .flatten()
+        {
+            *deadline = rescaled(*deadline, now, ratio);
+        }
+    }
+}
+
+impl ChatClient {
+    pub(super) fn has_bot(&self) -> bool {
+        self.config.bot.is_some()
+    }
+
+    /// Resumes the bot paused by `ChatClientCommand::StopBot`.
+    pub(super) fn start_bot(&mut self) {
+        self.bot.paused = false;
+        info!(self, "{} [ {} ]: Bot started", "ℹ".blue(), self.log_tag);
+    }
+
+    /// Pauses the bot until `ChatClientCommand::StartBot`.
+    pub(super) fn stop_bot(&mut self) {
+        self.bot.paused = true;
+        self.bot.next_at = None;
+        info!(
+            self,
+            "{} [ {} ]: Bot stopped after {} messages",
+            "ℹ".blue(),
+            self.log_tag,
+            self.bot.sent
+        );
+    }
+
+    /// Starts a think time, after a registration or a received message.
+    pub(super) fn bot_think(&mut self) {
+        if let Some(profile) = &self.config.bot {
+            self.bot.quiet_until = Some(Instant::now() + self.scaled(profile.think_time));
+        }
+    }
+
+    /// Sends the next message of the bot when it is due.
+    pub(super) fn check_bot(&mut self) {
+        let Some((interval, message_size)) = self
+            .config
+            .bot
+            .as_ref()
+            .map(|profile| (profile.interval, profile.message_size))
+        else {
+            return;
+        };
+        if !self.running || self.bot.paused || self.registered.is_none() {
+            self.bot.next_at = None;
+            return;
+        }
+        let now = Instant::now();
+        if self.bot.quiet_until.is_some_and(|until| now < until) {
+            return;
+        }
+
+        // exponentially distributed, so that messages form a Poisson
+        // process
+        let next_at = now
+            + self
+                .scaled(interval)
+                .mul_f64(-(1.0 - rand::random::<f64>()).ln());
+        match self.bot.next_at {
+            Some(due) if due <= now => self.bot.next_at = Some(next_at),
+            Some(_) => return,
+            None => {
+                self.bot.next_at = Some(next_at);
+                return;
+            }
+        }
+
+        let Some(peer) = self.bot_peer() else {
+            return;
+        };
+        let length = match message_size {
+            MessageSize::Fixed(length) => length,
+            MessageSize::Uniform { min, max } => rand::thread_rng().gen_range(min..=max),
+        };
+        let text: String = (0..length)
+            .map(|_| char::from(b'a' + rand::random::<u8>() % 26))
+            .collect();
+
+        info!(
+            self,
+            "{} [ {} ]: Bot sending {} characters to [ ChatClient {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            length,
+            peer
+        );
+        match self.send_chat_message(peer, text) {
+            Ok(()) => self.bot.sent += 1,
+            Err(e) => warn!(
+                self,
+                "{} [ {} ]: Bot could not send to [ ChatClient {} ]: {}",
+                "!!!".yellow(),
+                self.log_tag,
+                peer,
+                e
+            ),
+        }
+    }
+
+    /// Chooses the peer of the next message among the known, unblocked
+    /// peers.
+    fn bot_peer(&mut self) -> Option<NodeId> {
+        let peers: Vec<NodeId> = self
+            .peers
+            .ids()
+            .into_iter()
+            .filter(|&id| id != self.id && !self.is_blocked(id))
+            .collect();
+        if peers.is_empty() {
+            return None;
+        }
+
+        match &self.config.bot.as_ref()?.peers {
+            PeerSelection::Uniform => Some(peers[rand::thread_rng().gen_range(0..peers.len())]),
+            PeerSelection::RoundRobin => {
+                let peer = peers[self.bot.next_round_robin % peers.len()];
+                self.bot.next_round_robin = self.bot.next_round_robin.wrapping_add(1);
+                Some(peer)
+            }
+            PeerSelection::Weighted(weights) => {
+                let weights: Vec<(NodeId, u64)> = weights
+                    .iter()
+                    .filter(|&&(id, weight)| weight > 0 && peers.contains(&id))
+                    .map(|&(id, weight)| (id, u64::from(weight)))
+                    .collect();
+                let total: u64 = weights.iter().map(|&(_, weight)| weight).sum();
+                if total == 0 {
+                    return None;
+                }
+                let mut pick = rand::thread_rng().gen_range(0..total);
+                weights.into_iter().find_map(|(id, weight)| {
+                    if pick < weight {
+                        Some(id)
+                    } else {
+                        pick -= weight;
+                        None
+                    }
+                })
+            }
+        }
+    }
+}
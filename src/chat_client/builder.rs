@@ -0,0 +1,777 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use assembler::HighLevelMessageFactory;
+use crossbeam_channel::{tick, Receiver, Sender};
+use log::LevelFilter;
+use messages::client_commands::{ChatClientCommand, ChatClientEvent, ClientStats};
+use source_routing::Router;
+use wg_2024::{
+    network::NodeId,
+    packet::{NodeType, Packet, FRAGMENT_DSIZE},
+};
+
+#[cfg(feature = "fault-injection")]
+use super::fault_injection::FaultInjection;
+use super::{
+    ack_batching::AckBatches,
+    ack_gaps::AckGaps,
+    backoff::Backoff,
+    bot::Bot,
+    client_list_refresh::ClientListRefresh,
+    config::{
+        AckMode, AutoReply, BotProfile, ChatClientConfig, ConformanceMode, FloodMode, MessageSize,
+        RetryPolicy, ServerSelection, TimeScale, UnknownSourcePolicy, ValidationPolicies,
+    },
+    control_requests::ControlRequests,
+    conversations::Conversations,
+    dispatch::FragmentDispatch,
+    events::EventLog,
+    flood_sessions::FloodSessions,
+    history::MessageHistory,
+    inbound::InboundAssembler,
+    inbound_rate::InboundRate,
+    integrity::Integrity,
+    link_failures::LinkFailures,
+    logging::{ClientLogger, LogTarget},
+    nack_strategy::{DefaultNackStrategy, NackStrategy},
+    outbox::Outbox,
+    packet_cache::PacketCache,
+    peers::PeerRegistry,
+    personas::Personas,
+    presence::Presence,
+    probes::Probes,
+    progress::SendProgress,
+    quarantine::SourceQuarantine,
+    route_cache::RouteCache,
+    routing::Routing,
+    server_health::ServerHealth,
+    server_list::ServerList,
+    server_queries::ServerQueries,
+    server_selection::ServerSelector,
+    shortcuts::ShortcutRelays,
+    storage::{FileStorage, MemoryStorage, Storage},
+    topology::Topology,
+    traces::TraceRegistry,
+    ChatClient, TICK_INTERVAL,
+};
+
+/// Step-by-step constructor for a `ChatClient`.
+///
+/// Only the identifier and the controller and packet channels are required,
+/// everything else falls back to the defaults used by `ChatClient::new`.
+///
+/// # Methods
+///
+/// * `new` - Starts building a `ChatClient` with the mandatory parameters.
+/// * `with_retry_policy` - Sets how dropped fragments are retransmitted.
+/// * `with_nack_strategy` - Replaces how the client reacts to Nacks.
+/// * `with_routing` - Replaces the default `Router`.
+/// * `with_flood_interval` - Sets how long floods are given to complete.
+/// * `with_packet_cache` - Bounds the fragments kept for retransmission.
+/// * `with_multipath` - Spreads large messages over disjoint routes.
+/// * `with_send_window` - Sets how many fragments may be in flight per destination.
+/// * `with_auto_discovery` - Floods automatically on start and on missing routes.
+/// * `with_flood_mode` - Sets how floods of other nodes are handled.
+/// * `with_ack_mode` - Sets when received fragments are acknowledged.
+/// * `with_log_prefix` - Sets a prefix identifying the client in the logs.
+/// * `with_log_target` - Sets where the client's logs are written.
+/// * `with_log_level` - Sets the most verbose level that is logged.
+/// * `with_log_colors` - Enables or disables colors in the logs.
+/// * `with_packet_logging` - Sets the level and sampling of per-packet log lines.
+/// * `with_initial_neighbors` - Adds neighbours the client is connected to.
+/// * `with_download_dir` - Sets where files received from other clients are saved.
+/// * `with_storage` - Replaces where the history and the state are kept.
+/// * `with_history_file` - Backs the message history with a JSON file.
+/// * `with_outbox_file` - Keeps unacknowledged fragments across restarts.
+/// * `with_sequence_numbers` - Numbers chat messages to detect missing ones.
+/// * `with_message_timestamps` - Timestamps chat messages to order them.
+/// * `with_integrity_checks` - Checksums the contents sent to other clients.
+/// * `with_stats_interval` - Enables periodic statistics reports.
+/// * `with_telemetry` - Enables the structured telemetry events.
+/// * `with_tracing` - Records the timeline of every message.
+/// * `with_keep_alive` - Pings the registered server periodically.
+/// * `with_server_query_timeout` - Sets how long servers have to tell their type.
+/// * `with_max_message_size` - Rejects or splits long chat messages.
+/// * `with_auto_register` - Registers and moves between servers by itself.
+/// * `with_fragment_size` - Sends smaller fragments than the protocol's.
+/// * `with_reassembly_limits` - Bounds the incomplete messages kept per source.
+/// * `with_bot_profile` - Generates chat traffic without the controller.
+/// * `with_unknown_source_policy` - Sets how fragments from unknown nodes are handled.
+/// * `with_conformance` - Checks received packets against the protocol.
+/// * `with_validation` - Sets how packets not addressed to the client are handled.
+/// * `with_auto_reply` - Answers every chat message by itself.
+/// * `with_event_replay` - Numbers events and keeps the recent ones for replay.
+/// * `with_inbound_rate_limit` - Drops the fragments of sources sending too fast.
+/// * `with_compression` - Compresses long contents, with the `compression` feature.
+/// * `with_time_scale` - Speeds up or slows down every timer of the client.
+/// * `build` - Creates the `ChatClient`.
+pub struct ChatClientBuilder {
+    id: NodeId,
+    controller_send: Sender<ChatClientEvent>,
+    controller_recv: Receiver<ChatClientCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    config: ChatClientConfig,
+    log_prefix: Option<String>,
+    log_target: LogTarget,
+    log_level: LevelFilter,
+    log_colors: bool,
+    packet_logging: (LevelFilter, u32),
+    storage: Box<dyn Storage>,
+    outbox_file: Option<PathBuf>,
+    traces: TraceRegistry,
+    conversations: Conversations,
+    integrity: Integrity,
+    nack_strategy: Option<Box<dyn NackStrategy>>,
+    routing: Option<Box<dyn Routing>>,
+}
+
+impl ChatClientBuilder {
+    /// Starts building a `ChatClient`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The unique identifier for the `ChatClient`.
+    /// * `controller_send` - A `Sender` to send events to the controller.
+    /// * `controller_recv` - A `Receiver` to receive commands from the controller.
+    /// * `packet_recv` - A `Receiver` to receive packets.
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<ChatClientEvent>,
+        controller_recv: Receiver<ChatClientCommand>,
+        packet_recv: Receiver<Packet>,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send: HashMap::new(),
+            config: ChatClientConfig::default(),
+            log_prefix: None,
+            log_target: LogTarget::default(),
+            log_level: LevelFilter::Trace,
+            log_colors: true,
+            packet_logging: (LevelFilter::Trace, 1),
+            storage: Box::new(MemoryStorage::default()),
+            outbox_file: None,
+            traces: TraceRegistry::default(),
+            conversations: Conversations::default(),
+            integrity: Integrity::default(),
+            nack_strategy: None,
+            routing: None,
+        }
+    }
+
+    /// Sets how the client retransmits fragments that keep being dropped.
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Replaces the [`DefaultNackStrategy`] deciding whether a Nacked
+    /// fragment is retried, rerouted, reflooded for or given up. A custom
+    /// strategy makes the `RetryPolicy` unused.
+    #[must_use]
+    pub fn with_nack_strategy(mut self, nack_strategy: impl NackStrategy + 'static) -> Self {
+        self.nack_strategy = Some(Box::new(nack_strategy));
+        self
+    }
+
+    /// Replaces the `Router` of the `source_routing` crate by another
+    /// [`Routing`] implementation. The neighbours the client starts with
+    /// are added to it when the client is built.
+    #[must_use]
+    pub fn with_routing(mut self, routing: impl Routing + 'static) -> Self {
+        self.routing = Some(Box::new(routing));
+        self
+    }
+
+    /// Sets how long the client waits for flood responses before relying
+    /// on the topology they produced. Defaults to 2 seconds.
+    #[must_use]
+    pub fn with_flood_interval(mut self, flood_interval: Duration) -> Self {
+        self.config.flood_interval = flood_interval;
+        self
+    }
+
+    /// Bounds the memory used by fragments kept for retransmission.
+    ///
+    /// Messages are cancelled, and reported with
+    /// `ChatClientEvent::MessageExpired`, once they have not been fully
+    /// acknowledged within `message_timeout`, or when more than `capacity`
+    /// fragments are awaiting their Ack, oldest first. Defaults to 4096
+    /// fragments and 90 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of fragments awaiting their Ack.
+    /// * `message_timeout` - How long a message may wait for its Acks.
+    #[must_use]
+    pub fn with_packet_cache(mut self, capacity: usize, message_timeout: Duration) -> Self {
+        self.config.packet_cache_capacity = capacity;
+        self.config.message_timeout = message_timeout;
+        self
+    }
+
+    /// Spreads the fragments of a message over up to `paths` routes that
+    /// share no drone, so that a single lossy drone only delays part of it.
+    /// Defaults to 1, sending every fragment on the `Router`'s route.
+    #[must_use]
+    pub fn with_multipath(mut self, paths: usize) -> Self {
+        self.config.multipath = paths;
+        self
+    }
+
+    /// Sets how many fragments toward the same destination may be awaiting
+    /// their Ack at once; further fragments are sent as Acks arrive.
+    /// Defaults to 32.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `send_window` is zero.
+    #[must_use]
+    pub fn with_send_window(mut self, send_window: usize) -> Self {
+        assert!(send_window > 0, "the send window must not be empty");
+        self.config.send_window = send_window;
+        self
+    }
+
+    /// Makes the client discover the network by itself: it floods when
+    /// `ChatClientCommand::StartChatClient` arrives and queries the servers
+    /// once the flood completes, and a message without a route triggers a
+    /// flood and is sent again once it completes. Disabled by default.
+    #[must_use]
+    pub fn with_auto_discovery(mut self, auto_discovery: bool) -> Self {
+        self.config.auto_discovery = auto_discovery;
+        self
+    }
+
+    /// Sets how the client takes part in floods started by other nodes.
+    /// Defaults to `FloodMode::RespondOnly`.
+    #[must_use]
+    pub fn with_flood_mode(mut self, flood_mode: FloodMode) -> Self {
+        self.config.flood_mode = flood_mode;
+        self
+    }
+
+    /// Sets when the client acknowledges the fragments it receives.
+    /// Defaults to `AckMode::Immediate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a batched mode holds at most zero fragments.
+    #[must_use]
+    pub fn with_ack_mode(mut self, ack_mode: AckMode) -> Self {
+        if let AckMode::Batched { max_fragments, .. } = ack_mode {
+            assert!(max_fragments > 0, "an Ack batch must hold a fragment");
+        }
+        self.config.ack_mode = ack_mode;
+        self
+    }
+
+    /// Sets a prefix prepended to the client's name in every log line, to
+    /// tell apart clients of different simulations sharing a logger.
+    #[must_use]
+    pub fn with_log_prefix(mut self, log_prefix: impl Into<String>) -> Self {
+        self.log_prefix = Some(log_prefix.into());
+        self
+    }
+
+    /// Sets where the client's log lines are written. Defaults to the `log`
+    /// facade, so that they reach the logger installed by the simulation.
+    #[must_use]
+    pub fn with_log_target(mut self, log_target: LogTarget) -> Self {
+        self.log_target = log_target;
+        self
+    }
+
+    /// Sets the most verbose level the client logs at. Defaults to
+    /// `LevelFilter::Trace`, leaving the filtering to the log target.
+    #[must_use]
+    pub fn with_log_level(mut self, log_level: LevelFilter) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// Enables or disables the terminal colors of the log lines, which are
+    /// on by default. Disable them when logging to a file or a channel.
+    #[must_use]
+    pub fn with_log_colors(mut self, log_colors: bool) -> Self {
+        self.log_colors = log_colors;
+        self
+    }
+
+    /// Logs the lines written for every packet handled or forwarded up to
+    /// `level` only, and only one in `sampling` of them, so that high
+    /// packet rates do not spend their time formatting logs. Messages,
+    /// commands, warnings and errors are not affected. Defaults to every
+    /// line at every level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sampling` is zero.
+    #[must_use]
+    pub fn with_packet_logging(mut self, level: LevelFilter, sampling: u32) -> Self {
+        assert!(
+            sampling > 0,
+            "at least one packet line in every sample is logged"
+        );
+        self.packet_logging = (level, sampling);
+        self
+    }
+
+    /// Adds neighbours the client is connected to from the start.
+    ///
+    /// # Arguments
+    ///
+    /// * `neighbors` - Pairs of `NodeId` and the `Sender` to reach that node.
+    #[must_use]
+    pub fn with_initial_neighbors(
+        mut self,
+        neighbors: impl IntoIterator<Item = (NodeId, Sender<Packet>)>,
+    ) -> Self {
+        self.packet_send.extend(neighbors);
+        self
+    }
+
+    /// Sets the directory files received from other clients are saved to.
+    /// Defaults to the system's temporary directory.
+    #[must_use]
+    pub fn with_download_dir(mut self, download_dir: impl Into<PathBuf>) -> Self {
+        self.config.download_dir = download_dir.into();
+        self
+    }
+
+    /// Replaces where the message history and the state of the client are
+    /// kept, in memory by default.
+    ///
+    /// Both are loaded from `storage` when the client is built, and written
+    /// back to it when the client receives `ChatClientCommand::Shutdown`.
+    #[must_use]
+    pub fn with_storage(mut self, storage: impl Storage + 'static) -> Self {
+        self.storage = Box::new(storage);
+        self
+    }
+
+    /// Backs the message history with a JSON file, as
+    /// `with_storage(FileStorage::new(path))` does.
+    ///
+    /// Entries already stored in the file are loaded when the client is
+    /// built, and the whole history is written back when the client
+    /// receives `ChatClientCommand::Shutdown`.
+    #[must_use]
+    pub fn with_history_file(self, path: impl Into<PathBuf>) -> Self {
+        self.with_storage(FileStorage::new(path))
+    }
+
+    /// Keeps the fragments waiting for their Ack in a JSON file.
+    ///
+    /// They are written to the file when the client receives
+    /// `ChatClientCommand::Shutdown`, and read back when the client is
+    /// built, to be sent again once a flood has found a route to their
+    /// destination.
+    #[must_use]
+    pub fn with_outbox_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.outbox_file = Some(path.into());
+        self
+    }
+
+    /// Enables or disables sequence numbers on the chat messages sent.
+    /// Recipients use them to order their conversation with this client
+    /// and to send `ChatClientEvent::MessageGap` for the messages that
+    /// never arrived. Peers must run this client to read them. Disabled by
+    /// default.
+    #[must_use]
+    pub fn with_sequence_numbers(mut self, enabled: bool) -> Self {
+        self.conversations.set_sequenced(enabled);
+        self
+    }
+
+    /// Enables or disables timestamps on the chat messages sent.
+    /// Recipients use them, corrected for the skew between the clocks, to
+    /// order their conversation with this client and to time the
+    /// messages in `ChatClientEvent::MessageReceived` and in their history.
+    /// Peers must run this client to read them. Disabled by default.
+    #[must_use]
+    pub fn with_message_timestamps(mut self, enabled: bool) -> Self {
+        self.conversations.set_timestamped(enabled);
+        self
+    }
+
+    /// Enables or disables checksums on every content sent to another
+    /// client. A recipient whose copy does not match sends
+    /// `ChatClientEvent::CorruptMessage` to its controller, discards it and
+    /// asks the sender for it again. Peers must run this client to read
+    /// them. Disabled by default.
+    #[must_use]
+    pub fn with_integrity_checks(mut self, enabled: bool) -> Self {
+        self.integrity = Integrity::new(enabled);
+        self
+    }
+
+    /// Enables or disables `ChatClientEvent::Telemetry` events, sent to the
+    /// controller for every packet sent, Nack received, reroute and flood.
+    /// Disabled by default.
+    #[must_use]
+    pub fn with_telemetry(mut self, enabled: bool) -> Self {
+        self.config.telemetry = enabled;
+        self
+    }
+
+    /// Enables or disables the timelines returned by
+    /// `ChatClientCommand::TraceMessage`, recording when each message is
+    /// fragmented and each of its fragments is sent, Nacked, retransmitted,
+    /// acknowledged or reassembled. With telemetry enabled every step is
+    /// also sent as a `TelemetryKind::TraceStep`. Disabled by default.
+    #[must_use]
+    pub fn with_tracing(mut self, enabled: bool) -> Self {
+        self.traces = TraceRegistry::new(enabled);
+        self
+    }
+
+    /// Makes the client send a `ChatClientEvent::Stats` report to the
+    /// controller every `stats_interval`, on top of the ones requested
+    /// with `ChatClientCommand::GetStats`.
+    #[must_use]
+    pub fn with_stats_interval(mut self, stats_interval: Duration) -> Self {
+        self.config.stats_interval = Some(stats_interval);
+        self
+    }
+
+    /// Makes the client ping its registered server every `interval`. After
+    /// `max_missed` pings in a row go unanswered, the registration is
+    /// cleared, `ChatClientEvent::ServerUnreachable` is sent and the client
+    /// floods to discover the servers again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_missed` is zero.
+    #[must_use]
+    pub fn with_keep_alive(mut self, interval: Duration, max_missed: u32) -> Self {
+        assert!(max_missed > 0, "at least one keep-alive must be missed");
+        self.config.keep_alive = Some(interval);
+        self.config.max_missed_keep_alives = max_missed;
+        self
+    }
+
+    /// Gives servers `timeout` to answer a `GetServerType` query, and
+    /// resends it up to `retries` times before sending
+    /// `ChatClientEvent::ServerTypeQueryFailed`. Defaults to 5 seconds and
+    /// 2 retries.
+    #[must_use]
+    pub fn with_server_query_timeout(mut self, timeout: Duration, retries: u32) -> Self {
+        self.config.server_query_timeout = timeout;
+        self.config.server_query_retries = retries;
+        self
+    }
+
+    /// Limits chat messages to `max_size` bytes. Longer messages are
+    /// rejected with `ChatClientEvent::MessageTooLarge`, or, if `chunking`
+    /// is set, sent as numbered chunks the recipient puts back together.
+    /// Unlimited by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is zero.
+    #[must_use]
+    pub fn with_max_message_size(mut self, max_size: usize, chunking: bool) -> Self {
+        assert!(max_size > 0, "the maximum message size must not be zero");
+        self.config.max_message_size = Some(max_size);
+        self.config.chunk_oversized = chunking;
+        self
+    }
+
+    /// Makes the client register by itself to one of the communication
+    /// servers it discovered, chosen with `selection`, and move to a better
+    /// one when its server misses keep-alives or routing attempts. Every
+    /// choice is reported with `ChatClientEvent::ServerSelected`. Disabled
+    /// by default.
+    #[must_use]
+    pub fn with_auto_register(mut self, selection: ServerSelection) -> Self {
+        self.config.auto_register = Some(selection);
+        self
+    }
+
+    /// Sets the bytes of data carried by each fragment sent, to trade
+    /// throughput for a lower chance of losing a whole message. Defaults to
+    /// the protocol's 128 bytes.
+    ///
+    /// Fragments smaller than 128 bytes are only understood by nodes that
+    /// join fragments by their length, as this client does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero or larger than 128 bytes.
+    #[must_use]
+    pub fn with_fragment_size(mut self, size: usize) -> Self {
+        assert!(
+            (1..=FRAGMENT_DSIZE).contains(&size),
+            "a fragment carries between 1 and {FRAGMENT_DSIZE} bytes"
+        );
+        self.config.fragment_size = size;
+        self
+    }
+
+    /// Bounds the incomplete messages each source may have being put back
+    /// together. Past either limit, the oldest incomplete message of the
+    /// source is discarded and reported with
+    /// `ChatClientEvent::ReassemblyEvicted`. Defaults to 64 messages and
+    /// 4 MiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_sessions` - The maximum number of incomplete messages.
+    /// * `max_bytes` - The maximum number of bytes buffered in them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_sessions` is zero.
+    #[must_use]
+    pub fn with_reassembly_limits(mut self, max_sessions: usize, max_bytes: usize) -> Self {
+        assert!(max_sessions > 0, "a source must be able to send a message");
+        self.config.reassembly_sessions = max_sessions;
+        self.config.reassembly_bytes = max_bytes;
+        self
+    }
+
+    /// Makes the client chat by itself once registered, sending messages
+    /// of the profile's sizes to the profile's peers at its rate, as load
+    /// for the network. The messages go through the same path, and raise
+    /// the same events, as the ones the controller asks for. The bot can
+    /// be paused with `ChatClientCommand::StopBot`. Disabled by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the profile's interval is zero, or if its message sizes
+    /// range from a larger to a smaller length.
+    #[must_use]
+    pub fn with_bot_profile(mut self, profile: BotProfile) -> Self {
+        assert!(
+            !profile.interval.is_zero(),
+            "a bot must wait between its messages"
+        );
+        if let MessageSize::Uniform { min, max } = profile.message_size {
+            assert!(
+                min <= max,
+                "a bot's shortest message is longer than its longest"
+            );
+        }
+        self.config.bot = Some(profile);
+        self
+    }
+
+    /// Sets what happens to fragments coming from a node the client never
+    /// heard of, which are reported with `ChatClientEvent::UnknownSource`
+    /// unless they are accepted. Defaults to
+    /// `UnknownSourcePolicy::Accept`.
+    #[must_use]
+    pub fn with_unknown_source_policy(mut self, policy: UnknownSourcePolicy) -> Self {
+        self.config.unknown_sources = policy;
+        self
+    }
+
+    /// Checks every packet received against the rules of the WG 2024
+    /// protocol: the bounds of `hop_index`, a route ending at the client
+    /// and never visiting a node twice, path traces starting at the
+    /// initiator, and fragments lying within their message. Packets
+    /// breaking a rule are reported with
+    /// `ChatClientEvent::ProtocolViolation`. Defaults to
+    /// `ConformanceMode::Off`.
+    #[must_use]
+    pub fn with_conformance(mut self, mode: ConformanceMode) -> Self {
+        self.config.conformance = mode;
+        self
+    }
+
+    /// Sets, for each type of packet, what the client does with the
+    /// packets whose routing header does not end at it. Every outcome is
+    /// counted in `ClientStats::validation`. Defaults to
+    /// `ValidationPolicy::Strict` for every type.
+    #[must_use]
+    pub fn with_validation(mut self, policies: ValidationPolicies) -> Self {
+        self.config.validation = policies;
+        self
+    }
+
+    /// Makes the client answer every chat message it receives, through
+    /// the registered server, so that other clients can measure round
+    /// trips without a controller in the loop. Answers are marked so that
+    /// two clients in this mode do not reply to each other forever.
+    /// Disabled by default.
+    #[must_use]
+    pub fn with_auto_reply(mut self, reply: AutoReply) -> Self {
+        self.config.auto_reply = Some(reply);
+        self
+    }
+
+    /// Numbers every event sent to the controller, which then arrives as
+    /// a `ChatClientEvent::Sequenced`, and keeps the last `capacity` of
+    /// them. A controller that missed events, for instance because its
+    /// channel was recreated, gets them again with
+    /// `ChatClientCommand::ReplayEvents`. Disabled by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    pub fn with_event_replay(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "at least one event must be kept for replay");
+        self.config.event_replay = Some(capacity);
+        self
+    }
+
+    /// Drops the fragments a source sends beyond `fragments_per_second`,
+    /// Nacking them as `Dropped` so that the source sends them again
+    /// later, and reports the source with
+    /// `ChatClientEvent::SourceThrottled`. This keeps a misbehaving node
+    /// from starving the commands of the controller. Disabled by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fragments_per_second` is zero.
+    #[must_use]
+    pub fn with_inbound_rate_limit(mut self, fragments_per_second: u32) -> Self {
+        assert!(
+            fragments_per_second > 0,
+            "at least one fragment per second must be accepted"
+        );
+        self.config.inbound_rate_limit = Some(fragments_per_second);
+        self
+    }
+
+    /// Compresses with DEFLATE the contents sent to other clients that are
+    /// at least `threshold` bytes long, when that makes them shorter, so
+    /// that text-heavy messages take fewer fragments. Receiving clients
+    /// decompress them by themselves, provided they were built with the
+    /// `compression` feature too. What was saved is reported in
+    /// `ClientStats::compression`. Disabled by default.
+    #[cfg(feature = "compression")]
+    #[must_use]
+    pub fn with_compression(mut self, threshold: usize) -> Self {
+        self.config.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Runs every timer of the client, from retransmission backoffs and
+    /// timeouts to heartbeats and flood waits, at `scale` instead of in
+    /// real time. `ChatClientCommand::SetTimeScale` changes it while the
+    /// client runs. Real time by default.
+    #[must_use]
+    pub fn with_time_scale(mut self, scale: TimeScale) -> Self {
+        self.config.time_scale = scale;
+        self
+    }
+
+    /// Creates the `ChatClient`.
+    #[must_use]
+    pub fn build(self) -> ChatClient {
+        let id = self.id;
+        let mut router = self
+            .routing
+            .unwrap_or_else(|| Box::new(Router::new(id, NodeType::Client)));
+        let mut topology = Topology::default();
+        let nack_strategy = self
+            .nack_strategy
+            .unwrap_or_else(|| Box::new(DefaultNackStrategy::new(self.config.retry_policy)));
+        for &neighbor in self.packet_send.keys() {
+            router.add_neighbour(neighbor);
+            topology.add_link((id, NodeType::Client), (neighbor, NodeType::Drone));
+        }
+
+        let log_tag = match self.log_prefix {
+            Some(prefix) => format!("{prefix} ChatClient {id}"),
+            None => format!("ChatClient {id}"),
+        };
+
+        let (packet_level, packet_sampling) = self.packet_logging;
+        let logger = ClientLogger::new(self.log_target, self.log_level, self.log_colors)
+            .unwrap_or_else(|e| {
+                log::error!(
+                    "[ {log_tag} ]: Failed to open the log target, using the log facade: {e}"
+                );
+                ClientLogger::default()
+            })
+            .with_packet_logging(packet_level, packet_sampling);
+
+        let mut client = ChatClient {
+            id,
+            log_tag,
+            logger,
+            config: self.config,
+            msgfactory: HighLevelMessageFactory::new(id, NodeType::Client),
+            packet_cache: PacketCache::default(),
+            progress: SendProgress::default(),
+            probes: Probes::default(),
+            bot: Bot::default(),
+            quarantine: SourceQuarantine::default(),
+            inbound_rate: InboundRate::default(),
+            outbox: Outbox::default(),
+            send_windows: HashMap::new(),
+            dispatch: FragmentDispatch::default(),
+            backoff: Backoff::default(),
+            ack_batches: AckBatches::default(),
+            ack_gaps: AckGaps::default(),
+            nack_strategy,
+            router,
+            route_cache: RouteCache::default(),
+            link_failures: LinkFailures::default(),
+            server_queries: ServerQueries::default(),
+            shortcuts: ShortcutRelays::default(),
+            flood: None,
+            flood_sessions: FloodSessions::default(),
+            flood_requests_seen: HashMap::new(),
+            topology,
+            peers: PeerRegistry::default(),
+            personas: Personas::default(),
+            client_list_refresh: ClientListRefresh::default(),
+            blocked_clients: HashSet::new(),
+            username: None,
+            inbound: InboundAssembler::default(),
+            message_buffer: VecDeque::new(),
+            history: MessageHistory::default(),
+            storage: self.storage,
+            traces: self.traces,
+            conversations: self.conversations,
+            integrity: self.integrity,
+            #[cfg(feature = "fault-injection")]
+            faults: FaultInjection::default(),
+            pending_messages: VecDeque::new(),
+            rooms: HashMap::new(),
+            transfers: HashMap::new(),
+            incoming_chunks: HashMap::new(),
+            presence: Presence::default(),
+            stats: ClientStats::default(),
+            stats_reported_at: Instant::now(),
+            created_at: Instant::now(),
+            controller_send: self.controller_send,
+            controller_gone: Cell::new(false),
+            event_log: RefCell::new(EventLog::default()),
+            controller_recv: self.controller_recv,
+            packet_recv: self.packet_recv,
+            packet_send: self.packet_send,
+            tick: tick(self.config.time_scale.scale(TICK_INTERVAL)),
+            running: false,
+            shutdown: false,
+            registered: None,
+            server_health: ServerHealth::default(),
+            control_requests: ControlRequests::default(),
+            communication_server_list: ServerList::default(),
+            server_selector: ServerSelector::default(),
+        };
+
+        client.load_storage();
+        if let Some(path) = self.outbox_file {
+            client.load_outbox(path);
+        }
+
+        client
+    }
+}
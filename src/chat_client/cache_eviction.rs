@@ -0,0 +1,109 @@
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+impl ChatClient {
+    /// Cancels the oldest messages until the packet cache fits the
+    /// configured capacity.
+    pub(super) fn enforce_cache_capacity(&mut self) {
+        while self.packet_cache.len() > self.config.packet_cache_capacity {
+            let Some((peer, session_id)) = self.packet_cache.oldest_message() else {
+                break;
+            };
+            self.cancel_message(peer, session_id);
+        }
+    }
+
+    /// Cancels the messages that have not been fully acknowledged within
+    /// the configured message timeout.
+    pub(super) fn expire_cached_messages(&mut self) {
+        for (peer, session_id) in self
+            .packet_cache
+            .expired_messages(self.scaled(self.config.message_timeout))
+        {
+            self.cancel_message(peer, session_id);
+        }
+    }
+
+    /// Cancels the message the controller was told about with
+    /// `MessageSent`, its message id being the session id of its fragments.
+    pub(super) fn cancel_message_by_id(&mut self, message_id: u64) {
+        let peers = self.packet_cache.peers_of(message_id);
+        if peers.is_empty() {
+            warn!(
+                self,
+                "{} [ {} ]: Cannot cancel message {}, all its fragments were already acknowledged",
+                "!!!".yellow(),
+                self.log_tag,
+                message_id
+            );
+            return;
+        }
+
+        let fragments: usize = peers
+            .into_iter()
+            .map(|peer| self.drop_message(peer, message_id))
+            .sum();
+        info!(
+            self,
+            "{} [ {} ]: Cancelled message {}, {} fragments will not be sent",
+            "ℹ".blue(),
+            self.log_tag,
+            message_id,
+            fragments
+        );
+        self.send_event(ChatClientEvent::MessageCancelled(message_id));
+    }
+
+    /// Drops a message one of whose fragments ran out of retransmissions,
+    /// since it can no longer be reassembled.
+    pub(super) fn give_up_message(&mut self, peer: NodeId, session_id: u64) {
+        let fragments = self.drop_message(peer, session_id);
+
+        error!(
+            self,
+            "{} [ {} ]: Message with session_id: {} to [ Node {} ] cannot be delivered, dropping its {} remaining fragments",
+            "✗".red(),
+            self.log_tag,
+            session_id,
+            peer,
+            fragments
+        );
+        self.send_event(ChatClientEvent::MessageDeliveryFailed(session_id, peer));
+        if self.registered == Some(peer) {
+            self.schedule_client_list_refresh(&format!(
+                "message {session_id} could not be delivered"
+            ));
+        }
+    }
+
+    fn cancel_message(&mut self, peer: NodeId, session_id: u64) {
+        let fragments = self.drop_message(peer, session_id);
+
+        warn!(
+            self,
+            "{} [ {} ]: Giving up on message with session_id: {} to [ Node {} ], {} fragments were never acknowledged",
+            "!!!".yellow(),
+            self.log_tag,
+            session_id,
+            peer,
+            fragments
+        );
+        self.send_event(ChatClientEvent::MessageExpired(session_id));
+    }
+
+    /// Forgets every fragment of a message, whether cached, waiting to be
+    /// dispatched, queued behind the send window, deferred or waiting for
+    /// a retransmission.
+    fn drop_message(&mut self, peer: NodeId, session_id: u64) -> usize {
+        let fragments = self.packet_cache.remove_message(peer, session_id);
+        let dispatched = self.cancel_dispatched(peer, session_id);
+        self.cancel_windowed(peer, session_id, fragments.saturating_sub(dispatched));
+        self.cancel_deferred(peer, session_id);
+        self.cancel_scheduled(peer, session_id);
+        self.progress.forget(peer, session_id);
+        fragments
+    }
+}
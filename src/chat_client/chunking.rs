@@ -0,0 +1,177 @@
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, MessageDirection};
+use wg_2024::network::NodeId;
+
+use super::{error::ChatClientError, payload::Payload, ChatClient};
+
+/// Time after which the chunks of a message that never completed are
+/// discarded.
+const CHUNKED_MESSAGE_TTL: Duration = Duration::from_secs(90);
+
+/// Most chunks a received message may announce.
+const MAX_CHUNKS: u32 = 4096;
+
+/// Most incomplete chunked messages a single client may be sending at once.
+const MAX_OPEN_MESSAGES: usize = 16;
+
+/// The chunks of a long chat message received so far.
+#[derive(Debug)]
+pub(super) struct IncomingChunks {
+    chunks: Vec<Option<String>>,
+    started: Instant,
+}
+
+/// Splits `text` into pieces of at most `max_size` bytes, on character
+/// boundaries.
+fn split_text(text: &str, max_size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if !current.is_empty() && current.len() + c.len_utf8() > max_size {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    chunks.push(current);
+    chunks
+}
+
+impl ChatClient {
+    /// Whether `text` is longer than the configured maximum message size.
+    pub(super) fn is_oversized(&self, text: &str) -> bool {
+        self.config
+            .max_message_size
+            .is_some_and(|max_size| text.len() > max_size)
+    }
+
+    /// Sends a text longer than the maximum message size as a sequence of
+    /// chunks the recipient puts back together.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chunk cannot be sent, the chunks after it are
+    /// not sent.
+    pub(super) fn send_chunked_message(
+        &mut self,
+        recipient_id: NodeId,
+        text: String,
+    ) -> Result<(), ChatClientError> {
+        let Some(max_size) = self.config.max_message_size else {
+            return self.send_chat_message(recipient_id, text);
+        };
+
+        let chunks = split_text(&text, max_size);
+        let message_id = rand::random::<u64>();
+        let total = u32::try_from(chunks.len()).unwrap_or(u32::MAX);
+        info!(
+            self,
+            "{} [ {} ]: Sending a message of {} bytes to [ ChatClient {} ] in {} chunks",
+            "ℹ".blue(),
+            self.log_tag,
+            text.len(),
+            recipient_id,
+            total
+        );
+
+        for (index, content) in (0..total).zip(chunks) {
+            let payload = Payload::ChatChunk {
+                message_id,
+                index,
+                total,
+                content,
+            };
+            self.send_payload(recipient_id, &payload)?;
+        }
+
+        self.conversations.sent(recipient_id, None, text.clone());
+        self.history
+            .record(recipient_id, MessageDirection::Sent, text);
+        Ok(())
+    }
+
+    /// Stores a chunk of a long chat message, delivering the message once
+    /// every chunk has arrived.
+    pub(super) fn chunk_received(
+        &mut self,
+        sender_id: NodeId,
+        message_id: u64,
+        index: u32,
+        total: u32,
+        content: String,
+    ) {
+        let key = (sender_id, message_id);
+        let open = self
+            .incoming_chunks
+            .keys()
+            .filter(|&&(sender, _)| sender == sender_id)
+            .count();
+        let accepted = index < total
+            && total <= MAX_CHUNKS
+            && (open < MAX_OPEN_MESSAGES || self.incoming_chunks.contains_key(&key));
+        let chunks = accepted
+            .then(|| {
+                self.incoming_chunks
+                    .entry(key)
+                    .or_insert_with(|| IncomingChunks {
+                        chunks: vec![None; total as usize],
+                        started: Instant::now(),
+                    })
+            })
+            .filter(|chunks| chunks.chunks.len() == total as usize);
+        let Some(chunks) = chunks else {
+            warn!(
+                self,
+                "{} [ {} ]: Ignoring chunk {} of {} from [ Client {} ]",
+                "!!!".yellow(),
+                self.log_tag,
+                index,
+                total,
+                sender_id
+            );
+            return;
+        };
+        chunks.chunks[index as usize] = Some(content);
+
+        if chunks.chunks.iter().any(Option::is_none) {
+            return;
+        }
+        let Some(chunks) = self.incoming_chunks.remove(&key) else {
+            return;
+        };
+        let content: String = chunks.chunks.into_iter().flatten().collect();
+
+        info!(
+            self,
+            "{} [ {} ]: Message of {} chunks received from [ Client {} ]",
+            "✓".green(),
+            self.log_tag,
+            total,
+            sender_id
+        );
+        let Some(timestamps) = self.conversation_received(sender_id, None, None, content.clone())
+        else {
+            return;
+        };
+        self.count_unread(sender_id);
+        self.bot_think();
+        self.auto_reply(sender_id, &content);
+        self.history.record_at(
+            timestamps.normalized,
+            sender_id,
+            MessageDirection::Received,
+            content.clone(),
+        );
+        self.send_event(ChatClientEvent::MessageReceived(
+            sender_id, self.id, content, timestamps,
+        ));
+    }
+
+    /// Discards the chunked messages that did not complete in time.
+    pub(super) fn expire_incoming_chunks(&mut self) {
+        let ttl = self.scaled(CHUNKED_MESSAGE_TTL);
+        self.incoming_chunks
+            .retain(|_, chunks| chunks.started.elapsed() < ttl);
+    }
+}
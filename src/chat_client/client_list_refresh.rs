@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use messages::high_level_messages::{ClientMessage, MessageContent};
+
+use super::{time_scale::rescaled, ChatClient};
+
+/// Time the delivery failures are gathered for before the client list is
+/// requested, so that a burst of them costs a single request.
+const REFRESH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// A client list request due after peers turned out to be unreachable.
+#[derive(Debug, Default)]
+pub(super) struct ClientListRefresh {
+    due: Option<Instant>,
+}
+
+impl ClientListRefresh {
+    /// Stretches the time left before the request by `ratio`.
+    pub(super) fn rescale(&mut self, now: Instant, ratio: f64) {
+        if let Some(due) = &mut self.due {
+            *due = rescaled(*due, now, ratio);
+        }
+    }
+}
+
+impl ChatClient {
+    /// Requests the client list from the registered server within
+    /// [`REFRESH_DEBOUNCE`], unless a request is already scheduled.
+    pub(super) fn schedule_client_list_refresh(&mut self, reason: &str) {
+        if self.client_list_refresh.due.is_some() || self.registered.is_none() {
+            return;
+        }
+        info!(
+            self,
+            "{} [ {} ]: Refreshing the client list soon, {}",
+            "ℹ".blue(),
+            self.log_tag,
+            reason
+        );
+        self.client_list_refresh.due = Some(Instant::now() + self.scaled(REFRESH_DEBOUNCE));
+    }
+
+    /// Sends the scheduled client list request once it is due.
+    pub(super) fn check_client_list_refresh(&mut self) {
+        if self
+            .client_list_refresh
+            .due
+            .is_none_or(|due| Instant::now() < due)
+        {
+            return;
+        }
+        self.client_list_refresh.due = None;
+
+        if let Some(server_id) = self.registered {
+            let message_content = MessageContent::FromClient(ClientMessage::GetClientList);
+            // failures are logged, the next delivery failure schedules another request
+            let _ = self.generate_and_send_message(message_content, server_id);
+        }
+    }
+}
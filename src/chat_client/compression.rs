@@ -0,0 +1,127 @@
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+use colored::Colorize;
+#[cfg(feature = "compression")]
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+#[cfg(feature = "compression")]
+use messages::high_level_messages::{ClientMessage, MessageContent};
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// Marks contents sent compressed with DEFLATE, then encoded in base64 so
+/// that the `CommunicationServer` can relay them as strings.
+const COMPRESSED_PREFIX: &str = "\u{1}rustastic-deflate:";
+
+#[cfg(feature = "compression")]
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 without padding, the length of the content telling how many
+/// bytes its last group holds.
+#[cfg(feature = "compression")]
+fn to_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 4];
+        group[1..=chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes(group);
+        for i in 0..=chunk.len() {
+            encoded.push(char::from(BASE64[(n >> (18 - 6 * i)) as usize & 63]));
+        }
+    }
+    encoded
+}
+
+#[cfg(feature = "compression")]
+fn from_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3 + 2);
+    for chunk in encoded.as_bytes().chunks(4) {
+        if chunk.len() < 2 {
+            return None;
+        }
+        let mut n = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64.iter().position(|&b| b == c)?;
+            n |= u32::try_from(value).ok()? << (18 - 6 * i);
+        }
+        bytes.extend_from_slice(&n.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(bytes)
+}
+
+#[cfg(feature = "compression")]
+fn deflate(content: &str) -> Option<String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes()).ok()?;
+    let compressed = encoder.finish().ok()?;
+    Some(format!("{COMPRESSED_PREFIX}{}", to_base64(&compressed)))
+}
+
+#[cfg(feature = "compression")]
+fn inflate(encoded: &str) -> Option<String> {
+    let mut content = String::new();
+    DeflateDecoder::new(from_base64(encoded)?.as_slice())
+        .read_to_string(&mut content)
+        .ok()?;
+    Some(content)
+}
+
+/// Compressed contents cannot be read without the `compression` feature.
+#[cfg(not(feature = "compression"))]
+fn inflate(_encoded: &str) -> Option<String> {
+    None
+}
+
+impl ChatClient {
+    /// Compresses the content of a `SendMessage` at least as long as the
+    /// configured threshold, if that makes it shorter.
+    #[cfg(feature = "compression")]
+    pub(super) fn compressed(&mut self, mut message_content: MessageContent) -> MessageContent {
+        let Some(threshold) = self.config.compression_threshold else {
+            return message_content;
+        };
+        if let MessageContent::FromClient(ClientMessage::SendMessage { content, .. }) =
+            &mut message_content
+        {
+            if let Some(compressed) = (content.len() >= threshold)
+                .then(|| deflate(content))
+                .flatten()
+                .filter(|compressed| compressed.len() < content.len())
+            {
+                let saved = content.len() - compressed.len();
+                self.stats.compression.messages_compressed += 1;
+                self.stats.compression.bytes_saved += u64::try_from(saved).unwrap_or(u64::MAX);
+                *content = compressed;
+            }
+        }
+        message_content
+    }
+
+    /// `content` as `sender_id` wrote it before compressing it, if it did.
+    ///
+    /// # Returns
+    ///
+    /// The content, `None` if it was compressed and cannot be restored.
+    pub(super) fn decompressed(&self, sender_id: NodeId, content: String) -> Option<String> {
+        let Some(encoded) = content.strip_prefix(COMPRESSED_PREFIX) else {
+            return Some(content);
+        };
+        let restored = inflate(encoded);
+        if restored.is_none() {
+            error!(
+                self,
+                "{} [ {} ]: Cannot decompress the message of [ Client {} ]{}",
+                "✗".red(),
+                self.log_tag,
+                sender_id,
+                if cfg!(feature = "compression") {
+                    ""
+                } else {
+                    ", the client was built without compression"
+                }
+            );
+        }
+        restored
+    }
+}
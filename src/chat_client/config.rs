@@ -0,0 +1,365 @@
+use std::{env, path::PathBuf, time::Duration};
+
+use wg_2024::{
+    network::NodeId,
+    packet::{PacketType, FRAGMENT_DSIZE},
+};
+
+/// How the `ChatClient` reacts to fragments that keep being dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Dropped Nacks for the same fragment after which the client floods
+    /// the network again before retransmitting.
+    pub reflood_after: u32,
+    /// Dropped Nacks for the same fragment after which the client stops
+    /// retransmitting it, `None` to retry forever.
+    pub max_retransmissions: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            reflood_after: 30,
+            max_retransmissions: None,
+        }
+    }
+}
+
+/// How the `ChatClient` takes part in floods started by other nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FloodMode {
+    /// Answer the first `FloodRequest` of every flood with a
+    /// `FloodResponse` and ignore its duplicates.
+    #[default]
+    RespondOnly,
+    /// Behave like a drone: forward the first `FloodRequest` of a flood to
+    /// every other neighbour, and answer duplicates, or requests coming
+    /// from the only neighbour, with a `FloodResponse`.
+    PerSpec,
+}
+
+/// When the `ChatClient` acknowledges the fragments it receives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AckMode {
+    /// Send the Ack of every fragment as soon as it arrives, as the
+    /// protocol specifies.
+    #[default]
+    Immediate,
+    /// Hold the Acks of a message until `max_fragments` of them are
+    /// pending, `max_delay` has passed since the first one, or the message
+    /// is complete, then send them together. Fragments received again
+    /// meanwhile are only acknowledged once.
+    Batched {
+        max_fragments: usize,
+        max_delay: Duration,
+    },
+}
+
+/// How the `ChatClient` chooses a communication server to register to
+/// among the ones it discovered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ServerSelection {
+    /// Take the discovered servers in turn, one per registration.
+    RoundRobin,
+    /// Take the server that answered its queries the fastest.
+    #[default]
+    LowestLatency,
+    /// Take the server that last listed the most clients.
+    MostClients,
+}
+
+/// What the `ChatClient` does with fragments whose source it never heard
+/// of: neither a discovered server, a known client, a neighbour, nor a
+/// node of its topology.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownSourcePolicy {
+    /// Handle them like any other fragment.
+    #[default]
+    Accept,
+    /// Acknowledge and discard them.
+    Reject,
+    /// Acknowledge and hold them back until the source becomes known,
+    /// discarding them if it does not soon enough. With `flood`, the
+    /// client floods the network to learn about the source.
+    Quarantine { flood: bool },
+}
+
+/// Whether the `ChatClient` checks the packets it receives against the
+/// rules of the WG 2024 protocol, to audit the nodes it talks to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConformanceMode {
+    /// Only check what the client needs to handle a packet.
+    #[default]
+    Off,
+    /// Report every packet breaking a rule with
+    /// `ChatClientEvent::ProtocolViolation`, and handle it as usual.
+    Audit,
+    /// Report every packet breaking a rule, and discard it.
+    Strict,
+}
+
+/// What the `ChatClient` does with a packet whose routing header does not
+/// end at the client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationPolicy {
+    /// Nack fragments with `UnexpectedRecipient`, and hand the other
+    /// packets to the controller with a `ControllerShortcut`.
+    #[default]
+    Strict,
+    /// Discard the packet without telling anyone.
+    Lenient,
+    /// Handle the packet as if its routing header ended at the client.
+    Permissive,
+}
+
+/// The `ValidationPolicy` of each type of packet. `FloodRequest`s are left
+/// out, their routing header is never checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValidationPolicies {
+    pub fragment: ValidationPolicy,
+    pub ack: ValidationPolicy,
+    pub nack: ValidationPolicy,
+    pub flood_response: ValidationPolicy,
+}
+
+impl ValidationPolicies {
+    /// The same `policy` for every type of packet.
+    #[must_use]
+    pub fn uniform(policy: ValidationPolicy) -> Self {
+        Self {
+            fragment: policy,
+            ack: policy,
+            nack: policy,
+            flood_response: policy,
+        }
+    }
+
+    pub(super) fn of(&self, packet_type: &PacketType) -> ValidationPolicy {
+        match packet_type {
+            PacketType::MsgFragment(_) => self.fragment,
+            PacketType::Ack(_) => self.ack,
+            PacketType::Nack(_) => self.nack,
+            PacketType::FloodResponse(_) => self.flood_response,
+            // routed by their path trace, not by their routing header
+            PacketType::FloodRequest(_) => ValidationPolicy::Permissive,
+        }
+    }
+}
+
+/// What a `ChatClient` in auto-reply mode answers to every chat message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoReply {
+    /// The message itself.
+    Echo,
+    /// A fixed text.
+    Text(String),
+}
+
+/// How a `ChatClient` in bot mode chooses the peer of each message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PeerSelection {
+    /// Any known peer, with the same probability.
+    #[default]
+    Uniform,
+    /// The known peers in turn, by identifier.
+    RoundRobin,
+    /// The listed peers, each with a probability proportional to its
+    /// weight. Peers that are not known yet are skipped.
+    Weighted(Vec<(NodeId, u32)>),
+}
+
+/// The length, in characters, of the messages a `ChatClient` in bot mode
+/// sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageSize {
+    Fixed(usize),
+    /// Any length between `min` and `max` included, with the same
+    /// probability.
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+}
+
+impl Default for MessageSize {
+    fn default() -> Self {
+        Self::Uniform { min: 16, max: 256 }
+    }
+}
+
+/// The chat traffic a `ChatClient` in bot mode generates by itself once it
+/// is registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotProfile {
+    /// Average time between two messages. Messages are sent at random
+    /// times, as a Poisson process of this mean interval.
+    pub interval: Duration,
+    pub peers: PeerSelection,
+    pub message_size: MessageSize,
+    /// Time the bot stays silent after registering and after every
+    /// message it receives, as a person reading it would.
+    pub think_time: Duration,
+}
+
+impl Default for BotProfile {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            peers: PeerSelection::default(),
+            message_size: MessageSize::default(),
+            think_time: Duration::from_millis(500),
+        }
+    }
+}
+
+/// How many times faster than real time the timers of a `ChatClient` run,
+/// so that a simulation can be sped up or slowed down as a whole.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale(f64);
+
+impl TimeScale {
+    /// Timers run in real time.
+    pub const REAL_TIME: Self = Self(1.0);
+
+    /// Timers running `factor` times faster than real time, `None` unless
+    /// `factor` is positive and finite.
+    #[must_use]
+    pub fn new(factor: f64) -> Option<Self> {
+        (factor.is_finite() && factor > 0.0).then_some(Self(factor))
+    }
+
+    /// How many times faster than real time the timers run.
+    #[must_use]
+    pub fn factor(self) -> f64 {
+        self.0
+    }
+
+    /// The real time a `duration` of simulated time lasts.
+    pub(super) fn scale(self, duration: Duration) -> Duration {
+        Duration::try_from_secs_f64(duration.as_secs_f64() / self.0).unwrap_or(Duration::MAX)
+    }
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self::REAL_TIME
+    }
+}
+
+/// Tunables of a `ChatClient`, set through the `ChatClientBuilder`.
+#[derive(Debug, Clone)]
+pub(super) struct ChatClientConfig {
+    pub(super) retry_policy: RetryPolicy,
+    /// Time given to a flood to complete before the client relies on the
+    /// topology it produced.
+    pub(super) flood_interval: Duration,
+    pub(super) flood_mode: FloodMode,
+    /// When received fragments are acknowledged.
+    pub(super) ack_mode: AckMode,
+    /// Whether the client floods by itself when started and when a message
+    /// has no route.
+    pub(super) auto_discovery: bool,
+    /// Fragments awaiting their Ack after which the oldest messages are
+    /// cancelled.
+    pub(super) packet_cache_capacity: usize,
+    /// Time after which a message that is still not fully acknowledged is
+    /// cancelled.
+    pub(super) message_timeout: Duration,
+    /// Node-disjoint routes the fragments of a message are spread over.
+    pub(super) multipath: usize,
+    /// Fragments toward the same destination that may be awaiting their
+    /// Ack at the same time.
+    pub(super) send_window: usize,
+    /// Directory files received from other clients are saved to.
+    pub(super) download_dir: PathBuf,
+    /// Interval between automatic `ChatClientEvent::Stats` reports, `None`
+    /// to only report them on `ChatClientCommand::GetStats`.
+    pub(super) stats_interval: Option<Duration>,
+    /// Whether `ChatClientEvent::Telemetry` events are sent to the controller.
+    pub(super) telemetry: bool,
+    /// Interval between keep-alives to the registered server, `None` to
+    /// only notice a lost server through routing failures.
+    pub(super) keep_alive: Option<Duration>,
+    /// Unanswered keep-alives after which the server is unreachable.
+    pub(super) max_missed_keep_alives: u32,
+    /// Time a server is given to answer a `GetServerType` query.
+    pub(super) server_query_timeout: Duration,
+    /// Queries resent to a server that did not answer before giving up.
+    pub(super) server_query_retries: u32,
+    /// Longest chat message accepted, in bytes, `None` for no limit.
+    pub(super) max_message_size: Option<usize>,
+    /// Whether longer chat messages are split into chunks instead of
+    /// being rejected.
+    pub(super) chunk_oversized: bool,
+    /// How the client registers by itself to a discovered server, and
+    /// moves to a better one when its server degrades, `None` to only
+    /// register on `ChatClientCommand::RegisterTo`.
+    pub(super) auto_register: Option<ServerSelection>,
+    /// Bytes of data carried by each fragment sent.
+    pub(super) fragment_size: usize,
+    /// Incomplete messages a single source may have being reassembled.
+    pub(super) reassembly_sessions: usize,
+    /// Bytes a single source may have buffered in incomplete messages.
+    pub(super) reassembly_bytes: usize,
+    pub(super) unknown_sources: UnknownSourcePolicy,
+    pub(super) conformance: ConformanceMode,
+    /// What is done with the packets not addressed to the client.
+    pub(super) validation: ValidationPolicies,
+    /// What the client answers to every chat message by itself, `None`
+    /// to leave answering to the controller.
+    pub(super) auto_reply: Option<AutoReply>,
+    /// Events kept to be replayed, `None` to send events unnumbered and
+    /// keep none.
+    pub(super) event_replay: Option<usize>,
+    /// Fragments accepted from a single source every second, `None` for
+    /// no limit.
+    pub(super) inbound_rate_limit: Option<u32>,
+    /// Length from which the contents sent to other clients are
+    /// compressed, `None` to never compress them.
+    #[cfg(feature = "compression")]
+    pub(super) compression_threshold: Option<usize>,
+    /// The traffic generated by the client by itself, `None` to only send
+    /// what the controller asks.
+    pub(super) bot: Option<BotProfile>,
+    /// The speed of every timer of the client relative to real time.
+    pub(super) time_scale: TimeScale,
+}
+
+impl Default for ChatClientConfig {
+    fn default() -> Self {
+        Self {
+            retry_policy: RetryPolicy::default(),
+            flood_interval: Duration::from_secs(2),
+            flood_mode: FloodMode::default(),
+            ack_mode: AckMode::default(),
+            auto_discovery: false,
+            packet_cache_capacity: 4096,
+            message_timeout: Duration::from_secs(90),
+            multipath: 1,
+            send_window: 32,
+            download_dir: env::temp_dir(),
+            stats_interval: None,
+            telemetry: false,
+            keep_alive: None,
+            max_missed_keep_alives: 3,
+            server_query_timeout: Duration::from_secs(5),
+            server_query_retries: 2,
+            max_message_size: None,
+            chunk_oversized: false,
+            auto_register: None,
+            fragment_size: FRAGMENT_DSIZE,
+            reassembly_sessions: 64,
+            reassembly_bytes: 4 * 1024 * 1024,
+            unknown_sources: UnknownSourcePolicy::default(),
+            conformance: ConformanceMode::default(),
+            validation: ValidationPolicies::default(),
+            auto_reply: None,
+            event_replay: None,
+            inbound_rate_limit: None,
+            #[cfg(feature = "compression")]
+            compression_threshold: None,
+            bot: None,
+            time_scale: TimeScale::default(),
+        }
+    }
+}
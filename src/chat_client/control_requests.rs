@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::{
+    client_commands::{ChatClientEvent, ProtocolViolation},
+    high_level_messages::{ClientMessage, MessageContent},
+};
+use wg_2024::network::NodeId;
+
+use super::{error::ChatClientError, ChatClient};
+
+/// Time a server is given to answer a registration or a logout, after
+/// which its answer is no longer expected.
+const CONTROL_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A request changing the registration of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum ControlRequest {
+    Registration,
+    Logout,
+}
+
+impl fmt::Display for ControlRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Registration => write!(f, "registration"),
+            Self::Logout => write!(f, "logout"),
+        }
+    }
+}
+
+/// The registrations and logouts sent to servers that did not answer yet.
+///
+/// A `SuccessfulRegistration` or `SuccessfullLogOut` only changes the
+/// registration of the client when it answers one of them, so that a
+/// server cannot register or log out the client by itself.
+#[derive(Debug, Default)]
+pub(super) struct ControlRequests {
+    outstanding: HashMap<(NodeId, ControlRequest), Instant>,
+}
+
+impl ChatClient {
+    /// Sends `request` to `server_id`, expecting its answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be sent.
+    pub(super) fn send_control_request(
+        &mut self,
+        server_id: NodeId,
+        request: ControlRequest,
+    ) -> Result<(), ChatClientError> {
+        let client_message = match request {
+            ControlRequest::Registration => ClientMessage::RegisterToChat,
+            ControlRequest::Logout => ClientMessage::Logout,
+        };
+        // kept on failure, the message may still be sent once a route is found
+        self.control_requests
+            .outstanding
+            .insert((server_id, request), Instant::now());
+        self.generate_and_send_message(MessageContent::FromClient(client_message), server_id)
+    }
+
+    /// Takes the `request` outstanding to `server_id`, which just answered
+    /// it in the message of `session_id`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the answer must be applied, `false` if no such request was
+    /// sent, in which case it was reported as a
+    /// `ChatClientEvent::ProtocolViolation`.
+    pub(super) fn control_answered(
+        &mut self,
+        server_id: NodeId,
+        session_id: u64,
+        request: ControlRequest,
+    ) -> bool {
+        if self
+            .control_requests
+            .outstanding
+            .remove(&(server_id, request))
+            .is_some()
+        {
+            return true;
+        }
+
+        warn!(
+            self,
+            "{} [ {} ]: Ignoring the {} confirmed by [ CommunicationServer {} ], it was not requested",
+            "!!!".yellow(),
+            self.log_tag,
+            request,
+            server_id
+        );
+        self.send_event(ChatClientEvent::ProtocolViolation(ProtocolViolation {
+            session_id,
+            packet_type: format!("{request} confirmation"),
+            routing_header: format!("from {server_id}"),
+            violations: vec![format!("no {request} was sent to {server_id}")],
+        }));
+        false
+    }
+
+    /// Forgets the requests that were not answered within
+    /// [`CONTROL_REQUEST_TIMEOUT`].
+    pub(super) fn expire_control_requests(&mut self) {
+        let timeout = self.scaled(CONTROL_REQUEST_TIMEOUT);
+        self.control_requests
+            .outstanding
+            .retain(|_, sent_at| sent_at.elapsed() < timeout);
+    }
+}
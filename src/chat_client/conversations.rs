@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, HashMap};
+
+use colored::Colorize;
+use messages::client_commands::{
+    ChatClientEvent, Conversation, ConversationEntry, MessageDirection, MessageTimestamps,
+};
+use wg_2024::network::NodeId;
+
+use super::{history::now_millis, payload::Payload, ChatClient};
+
+/// Largest jump in sequence numbers still reported as missing messages,
+/// larger ones, forward or backward, are taken as the peer starting over.
+const MAX_GAP: u64 = 1024;
+
+/// Largest increase of the apparent delay of a peer's messages, in
+/// milliseconds, still taken as network delay. Larger ones are taken as
+/// the peer's clock being set back.
+const MAX_CLOCK_STEP: u64 = 60_000;
+
+/// The chat messages exchanged with one peer.
+#[derive(Debug, Default)]
+struct Thread {
+    entries: Vec<ConversationEntry>,
+    next_outgoing: u64,
+    /// The highest sequence number received, or the one the peer started
+    /// over from.
+    last_incoming: Option<u64>,
+    missing: BTreeSet<u64>,
+    /// Estimated difference between this client's clock and the peer's,
+    /// plus the shortest delay of its messages.
+    clock_offset: Option<i128>,
+}
+
+impl Thread {
+    /// The time on this client's clock at which a message sent at
+    /// `sent_at` on the peer's clock is taken to have been sent.
+    ///
+    /// The offset between the clocks is the smallest delay seen between a
+    /// send timestamp and its arrival, so messages keep the order they
+    /// were sent in and are never placed after their arrival.
+    fn normalize(&mut self, sent_at: u64, received_at: u64) -> u64 {
+        let delay = i128::from(received_at) - i128::from(sent_at);
+        let offset = match self.clock_offset {
+            Some(offset) if delay >= offset && delay - offset <= i128::from(MAX_CLOCK_STEP) => {
+                offset
+            }
+            _ => delay,
+        };
+        self.clock_offset = Some(offset);
+        u64::try_from(i128::from(sent_at) + offset)
+            .map_or(received_at, |normalized| normalized.min(received_at))
+    }
+
+    /// Adds `entry` after every entry of the same time or earlier.
+    fn insert_by_time(&mut self, entry: ConversationEntry) {
+        let position = self
+            .entries
+            .iter()
+            .rposition(|other| other.timestamp <= entry.timestamp)
+            .map_or(0, |position| position + 1);
+        self.entries.insert(position, entry);
+    }
+}
+
+/// Chat messages grouped by peer, in sequence order.
+///
+/// When sequence numbers are enabled every chat message sent carries the
+/// next number of its conversation, so the recipient can put late
+/// messages back in place and report the ones that never arrived.
+///
+/// When timestamps are enabled every chat message sent carries the time it
+/// was sent, and received messages without sequence numbers are ordered by
+/// that time, corrected for the skew between the two clocks.
+#[derive(Debug, Default)]
+pub(super) struct Conversations {
+    sequenced: bool,
+    timestamped: bool,
+    threads: HashMap<NodeId, Thread>,
+}
+
+impl Conversations {
+    pub(super) fn set_sequenced(&mut self, sequenced: bool) {
+        self.sequenced = sequenced;
+    }
+
+    pub(super) fn set_timestamped(&mut self, timestamped: bool) {
+        self.timestamped = timestamped;
+    }
+
+    /// The content to send for `content`, carrying the current time if
+    /// timestamps are enabled.
+    pub(super) fn timestamped(&self, content: String) -> String {
+        if self.timestamped {
+            Payload::Timestamped {
+                sent_at: now_millis(),
+                content,
+            }
+            .encode()
+        } else {
+            content
+        }
+    }
+
+    /// The sequence number the next chat message to `peer` carries, if
+    /// sequence numbers are enabled.
+    pub(super) fn next_sequence(&self, peer: NodeId) -> Option<u64> {
+        self.sequenced.then(|| {
+            self.threads
+                .get(&peer)
+                .map_or(0, |thread| thread.next_outgoing)
+        })
+    }
+
+    pub(super) fn sent(&mut self, peer: NodeId, sequence: Option<u64>, content: String) {
+        let thread = self.threads.entry(peer).or_default();
+        if let Some(sequence) = sequence {
+            // past u64::MAX the recipient sees the peer starting over
+            thread.next_outgoing = sequence.wrapping_add(1);
+        }
+        thread.entries.push(ConversationEntry {
+            timestamp: now_millis(),
+            direction: MessageDirection::Sent,
+            sequence,
+            content,
+        });
+    }
+
+    pub(super) fn get(&self, peer: NodeId) -> Conversation {
+        let thread = self.threads.get(&peer);
+        Conversation {
+            peer,
+            entries: thread
+                .map(|thread| thread.entries.clone())
+                .unwrap_or_default(),
+            missing: thread
+                .map(|thread| thread.missing.iter().copied().collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl ChatClient {
+    /// Adds a received chat message to its conversation, reporting the
+    /// messages its sequence number shows were skipped. Duplicates are
+    /// discarded with a `ChatClientEvent::MessageDiscarded`.
+    ///
+    /// Messages with a sequence number are placed by it, the others by the
+    /// time they were sent, or arrived if `sent_at` is `None`.
+    ///
+    /// # Returns
+    ///
+    /// The times of the message if it is new, `None` if it was already
+    /// received.
+    pub(super) fn conversation_received(
+        &mut self,
+        peer: NodeId,
+        sequence: Option<u64>,
+        sent_at: Option<u64>,
+        content: String,
+    ) -> Option<MessageTimestamps> {
+        let thread = self.conversations.threads.entry(peer).or_default();
+        let received_at = now_millis();
+        let timestamps = MessageTimestamps {
+            sent_at,
+            received_at,
+            normalized: sent_at.map_or(received_at, |sent_at| {
+                thread.normalize(sent_at, received_at)
+            }),
+        };
+        let entry = ConversationEntry {
+            timestamp: timestamps.normalized,
+            direction: MessageDirection::Received,
+            sequence,
+            content,
+        };
+
+        let Some(sequence) = sequence else {
+            thread.insert_by_time(entry);
+            return Some(timestamps);
+        };
+
+        let expected = thread
+            .last_incoming
+            .map_or(0, |last| last.saturating_add(1));
+        let reset = thread
+            .last_incoming
+            .is_some_and(|last| sequence < last && last - sequence > MAX_GAP);
+        if reset || thread.last_incoming.is_none_or(|last| sequence > last) {
+            let gap: Vec<u64> = if reset || sequence - expected > MAX_GAP {
+                thread.missing.clear();
+                Vec::new()
+            } else {
+                (expected..sequence).collect()
+            };
+            thread.missing.extend(&gap);
+            thread.last_incoming = Some(sequence);
+            thread.entries.push(entry);
+
+            if reset {
+                info!(
+                    self,
+                    "{} [ {} ]: [ Client {} ] started its sequence over at {}",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    peer,
+                    sequence
+                );
+            }
+            if !gap.is_empty() {
+                warn!(
+                    self,
+                    "{} [ {} ]: Messages {:?} from [ Client {} ] are missing",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    gap,
+                    peer
+                );
+                self.send_event(ChatClientEvent::MessageGap(peer, gap));
+            }
+            Some(timestamps)
+        } else if thread.missing.remove(&sequence) {
+            // a late message goes before the received ones that overtook it
+            let position = thread
+                .entries
+                .iter()
+                .position(|entry| {
+                    entry.direction == MessageDirection::Received
+                        && entry.sequence.is_some_and(|other| other > sequence)
+                })
+                .unwrap_or(thread.entries.len());
+            thread.entries.insert(position, entry);
+            info!(
+                self,
+                "{} [ {} ]: Message {} from [ Client {} ] arrived out of order",
+                "ℹ".blue(),
+                self.log_tag,
+                sequence,
+                peer
+            );
+            Some(timestamps)
+        } else {
+            warn!(
+                self,
+                "{} [ {} ]: Ignoring duplicate message {} from [ Client {} ]",
+                "!!!".yellow(),
+                self.log_tag,
+                sequence,
+                peer
+            );
+            self.send_event(ChatClientEvent::MessageDiscarded(peer, sequence));
+            None
+        }
+    }
+
+    /// Reads a chat message carrying the time it was sent.
+    pub(super) fn timestamped_received(
+        &mut self,
+        sender_id: NodeId,
+        sent_at: u64,
+        content: String,
+    ) {
+        match Payload::decode(&content) {
+            Some(Payload::ChatMessage { sequence, content }) => {
+                self.sequenced_message_received(sender_id, sequence, Some(sent_at), content);
+            }
+            Some(payload) => self.read_payload(sender_id, payload),
+            None => self.chat_text_received(sender_id, Some(sent_at), content),
+        }
+    }
+
+    pub(super) fn sequenced_message_received(
+        &mut self,
+        sender_id: NodeId,
+        sequence: u64,
+        sent_at: Option<u64>,
+        content: String,
+    ) {
+        let Some(timestamps) =
+            self.conversation_received(sender_id, Some(sequence), sent_at, content.clone())
+        else {
+            return;
+        };
+
+        info!(
+            self,
+            "{} [ {} ]: Message {} received from [ Client {} ]: {}",
+            "✓".green(),
+            self.log_tag,
+            sequence,
+            sender_id,
+            content
+        );
+        self.count_unread(sender_id);
+        self.bot_think();
+        self.auto_reply(sender_id, &content);
+        self.history.record_at(
+            timestamps.normalized,
+            sender_id,
+            MessageDirection::Received,
+            content.clone(),
+        );
+        self.send_event(ChatClientEvent::MessageReceived(
+            sender_id, self.id, content, timestamps,
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{HashSet, VecDeque};
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+use wg_2024::{
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+use super::{send_window::Priority, ChatClient};
+
+/// Fragments handed to the send windows per turn of the event loop.
+const DISPATCH_BATCH: usize = 64;
+
+/// Fragments of the messages being sent that were not handed to their
+/// send window yet.
+///
+/// A large message is fragmented at once, but its fragments are released
+/// [`DISPATCH_BATCH`] at a time, each batch in a turn of the event loop of
+/// its own, so that commands and packets keep being handled in between.
+/// The loop is woken up through `wake_recv` while fragments are left.
+#[derive(Debug)]
+pub(super) struct FragmentDispatch {
+    queue: VecDeque<(NodeId, Packet, Priority)>,
+    wake_send: Sender<()>,
+    pub(super) wake_recv: Receiver<()>,
+}
+
+impl Default for FragmentDispatch {
+    fn default() -> Self {
+        let (wake_send, wake_recv) = bounded(1);
+        Self {
+            queue: VecDeque::new(),
+            wake_send,
+            wake_recv,
+        }
+    }
+}
+
+impl ChatClient {
+    /// Queues the fragments of a message toward `destination`, releasing
+    /// the first batch right away.
+    pub(super) fn dispatch(
+        &mut self,
+        destination: NodeId,
+        fragments: Vec<Packet>,
+        priority: Priority,
+    ) {
+        self.dispatch.queue.extend(
+            fragments
+                .into_iter()
+                .map(|packet| (destination, packet, priority)),
+        );
+        self.dispatch_fragments();
+    }
+
+    /// Hands the next batch of queued fragments to their send windows.
+    pub(super) fn dispatch_fragments(&mut self) {
+        for _ in 0..DISPATCH_BATCH {
+            let Some((destination, packet, priority)) = self.dispatch.queue.pop_front() else {
+                break;
+            };
+            self.send_windowed(destination, packet, priority);
+        }
+        if !self.dispatch.queue.is_empty() {
+            // a wake-up is already pending when the channel is full
+            let _ = self.dispatch.wake_send.try_send(());
+        }
+    }
+
+    /// Fragments still waiting to be handed to their send window.
+    pub(super) fn undispatched(&self) -> usize {
+        self.dispatch.queue.len()
+    }
+
+    /// Drops the queued fragments of a session, returning how many there
+    /// were.
+    pub(super) fn cancel_dispatched(&mut self, destination: NodeId, session_id: u64) -> usize {
+        let before = self.dispatch.queue.len();
+        self.dispatch
+            .queue
+            .retain(|(to, packet, _)| *to != destination || packet.session_id != session_id);
+        before - self.dispatch.queue.len()
+    }
+
+    /// Gives a new route to the queued fragments whose route starts with
+    /// `first_hop`, returning their session ids and fragment indexes.
+    pub(super) fn reroute_dispatched(&mut self, first_hop: NodeId) -> HashSet<(u64, u64)> {
+        let mut destinations: Vec<NodeId> = self
+            .dispatch
+            .queue
+            .iter()
+            .filter(|(_, packet, _)| packet.routing_header.hops.get(1) == Some(&first_hop))
+            .map(|&(destination, _, _)| destination)
+            .collect();
+        destinations.sort_unstable();
+        destinations.dedup();
+
+        let mut rerouted = HashSet::new();
+        for destination in destinations {
+            let route = self.route_to(destination);
+            for (to, packet, _) in &mut self.dispatch.queue {
+                if *to != destination || packet.routing_header.hops.get(1) != Some(&first_hop) {
+                    continue;
+                }
+                if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+                    rerouted.insert((packet.session_id, fragment.fragment_index));
+                }
+                if let Some(route) = &route {
+                    packet.routing_header = route.clone();
+                }
+            }
+        }
+        rerouted
+    }
+}
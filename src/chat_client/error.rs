@@ -0,0 +1,51 @@
+use std::{error::Error, fmt};
+
+use wg_2024::network::NodeId;
+
+/// Why the `ChatClient` could not carry out an operation.
+///
+/// The event loop never stops on one of these: the handler of the packet
+/// or command that ran into it logs it, and tells the controller with the
+/// event the situation calls for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatClientError {
+    /// No route to the node is known.
+    RoutingFailed(NodeId),
+    /// The neighbour a packet had to be handed to is not connected, or
+    /// its channel is closed.
+    NeighborUnavailable(NodeId),
+    /// The operation needs a registration to a communication server.
+    NotRegistered,
+    /// The operation needs the client to be started.
+    NotRunning,
+    /// A packet cannot be sent or handled as it is.
+    MalformedPacket(String),
+    /// A message is longer than the maximum message size.
+    MessageTooLarge { size: usize, limit: usize },
+    /// The controller disconnected, so a packet could not be handed to it.
+    ControllerGone,
+    /// The persona is already hosted, or is the main persona.
+    PersonaExists(u16),
+    /// The persona is not hosted by this client.
+    UnknownPersona(u16),
+}
+
+impl fmt::Display for ChatClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RoutingFailed(node_id) => write!(f, "no route to node {node_id}"),
+            Self::NeighborUnavailable(node_id) => write!(f, "neighbour {node_id} is unavailable"),
+            Self::NotRegistered => write!(f, "the client is not registered to any server"),
+            Self::NotRunning => write!(f, "the client is not running"),
+            Self::MalformedPacket(reason) => write!(f, "malformed packet: {reason}"),
+            Self::MessageTooLarge { size, limit } => {
+                write!(f, "message of {size} bytes exceeds {limit} bytes")
+            }
+            Self::ControllerGone => write!(f, "the controller disconnected"),
+            Self::PersonaExists(persona) => write!(f, "persona {persona} already exists"),
+            Self::UnknownPersona(persona) => write!(f, "persona {persona} is not hosted"),
+        }
+    }
+}
+
+impl Error for ChatClientError {}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+
+use super::ChatClient;
+
+/// The events recently sent to the controller, numbered in the order they
+/// were sent so that a controller that missed some can ask for them again.
+#[derive(Debug, Default)]
+pub(super) struct EventLog {
+    next_seq: u64,
+    recent: VecDeque<(u64, ChatClientEvent)>,
+}
+
+impl ChatClient {
+    /// Sends `event` to the controller, numbered and kept for
+    /// `ChatClientCommand::ReplayEvents` when event replay is enabled.
+    ///
+    /// If the controller dropped its receiver the event is discarded, and the
+    /// run loop stops at its next iteration instead of the client panicking.
+    pub(super) fn send_event(&self, event: ChatClientEvent) {
+        let Some(capacity) = self.config.event_replay else {
+            self.deliver_event(event);
+            return;
+        };
+
+        let seq = {
+            let mut log = self.event_log.borrow_mut();
+            let seq = log.next_seq;
+            log.next_seq += 1;
+            if log.recent.len() == capacity {
+                log.recent.pop_front();
+            }
+            log.recent.push_back((seq, event.clone()));
+            seq
+        };
+        self.deliver_event(ChatClientEvent::Sequenced {
+            seq,
+            event: Box::new(event),
+        });
+    }
+
+    /// Sends again the kept events numbered `from_seq` or later, preceded
+    /// by a `ChatClientEvent::EventsMissed` if some of them were already
+    /// forgotten.
+    pub(super) fn replay_events(&self, from_seq: u64) {
+        let (replayed, next_seq): (Vec<(u64, ChatClientEvent)>, u64) = {
+            let log = self.event_log.borrow();
+            let replayed = log
+                .recent
+                .iter()
+                .filter(|&&(seq, _)| seq >= from_seq)
+                .cloned()
+                .collect();
+            (replayed, log.next_seq)
+        };
+        let oldest = replayed.first().map_or(next_seq, |&(seq, _)| seq);
+
+        info!(
+            self,
+            "{} [ {} ]: Replaying {} events from {}",
+            "ℹ".blue(),
+            self.log_tag,
+            replayed.len(),
+            from_seq
+        );
+        if from_seq < oldest {
+            self.deliver_event(ChatClientEvent::EventsMissed {
+                from: from_seq,
+                to: oldest,
+            });
+        }
+        for (seq, event) in replayed {
+            self.deliver_event(ChatClientEvent::Sequenced {
+                seq,
+                event: Box::new(event),
+            });
+        }
+    }
+
+    fn deliver_event(&self, event: ChatClientEvent) {
+        if self.controller_send.send(event).is_err() && !self.controller_gone.replace(true) {
+            error!(
+                self,
+                "{} [ {} ]: The controller disconnected, shutting down",
+                "✗".red(),
+                self.log_tag
+            );
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+use crossbeam_channel::Sender;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{NodeType, Packet, PacketType},
+};
+
+use super::ChatClient;
+
+impl ChatClient {
+    /// Whether `packet` originates here and is about to take its first hop.
+    pub(super) fn is_first_hop(&self, packet: &Packet) -> bool {
+        packet.routing_header.hop_index == 1 && packet.routing_header.hops.first() == Some(&self.id)
+    }
+
+    /// Removes a neighbour, as `ChatClientCommand::RemoveSender` does, and
+    /// moves the fragments routed through it onto new routes.
+    pub(super) fn remove_neighbor(&mut self, neighbor: NodeId) {
+        self.packet_send.remove(&neighbor);
+        self.router.remove_neighbour(neighbor);
+        self.invalidate_routes();
+        self.topology.remove_link(self.id, neighbor);
+        self.reroute_around(neighbor);
+    }
+
+    /// Swaps the channel to a neighbour, as
+    /// `ChatClientCommand::ReplaceSender` does, connecting it if it was not.
+    ///
+    /// The link is no longer considered down and the routes are computed
+    /// again, so that none keeps avoiding the neighbour.
+    pub(super) fn replace_neighbor(&mut self, neighbor: NodeId, sender: Sender<Packet>) {
+        let replaced = self.packet_send.insert(neighbor, sender).is_some();
+        if !replaced {
+            self.router.add_neighbour(neighbor);
+        }
+        let link = [(self.id, NodeType::Client), (neighbor, NodeType::Drone)];
+        self.topology.add_link(link[0], link[1]);
+        self.links_seen(&link);
+        self.invalidate_routes();
+
+        info!(
+            self,
+            "{} [ {} ]: {} the channel to [ Drone {} ]",
+            "✓".green(),
+            self.log_tag,
+            if replaced { "Replaced" } else { "Added" },
+            neighbor
+        );
+    }
+
+    /// Treats the neighbour whose channel just closed as gone, until an
+    /// `AddSender` or a `ReplaceSender` connects it again.
+    ///
+    /// Sends only fail once the receiving end is dropped, so a single
+    /// failure is enough to tell the neighbour will never answer again.
+    pub(super) fn neighbor_down(&mut self, neighbor: NodeId) {
+        warn!(
+            self,
+            "{} [ {} ]: The channel to [ Drone {} ] is closed, removing it",
+            "!!!".yellow(),
+            self.log_tag,
+            neighbor
+        );
+        self.remove_neighbor(neighbor);
+        self.send_event(ChatClientEvent::NeighborDown(neighbor));
+    }
+
+    /// Resends `packet`, whose first hop just went down, through another
+    /// neighbour, if any of them leads to its destination.
+    ///
+    /// # Returns
+    ///
+    /// Whether the packet was sent through another neighbour, cached
+    /// fragments having already been moved by [`Self::neighbor_down`].
+    pub(super) fn fail_over(&mut self, packet: &Packet) -> bool {
+        if let (PacketType::MsgFragment(fragment), Some(peer)) =
+            (&packet.pack_type, packet.routing_header.destination())
+        {
+            if self
+                .packet_cache
+                .route_of(peer, packet.session_id, fragment.fragment_index)
+                .is_some()
+            {
+                return true;
+            }
+        }
+
+        let Some(hops) = self.failover_hops(&packet.routing_header.hops) else {
+            error!(
+                self,
+                "{} [ {} ]: No other neighbour leads to the destination of the {}",
+                "✗".red(),
+                self.log_tag,
+                packet.pack_type
+            );
+            return false;
+        };
+
+        info!(
+            self,
+            "{} [ {} ]: Failing over to [ Drone {} ] for the {}",
+            "ℹ".blue(),
+            self.log_tag,
+            hops[1],
+            packet.pack_type
+        );
+        let packet = Packet {
+            routing_header: SourceRoutingHeader { hop_index: 1, hops },
+            ..packet.clone()
+        };
+        self.forward_packet(packet).is_ok()
+    }
+
+    /// The most reliable known route to the destination of `hops` through
+    /// each of the remaining neighbours, the shortest one among equals.
+    fn failover_hops(&self, hops: &[NodeId]) -> Option<Vec<NodeId>> {
+        let destination = *hops.last()?;
+        let excluded = HashSet::new();
+
+        self.packet_send
+            .keys()
+            .filter_map(|&neighbour| {
+                let path = self
+                    .topology
+                    .path_avoiding(neighbour, destination, &excluded)?;
+                Some([vec![self.id], path].concat())
+            })
+            .max_by(|a, b| {
+                self.topology
+                    .route_reliability(a)
+                    .total_cmp(&self.topology.route_reliability(b))
+                    .then(b.len().cmp(&a.len()))
+            })
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use wg_2024::packet::{Packet, PacketType};
+
+use super::{time_scale::rescaled, ChatClient};
+
+/// Faults the controller asked the client to commit, so that scenarios can
+/// check how drones and servers cope with a misbehaving client.
+#[derive(Debug, Default)]
+pub(super) struct FaultInjection {
+    drop_next_ack: bool,
+    corrupt_next_fragment: bool,
+    delay: Option<Duration>,
+    /// Packets held back by the delay, with the time they are sent at.
+    delayed: VecDeque<(Instant, Packet)>,
+}
+
+impl FaultInjection {
+    pub(super) fn rescale(&mut self, now: Instant, ratio: f64) {
+        for (due, _) in &mut self.delayed {
+            *due = rescaled(*due, now, ratio);
+        }
+    }
+}
+
+impl ChatClient {
+    pub(super) fn inject_drop_next_ack(&mut self) {
+        warn!(
+            self,
+            "{} [ {} ]: Fault injection: the next Ack will not be sent",
+            "!!!".yellow(),
+            self.log_tag
+        );
+        self.faults.drop_next_ack = true;
+    }
+
+    pub(super) fn inject_corrupt_fragment(&mut self) {
+        warn!(
+            self,
+            "{} [ {} ]: Fault injection: the next fragment sent will be corrupted",
+            "!!!".yellow(),
+            self.log_tag
+        );
+        self.faults.corrupt_next_fragment = true;
+    }
+
+    /// Delays every packet sent by `millis` milliseconds, `0` sends them
+    /// right away again. Delayed packets are held back and sent by the
+    /// tick, so the event loop keeps running meanwhile.
+    pub(super) fn inject_delay(&mut self, millis: u64) {
+        warn!(
+            self,
+            "{} [ {} ]: Fault injection: delaying every packet sent by {} ms",
+            "!!!".yellow(),
+            self.log_tag,
+            millis
+        );
+        self.faults.delay = (millis > 0).then(|| Duration::from_millis(millis));
+    }
+
+    /// Whether the Ack about to be sent is dropped instead.
+    pub(super) fn ack_dropped(&mut self) -> bool {
+        std::mem::take(&mut self.faults.drop_next_ack)
+    }
+
+    /// Applies the injected faults to a packet about to be sent.
+    ///
+    /// # Returns
+    ///
+    /// The packet to send now, `None` if it is delayed.
+    pub(super) fn perturb(&mut self, mut packet: Packet) -> Option<Packet> {
+        if let Some(delay) = self.faults.delay {
+            let due = Instant::now() + self.scaled(delay);
+            self.faults.delayed.push_back((due, packet));
+            return None;
+        }
+
+        if let PacketType::MsgFragment(fragment) = &mut packet.pack_type {
+            if std::mem::take(&mut self.faults.corrupt_next_fragment) {
+                let length = usize::from(fragment.length).clamp(1, fragment.data.len());
+                for byte in &mut fragment.data[..length] {
+                    *byte ^= 0xFF;
+                }
+            }
+        }
+        Some(packet)
+    }
+
+    /// Sends the delayed packets whose delay elapsed.
+    pub(super) fn release_delayed_packets(&mut self) {
+        let now = Instant::now();
+        while let Some(&(due, _)) = self.faults.delayed.front() {
+            if due > now {
+                break;
+            }
+            let Some((_, packet)) = self.faults.delayed.pop_front() else {
+                break;
+            };
+            // failures were Nacked or handed to the controller
+            let _ = self.send_to_next_hop(packet);
+        }
+    }
+}
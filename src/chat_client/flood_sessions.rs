@@ -0,0 +1,103 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Instant,
+};
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, FloodSummary};
+use wg_2024::{network::NodeId, packet::FloodResponse};
+
+use super::ChatClient;
+
+/// How a flood started by the client went so far.
+#[derive(Debug)]
+struct FloodSession {
+    started: Instant,
+    last_response: Option<Instant>,
+    responses: usize,
+    /// Nodes the client did not know of before this flood revealed them.
+    new_nodes: BTreeSet<NodeId>,
+}
+
+/// Every flood the client started whose responses are still arriving,
+/// by flood id, so that concurrent floods are told apart.
+///
+/// A flood is complete once no response arrived for the configured flood
+/// interval, and is then reported with `ChatClientEvent::FloodCompleted`.
+#[derive(Debug, Default)]
+pub(super) struct FloodSessions {
+    active: HashMap<u64, FloodSession>,
+}
+
+impl FloodSessions {
+    pub(super) fn started(&mut self, flood_id: u64) {
+        self.active.insert(
+            flood_id,
+            FloodSession {
+                started: Instant::now(),
+                last_response: None,
+                responses: 0,
+                new_nodes: BTreeSet::new(),
+            },
+        );
+    }
+}
+
+impl ChatClient {
+    /// Accounts for a response to one of the client's floods. Must be
+    /// called before the topology learns from its path trace.
+    pub(super) fn correlate_flood_response(&mut self, flood_response: &FloodResponse) {
+        let topology = &self.topology;
+        let Some(session) = self.flood_sessions.active.get_mut(&flood_response.flood_id) else {
+            return;
+        };
+
+        session.last_response = Some(Instant::now());
+        session.responses += 1;
+        session.new_nodes.extend(
+            flood_response
+                .path_trace
+                .iter()
+                .map(|&(node_id, _)| node_id)
+                .filter(|&node_id| node_id != self.id && !topology.contains(node_id)),
+        );
+    }
+
+    /// Reports the floods whose responses stopped arriving.
+    pub(super) fn check_flood_sessions(&mut self) {
+        let quiet_for = self.scaled(self.config.flood_interval);
+        let completed: Vec<u64> = self
+            .flood_sessions
+            .active
+            .iter()
+            .filter(|(_, session)| {
+                session.last_response.unwrap_or(session.started).elapsed() >= quiet_for
+            })
+            .map(|(&flood_id, _)| flood_id)
+            .collect();
+
+        for flood_id in completed {
+            let Some(session) = self.flood_sessions.active.remove(&flood_id) else {
+                continue;
+            };
+            let summary = FloodSummary {
+                responses: session.responses,
+                new_nodes: session.new_nodes.into_iter().collect(),
+                duration: session
+                    .last_response
+                    .map_or_else(Default::default, |last| last - session.started),
+            };
+            info!(
+                self,
+                "{} [ {} ]: Flood {} completed with {} responses in {:?}, revealing {} new nodes",
+                "ℹ".blue(),
+                self.log_tag,
+                flood_id,
+                summary.responses,
+                summary.duration,
+                summary.new_nodes.len()
+            );
+            self.send_event(ChatClientEvent::FloodCompleted(flood_id, summary));
+        }
+    }
+}
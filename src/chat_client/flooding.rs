@@ -0,0 +1,291 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::{client_commands::TelemetryKind, high_level_messages::MessageContent};
+use wg_2024::{
+    network::NodeId,
+    packet::{FloodRequest, FloodResponse, Packet, PacketType},
+};
+
+use super::ChatClient;
+
+/// How long a `(flood_id, initiator_id)` pair is remembered. Duplicates of
+/// a `FloodRequest` arriving later are answered again.
+const FLOOD_REQUEST_MEMORY: Duration = Duration::from_secs(30);
+
+/// A flood started by the client to rebuild its view of the network.
+///
+/// Packets that should be retransmitted while the flood is in progress are
+/// held back until a response reveals a route to their destination, so that
+/// they are routed on the new topology. Whatever is still held is resent
+/// when as many distinct responses as requests sent have arrived, or when
+/// the configured flood interval expires.
+#[derive(Debug)]
+pub(super) struct FloodState {
+    flood_ids: HashSet<u64>,
+    started: Instant,
+    /// The distinct responses received, by flood id and path trace.
+    responses: HashSet<(u64, Vec<NodeId>)>,
+    deferred: Vec<Packet>,
+    /// Messages that had no route to their destination when sent.
+    unroutable: Vec<(MessageContent, NodeId)>,
+    /// Whether the servers are queried once the flood completes.
+    query_servers: bool,
+}
+
+impl ChatClient {
+    pub(super) fn start_flood(&mut self) {
+        if self.flood.is_some() {
+            return;
+        }
+
+        let flood_ids = self.send_flood_requests();
+        self.flood = Some(FloodState {
+            flood_ids,
+            started: Instant::now(),
+            responses: HashSet::new(),
+            deferred: Vec::new(),
+            unroutable: Vec::new(),
+            query_servers: false,
+        });
+    }
+
+    /// Floods the network anew after its topology changed. A flood already
+    /// in progress restarts its timeout and also waits for the responses
+    /// to the new requests, instead of the event loop pausing for them.
+    pub(super) fn reflood(&mut self) {
+        if self.flood.is_none() {
+            self.start_flood();
+            return;
+        }
+
+        let flood_ids = self.send_flood_requests();
+        if let Some(flood) = &mut self.flood {
+            flood.flood_ids.extend(flood_ids);
+            flood.started = Instant::now();
+        }
+    }
+
+    /// Sends a `FloodRequest` to every neighbour, tracking each flood
+    /// until its responses stop arriving.
+    ///
+    /// # Returns
+    ///
+    /// The ids of the floods started.
+    pub(super) fn send_flood_requests(&mut self) -> HashSet<u64> {
+        let requests = self.router.get_flood_requests(self.packet_send.len());
+        self.stats.floods_initiated += 1;
+        self.emit_telemetry(TelemetryKind::FloodStarted {
+            requests: requests.len(),
+        });
+        let flood_ids: HashSet<u64> = requests
+            .iter()
+            .filter_map(|request| match &request.pack_type {
+                PacketType::FloodRequest(flood_request) => Some(flood_request.flood_id),
+                _ => None,
+            })
+            .collect();
+
+        for (sender, request) in self.packet_send.values().zip(requests) {
+            if sender.send(request).is_err() {
+                error!(
+                    self,
+                    "{} [ {} ]: Failed to send floodrequest",
+                    "✗".red(),
+                    self.log_tag
+                );
+            }
+        }
+
+        for &flood_id in &flood_ids {
+            self.flood_sessions.started(flood_id);
+        }
+        flood_ids
+    }
+
+    pub(super) fn is_flooding(&self) -> bool {
+        self.flood.is_some()
+    }
+
+    pub(super) fn defer_until_flooded(&mut self, packet: Packet) {
+        if let Some(flood) = &mut self.flood {
+            info!(
+                self,
+                "{} [ {} ]: Holding back packet with session_id: {} until the flood completes",
+                "ℹ".blue(),
+                self.log_tag,
+                packet.session_id
+            );
+            flood.deferred.push(packet);
+        }
+    }
+
+    /// Floods the network, then sends `message_content` to `destination`
+    /// if the flood found a route to it.
+    pub(super) fn send_after_flood(
+        &mut self,
+        message_content: MessageContent,
+        destination: NodeId,
+    ) {
+        self.start_flood();
+        if let Some(flood) = &mut self.flood {
+            flood.unroutable.push((message_content, destination));
+        }
+    }
+
+    /// Floods the network, then queries the servers it discovered.
+    pub(super) fn discover_servers(&mut self) {
+        self.start_flood();
+        if let Some(flood) = &mut self.flood {
+            flood.query_servers = true;
+        }
+    }
+
+    pub(super) fn cancel_deferred(&mut self, destination: NodeId, session_id: u64) {
+        if let Some(flood) = &mut self.flood {
+            flood.deferred.retain(|packet| {
+                packet.session_id != session_id
+                    || packet.routing_header.destination() != Some(destination)
+            });
+        }
+    }
+
+    pub(super) fn flood_response_received(&mut self, flood_response: &FloodResponse) {
+        let Some(flood) = &mut self.flood else {
+            return;
+        };
+
+        if !flood.flood_ids.contains(&flood_response.flood_id) {
+            return;
+        }
+        let path = flood_response
+            .path_trace
+            .iter()
+            .map(|&(node_id, _)| node_id)
+            .collect();
+        if !flood.responses.insert((flood_response.flood_id, path)) {
+            return;
+        }
+
+        if flood.responses.len() >= flood.flood_ids.len() {
+            self.finish_flood();
+        } else {
+            self.resend_routable();
+        }
+    }
+
+    /// Resends what the flood holds back toward the destinations it already
+    /// found a route to.
+    fn resend_routable(&mut self) {
+        let Some(flood) = &mut self.flood else {
+            return;
+        };
+        let deferred = std::mem::take(&mut flood.deferred);
+        let unroutable = std::mem::take(&mut flood.unroutable);
+
+        let (ready, held): (Vec<Packet>, Vec<Packet>) = deferred.into_iter().partition(|packet| {
+            packet
+                .routing_header
+                .destination()
+                .is_some_and(|destination| self.route_to(destination).is_some())
+        });
+        let (sendable, still_unroutable): (Vec<_>, Vec<_>) = unroutable
+            .into_iter()
+            .partition(|(_, destination)| self.route_to(*destination).is_some());
+        if let Some(flood) = &mut self.flood {
+            flood.deferred = held;
+            flood.unroutable = still_unroutable;
+        }
+
+        if ready.is_empty() && sendable.is_empty() {
+            return;
+        }
+        info!(
+            self,
+            "{} [ {} ]: Found routes during the flood, resending {} held back packets and {} messages",
+            "ℹ".blue(),
+            self.log_tag,
+            ready.len(),
+            sendable.len()
+        );
+        for packet in ready {
+            self.reroute_packet(packet);
+        }
+        for (message_content, destination) in sendable {
+            let _ = self.generate_and_send_message(message_content, destination);
+        }
+    }
+
+    pub(super) fn check_flood_timeout(&mut self) {
+        if self
+            .flood
+            .as_ref()
+            .is_some_and(|flood| flood.started.elapsed() >= self.scaled(self.config.flood_interval))
+        {
+            self.finish_flood();
+        }
+    }
+
+    fn finish_flood(&mut self) {
+        let Some(flood) = self.flood.take() else {
+            return;
+        };
+
+        info!(
+            self,
+            "{} [ {} ]: Flood completed with {} responses, resending {} held back packets",
+            "ℹ".blue(),
+            self.log_tag,
+            flood.responses.len(),
+            flood.deferred.len()
+        );
+
+        self.emit_telemetry(TelemetryKind::FloodCompleted {
+            responses: flood.responses.len(),
+        });
+
+        for packet in flood.deferred {
+            self.reroute_packet(packet);
+        }
+
+        for (message_content, destination) in flood.unroutable {
+            if self.route_to(destination).is_some() {
+                let _ = self.generate_and_send_message(message_content, destination);
+            } else {
+                error!(
+                    self,
+                    "{} [ {} ]: Still no path to destination [ Server {} ] after flooding",
+                    "✗".red(),
+                    self.log_tag,
+                    destination
+                );
+                self.routing_failed(destination);
+            }
+        }
+
+        if flood.query_servers {
+            self.query_communication_servers();
+        }
+    }
+
+    /// Whether `flood_request` is the first one received for its flood,
+    /// remembering it if so.
+    pub(super) fn first_flood_request(&mut self, flood_request: &FloodRequest) -> bool {
+        let memory = self.scaled(FLOOD_REQUEST_MEMORY);
+        self.flood_requests_seen
+            .insert(
+                (flood_request.flood_id, flood_request.initiator_id),
+                Instant::now(),
+            )
+            .is_none_or(|seen| seen.elapsed() >= memory)
+    }
+
+    pub(super) fn prune_flood_requests_seen(&mut self) {
+        let memory = self.scaled(FLOOD_REQUEST_MEMORY);
+        self.flood_requests_seen
+            .retain(|_, seen| seen.elapsed() < memory);
+    }
+}
@@ -0,0 +1,309 @@
+use std::fmt;
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+use crate::chat_client::{config::TimeScale, error::ChatClientError, personas::MAIN_PERSONA};
+
+/// Why a command was rejected.
+enum Rejection {
+    /// The command needs the client to be started.
+    NotRunning,
+    /// The command needs a registration to a communication server.
+    NotRegistered,
+    /// The recipient is blocked.
+    Blocked(NodeId),
+    /// The message is longer than the maximum message size.
+    TooLarge { client_id: NodeId, size: usize },
+    /// The recipient is not a client of the registered server.
+    Unreachable(NodeId),
+    /// The server is not a known communication server.
+    NotACommunicationServer(NodeId),
+    /// A reason the controller has no dedicated event for.
+    Other(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRunning => write!(f, "{}", ChatClientError::NotRunning),
+            Self::NotRegistered => write!(f, "{}", ChatClientError::NotRegistered),
+            Self::Blocked(client_id) => write!(f, "client {client_id} is blocked"),
+            Self::TooLarge { size, .. } => write!(f, "message of {size} bytes is too large"),
+            Self::Unreachable(client_id) => write!(f, "client {client_id} is unreachable"),
+            Self::NotACommunicationServer(server_id) => {
+                write!(f, "{server_id} is not a known communication server")
+            }
+            Self::Other(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl ChatClient {
+    /// Tells the controller whether `command` can be carried out, tagging
+    /// the answer with the correlation id the command was wrapped with.
+    ///
+    /// A rejection is also reported with the event dedicated to its reason,
+    /// if there is one.
+    ///
+    /// # Returns
+    ///
+    /// Whether the command was accepted.
+    pub(super) fn acknowledge(&self, id: Option<u64>, command: &ChatClientCommand) -> bool {
+        let Some(rejection) = self.rejection(command) else {
+            self.send_event(ChatClientEvent::CommandAccepted { id });
+            return true;
+        };
+
+        let reason = rejection.to_string();
+        warn!(
+            self,
+            "{} [ {} ]: Rejected command with id {:?}: {}",
+            "!!!".yellow(),
+            self.log_tag,
+            id,
+            reason
+        );
+        self.send_event(ChatClientEvent::CommandRejected {
+            id,
+            reason: reason.clone(),
+        });
+        self.report_rejection(command, &rejection, reason);
+        false
+    }
+
+    /// Sends the controller the event dedicated to `rejection`.
+    fn report_rejection(&self, command: &ChatClientCommand, rejection: &Rejection, reason: String) {
+        let context = self.command_context(command);
+        match *rejection {
+            Rejection::NotRunning => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot execute {}, ChatClient is not running",
+                    "✗".red(),
+                    self.log_tag,
+                    context.command
+                );
+                self.send_event(ChatClientEvent::ErrorNotRunning(context.clone()));
+                self.command_failed(&context, reason);
+            }
+            Rejection::NotRegistered => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot execute {}, not registered to any server",
+                    "✗".red(),
+                    self.log_tag,
+                    context.command
+                );
+                self.send_event(ChatClientEvent::ErrorNotRegistered(context.clone()));
+                self.command_failed(&context, reason);
+            }
+            Rejection::Blocked(client_id) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot send to [ Client {} ], it is blocked",
+                    "✗".red(),
+                    self.log_tag,
+                    client_id
+                );
+                self.send_event(ChatClientEvent::ClientBlocked(client_id));
+            }
+            Rejection::TooLarge { client_id, size } => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot send message of {} bytes, the limit is {:?}",
+                    "✗".red(),
+                    self.log_tag,
+                    size,
+                    self.config.max_message_size
+                );
+                self.send_event(ChatClientEvent::MessageTooLarge(client_id, size));
+            }
+            Rejection::Unreachable(client_id) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot send message, destination client {} is unreachable",
+                    "✗".red(),
+                    self.log_tag,
+                    client_id
+                );
+                self.send_event(ChatClientEvent::UnreachableClient(client_id));
+                self.command_failed(&context, reason);
+            }
+            Rejection::NotACommunicationServer(server_id) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Cannot register to server {}, it is not a communication server, communication_server_list: {:?}",
+                    "✗".red(),
+                    self.log_tag,
+                    server_id,
+                    self.communication_server_list.ids()
+                );
+                self.command_failed(&context, reason);
+            }
+            Rejection::Other(_) => {}
+        }
+    }
+
+    fn rejection(&self, command: &ChatClientCommand) -> Option<Rejection> {
+        let needs_running = matches!(
+            command,
+            ChatClientCommand::SendMessageTo(..)
+                | ChatClientCommand::SendRichMessageTo(..)
+                | ChatClientCommand::SendAsPersona { .. }
+                | ChatClientCommand::RegisterTo(_)
+                | ChatClientCommand::RegisterToBest
+                | ChatClientCommand::Ping(_)
+                | ChatClientCommand::GetClientList
+                | ChatClientCommand::LogOut
+                | ChatClientCommand::CreateRoom(_)
+                | ChatClientCommand::JoinRoom(_)
+                | ChatClientCommand::LeaveRoom(_)
+                | ChatClientCommand::SendRoomMessage(..)
+                | ChatClientCommand::SendFileTo(..)
+                | ChatClientCommand::SetStatus(_)
+                | ChatClientCommand::NotifyTyping(_)
+                | ChatClientCommand::SetUsername(_)
+        );
+        let needs_registration = matches!(
+            command,
+            ChatClientCommand::GetClientList
+                | ChatClientCommand::LogOut
+                | ChatClientCommand::SendRichMessageTo(..)
+                | ChatClientCommand::SendAsPersona { .. }
+                | ChatClientCommand::JoinRoom(_)
+                | ChatClientCommand::LeaveRoom(_)
+                | ChatClientCommand::SendRoomMessage(..)
+                | ChatClientCommand::SendFileTo(..)
+                | ChatClientCommand::NotifyTyping(_)
+        );
+
+        let injects_fault = matches!(
+            command,
+            ChatClientCommand::InjectDropNextAck
+                | ChatClientCommand::InjectCorruptFragment
+                | ChatClientCommand::InjectDelay(_)
+        );
+        if injects_fault && cfg!(not(feature = "fault-injection")) {
+            return Some(Rejection::Other(
+                "fault injection is not enabled".to_string(),
+            ));
+        }
+        if needs_running && !self.running {
+            return Some(Rejection::NotRunning);
+        }
+        if needs_registration && self.registered.is_none() {
+            return Some(Rejection::NotRegistered);
+        }
+
+        match command {
+            ChatClientCommand::SendMessageTo(client_id, _)
+            | ChatClientCommand::SendRichMessageTo(client_id, _)
+            | ChatClientCommand::SendFileTo(client_id, _)
+            | ChatClientCommand::NotifyTyping(client_id)
+            | ChatClientCommand::SendAsPersona {
+                recipient: client_id,
+                ..
+            } if self.is_blocked(*client_id) => Some(Rejection::Blocked(*client_id)),
+            ChatClientCommand::SendMessageTo(client_id, text)
+                if self.is_oversized(text) && !self.config.chunk_oversized =>
+            {
+                Some(Rejection::TooLarge {
+                    client_id: *client_id,
+                    size: text.len(),
+                })
+            }
+            ChatClientCommand::SendMessageTo(client_id, _)
+            | ChatClientCommand::SendRichMessageTo(client_id, _)
+                if self.registered.is_some() && !self.peers.contains(*client_id) =>
+            {
+                Some(Rejection::Unreachable(*client_id))
+            }
+            ChatClientCommand::RegisterTo(server_id)
+                if !self.communication_server_list.contains(*server_id) =>
+            {
+                Some(Rejection::NotACommunicationServer(*server_id))
+            }
+            _ => self.rejection_reason(command).map(Rejection::Other),
+        }
+    }
+
+    /// Why `command` is rejected, for the reasons with no event of their
+    /// own.
+    fn rejection_reason(&self, command: &ChatClientCommand) -> Option<String> {
+        match command {
+            ChatClientCommand::AddSender(node_id, _) if self.packet_send.contains_key(node_id) => {
+                Some(format!("already connected to drone {node_id}"))
+            }
+            ChatClientCommand::RemoveSender(node_id) if !self.packet_send.contains_key(node_id) => {
+                Some(format!("not connected to drone {node_id}"))
+            }
+            ChatClientCommand::GetSendProgress(message_id)
+                if !self.progress.contains(*message_id) =>
+            {
+                Some(format!("message {message_id} is not in flight"))
+            }
+            ChatClientCommand::AddPersona(persona, _) if self.has_persona(*persona) => {
+                Some(format!("persona {persona} already exists"))
+            }
+            ChatClientCommand::RemovePersona(persona) | ChatClientCommand::GetPersona(persona)
+                if *persona == MAIN_PERSONA || !self.has_persona(*persona) =>
+            {
+                Some(format!("persona {persona} is not hosted"))
+            }
+            ChatClientCommand::SendAsPersona { persona, .. } if !self.has_persona(*persona) => {
+                Some(format!("persona {persona} is not hosted"))
+            }
+            ChatClientCommand::MarkRead(client_id) if !self.peers.contains(*client_id) => {
+                Some(format!("client {client_id} is not known"))
+            }
+            ChatClientCommand::SetTimeScale(factor) if TimeScale::new(*factor).is_none() => {
+                Some(format!("invalid time scale {factor}"))
+            }
+            ChatClientCommand::UnblockClient(client_id) if !self.is_blocked(*client_id) => {
+                Some(format!("client {client_id} is not blocked"))
+            }
+            ChatClientCommand::Ping(node_id) if !self.is_probeable(*node_id) => Some(format!(
+                "{node_id} is neither a known server nor a known client"
+            )),
+            ChatClientCommand::ReplayEvents(_) if self.config.event_replay.is_none() => {
+                Some("event replay is not enabled".to_string())
+            }
+            ChatClientCommand::StartBot | ChatClientCommand::StopBot if !self.has_bot() => {
+                Some("no bot profile is configured".to_string())
+            }
+            ChatClientCommand::RegisterToBest
+                if self.communication_server_list.ids().is_empty() =>
+            {
+                Some("no communication server is known".to_string())
+            }
+            ChatClientCommand::CancelMessage(message_id)
+                if self.packet_cache.peers_of(*message_id).is_empty() =>
+            {
+                Some(format!("message {message_id} is not in flight"))
+            }
+            ChatClientCommand::PinRoute(destination, hops) => {
+                self.pinned_route_problem(*destination, hops)
+            }
+            ChatClientCommand::UnpinRoute(destination) if !self.is_pinned(*destination) => {
+                Some(format!("no route to {destination} is pinned"))
+            }
+            ChatClientCommand::BlacklistNode(node_id) if *node_id == self.id => {
+                Some("the client cannot blacklist itself".to_string())
+            }
+            ChatClientCommand::BlacklistNode(node_id) if self.is_blacklisted(*node_id) => {
+                Some(format!("{node_id} is already blacklisted"))
+            }
+            ChatClientCommand::WhitelistNode(node_id) if !self.is_blacklisted(*node_id) => {
+                Some(format!("{node_id} is not blacklisted"))
+            }
+            ChatClientCommand::TraceMessage(_) if !self.traces.is_enabled() => {
+                Some("tracing is not enabled".to_string())
+            }
+            ChatClientCommand::WithId(..) => Some("correlation ids cannot be nested".to_string()),
+            _ => None,
+        }
+    }
+}
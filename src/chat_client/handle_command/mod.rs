@@ -1,202 +1,302 @@
 use colored::Colorize;
-use log::{error, info, warn};
 use messages::{
     client_commands::{ChatClientCommand, ChatClientEvent},
     high_level_messages::{ClientMessage, MessageContent},
 };
-use std::{thread, time::Duration};
+use wg_2024::packet::NodeType;
 
-use super::ChatClient;
+use super::{config::TimeScale, control_requests::ControlRequest, ChatClient};
 
+mod acknowledge;
 mod send_message;
 
 impl ChatClient {
-    #[allow(clippy::too_many_lines)]
+    /// Acknowledges `command` to the controller, then executes it if it
+    /// was accepted.
     pub(super) fn handle_command(&mut self, command: ChatClientCommand) {
+        let (id, command) = match command {
+            ChatClientCommand::WithId(id, command) => (Some(id), *command),
+            command => (None, command),
+        };
+        if self.acknowledge(id, &command) {
+            self.execute_command(command);
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn execute_command(&mut self, command: ChatClientCommand) {
+        let context = self.command_context(&command);
         match command {
             ChatClientCommand::AddSender(node_id, sender) => {
-                if let std::collections::hash_map::Entry::Vacant(e) =
-                    self.packet_send.entry(node_id)
-                {
-                    info!(
-                        "{} Adding sender: {} to [ ChatClient {} ]",
-                        "✓".green(),
-                        node_id,
-                        self.id,
-                    );
-                    e.insert(sender);
-                    self.router.add_neighbour(node_id);
-                } else {
-                    warn!(
-                        "{} [ ChatClient {} ] is already connected to [ Drone {} ]",
-                        "!!!".yellow(),
-                        self.id,
-                        node_id
-                    );
-                }
+                info!(
+                    self,
+                    "{} Adding sender: {} to [ {} ]",
+                    "✓".green(),
+                    node_id,
+                    self.log_tag
+                );
+                self.packet_send.insert(node_id, sender);
+                self.router.add_neighbour(node_id);
+                self.invalidate_routes();
+                self.topology
+                    .add_link((self.id, NodeType::Client), (node_id, NodeType::Drone));
 
                 info!(
-                    "{} [ ChatClient {} ]: Reinitializing network AddSender",
+                    self,
+                    "{} [ {} ]: Reinitializing network AddSender",
                     "ℹ".blue(),
-                    self.id
+                    self.log_tag
                 );
-                let requests = self.router.get_flood_requests(self.packet_send.len());
-                for (sender, request) in self.packet_send.values().zip(requests) {
-                    if sender.send(request).is_err() {
-                        error!(
-                            "{} [ ChatClient {} ]: Failed to send floodrequest",
-                            "✓".green(),
-                            self.id
-                        );
-                    }
+                self.reflood();
+            }
+            ChatClientCommand::Ping(destination) => {
+                if let Err(e) = self.ping(destination) {
+                    self.command_failed(&context, e.to_string());
                 }
-                thread::sleep(Duration::from_secs(2));
+            }
+            ChatClientCommand::ReplaceSender(node_id, sender) => {
+                self.replace_neighbor(node_id, sender);
             }
             ChatClientCommand::RemoveSender(node_id) => {
-                if self.packet_send.contains_key(&node_id) {
-                    info!(
-                        "{} Removing sender: {} from [ ChatClient {} ]",
-                        "✓".green(),
-                        node_id,
-                        self.id
-                    );
-                    self.packet_send.remove(&node_id);
-                    self.router.remove_neighbour(node_id);
-                } else {
-                    warn!(
-                        "{} [ ChatClient {} ] is already disconnected from [ Drone {} ]",
-                        "!!!".yellow(),
-                        self.id,
-                        node_id
-                    );
-                }
+                info!(
+                    self,
+                    "{} Removing sender: {} from [ {} ]",
+                    "✓".green(),
+                    node_id,
+                    self.log_tag
+                );
+                self.remove_neighbor(node_id);
 
                 info!(
-                    "{} [ ChatClient {} ]: Reinitializing network RemoveSender",
+                    self,
+                    "{} [ {} ]: Reinitializing network RemoveSender",
                     "ℹ".blue(),
-                    self.id
+                    self.log_tag
                 );
-                let requests = self.router.get_flood_requests(self.packet_send.len());
-                for (sender, request) in self.packet_send.values().zip(requests) {
-                    if sender.send(request).is_err() {
-                        error!(
-                            "{} [ ChatClient {} ]: Failed to send floodrequest",
-                            "✓".green(),
-                            self.id
-                        );
-                    }
-                }
-                thread::sleep(Duration::from_secs(2));
+                self.reflood();
             }
             ChatClientCommand::InitFlooding => {
                 info!(
-                    "{} [ ChatClient {} ]: Initiating flooding process",
+                    self,
+                    "{} [ {} ]: Initiating flooding process",
                     "ℹ".blue(),
-                    self.id
+                    self.log_tag
                 );
-                let requests = self.router.get_flood_requests(self.packet_send.len());
-                for (sender, request) in self.packet_send.values().zip(requests) {
-                    if sender.send(request).is_err() {
-                        error!(
-                            "{} [ ChatClient {} ]: Failed to send floodrequest",
-                            "✓".green(),
-                            self.id
-                        );
-                    }
-                }
-                thread::sleep(Duration::from_secs(2));
+                self.reflood();
             }
             ChatClientCommand::StartChatClient => {
                 self.running = true;
                 info!(
-                    "{} [ ChatClient {} ]: Starting ChatClient",
+                    self,
+                    "{} [ {} ]: Starting ChatClient",
                     "ℹ".blue(),
-                    self.id
+                    self.log_tag
                 );
-                self.query_communication_servers();
+                if self.config.auto_discovery {
+                    self.discover_servers();
+                } else {
+                    self.query_communication_servers();
+                }
             }
             ChatClientCommand::SendMessageTo(client_id, text) => {
-                if self.is_running() && self.is_registered() {
-                    if self.client_list.contains(&client_id) {
-                        let server_id = self.registered.unwrap();
-                        info!(
-                            "{} [ ChatClient {} ]: Sending message to [ ChatClient {} ] through [ CommunicationServer {} ]",
-                            "ℹ".blue(),
-                            self.id,
-                            client_id,
-                            server_id,
-                        );
-                        let message_content =
-                            MessageContent::FromClient(ClientMessage::SendMessage {
-                                recipient_id: client_id,
-                                content: text,
-                            });
-                        self.generate_and_send_message(message_content, server_id);
-                    } else {
-                        error!(
-                            "{} [ ChatClient {} ]: Cannot send message, destination client {} is unreachable",
-                            "✗".red(),
-                            self.id,
-                            client_id
-                        );
-                        self.controller_send
-                            .send(ChatClientEvent::UnreachableClient(client_id))
-                            .unwrap();
+                if let Some(server_id) = self.registered {
+                    info!(
+                        self,
+                        "{} [ {} ]: Sending message to [ ChatClient {} ] through [ CommunicationServer {} ]",
+                        "ℹ".blue(),
+                        self.log_tag,
+                        client_id,
+                        server_id);
+                    if self.send_chat_message(client_id, text.clone()).is_err() {
+                        self.queue_message(client_id, text);
                     }
+                } else {
+                    self.queue_message(client_id, text);
                 }
             }
             ChatClientCommand::RegisterTo(server_id) => {
-                if self.is_running() {
-                    if self.communication_server_list.contains(&server_id) {
-                        info!(
-                            "{} [ ChatClient {} ]: Registering to [ CommunicationServer {} ]",
-                            "ℹ".blue(),
-                            self.id,
-                            server_id,
-                        );
-                        let message_content =
-                            MessageContent::FromClient(ClientMessage::RegisterToChat);
-                        self.generate_and_send_message(message_content, server_id);
-                    } else {
-                        error!(
-                            "{} [ ChatClient {} ]: Cannot register to server {}, it is not a communication server, communication_server_list: {:?}",
-                            "✗".red(),
-                            self.id,
-                            server_id,
-                            self.communication_server_list
-                        );
-                    }
+                info!(
+                    self,
+                    "{} [ {} ]: Registering to [ CommunicationServer {} ]",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    server_id
+                );
+                if let Err(e) = self.send_control_request(server_id, ControlRequest::Registration) {
+                    self.command_failed(&context, e.to_string());
                 }
             }
             ChatClientCommand::GetClientList => {
-                if self.is_running() && self.is_registered() {
-                    let server_id = self.registered.unwrap();
+                if let Some(server_id) = self.registered {
                     info!(
-                        "{} [ ChatClient {} ]: Requesting client list from [ Server {} ]",
+                        self,
+                        "{} [ {} ]: Requesting client list from [ Server {} ]",
                         "ℹ".blue(),
-                        self.id,
-                        server_id,
+                        self.log_tag,
+                        server_id
                     );
                     let message_content = MessageContent::FromClient(ClientMessage::GetClientList);
-                    self.generate_and_send_message(message_content, server_id);
+                    if let Err(e) = self.generate_and_send_message(message_content, server_id) {
+                        self.command_failed(&context, e.to_string());
+                    }
                 }
             }
             ChatClientCommand::LogOut => {
-                if self.is_running() && self.is_registered() {
-                    let server_id = self.registered.unwrap();
+                if let Some(server_id) = self.registered {
                     info!(
-                        "{} [ ChatClient {} ]: Logging out from [ CommunicationServer {} ]",
+                        self,
+                        "{} [ {} ]: Logging out from [ CommunicationServer {} ]",
                         "ℹ".blue(),
-                        self.id,
-                        server_id,
+                        self.log_tag,
+                        server_id
                     );
-                    let message_content = MessageContent::FromClient(ClientMessage::Logout);
-                    self.generate_and_send_message(message_content, server_id);
+                    if let Err(e) = self.send_control_request(server_id, ControlRequest::Logout) {
+                        self.command_failed(&context, e.to_string());
+                    }
                 }
             }
+            ChatClientCommand::CreateRoom(room_id) => self.create_room(room_id),
+            ChatClientCommand::JoinRoom(room_id) => self.join_room(room_id),
+            ChatClientCommand::LeaveRoom(room_id) => self.leave_room(&room_id),
+            ChatClientCommand::SendRoomMessage(room_id, text) => {
+                self.send_room_message(&room_id, text);
+            }
+            ChatClientCommand::SendFileTo(client_id, path) => self.send_file(client_id, &path),
+            ChatClientCommand::SetStatus(status) => self.set_status(status),
+            ChatClientCommand::SendRichMessageTo(client_id, payload) => {
+                if let Err(e) = self.send_rich_message(client_id, payload) {
+                    self.command_failed(&context, e.to_string());
+                }
+            }
+            ChatClientCommand::NotifyTyping(client_id) => self.notify_typing(client_id),
+            ChatClientCommand::GetStats => self.report_stats(),
+            ChatClientCommand::HealthCheck => self.report_health(),
+            ChatClientCommand::SetLogColors(colored) => self.logger.set_colored(colored),
+            ChatClientCommand::SetTimeScale(factor) => {
+                // an invalid scale is rejected by `acknowledge`
+                if let Some(scale) = TimeScale::new(factor) {
+                    self.set_time_scale(scale);
+                }
+            }
+            ChatClientCommand::InjectPacket(packet) => {
+                info!(
+                    self,
+                    "{} [ {} ]: Received packet with session_id: {} from the controller",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    packet.session_id
+                );
+                // a shortcut around a broken link, which was meant to arrive from a neighbour
+                self.timed_packet(packet);
+            }
+            ChatClientCommand::AddPersona(persona, name) => {
+                if let Err(e) = self.add_persona(persona, name) {
+                    self.command_failed(&context, e.to_string());
+                }
+            }
+            ChatClientCommand::RemovePersona(persona) => self.remove_persona(persona),
+            ChatClientCommand::GetPersona(persona) => self.send_persona_info(persona),
+            ChatClientCommand::SendAsPersona {
+                persona,
+                recipient,
+                recipient_persona,
+                content,
+            } => {
+                if let Err(e) = self.send_as_persona(persona, recipient, recipient_persona, content)
+                {
+                    self.command_failed(&context, e.to_string());
+                }
+            }
+            ChatClientCommand::SetUsername(username) => self.set_username(username),
+            ChatClientCommand::GetUsernames => self.send_username_list(),
+            ChatClientCommand::RegisterToBest => {
+                if !self.register_to_best_server() {
+                    self.command_failed(
+                        &context,
+                        "no communication server can be reached".to_string(),
+                    );
+                }
+            }
+            ChatClientCommand::ReplayEvents(from_seq) => self.replay_events(from_seq),
+            ChatClientCommand::StartBot => self.start_bot(),
+            ChatClientCommand::StopBot => self.stop_bot(),
+            ChatClientCommand::MarkRead(peer) => self.mark_read(peer),
+            ChatClientCommand::BlockClient(client_id) => self.block_client(client_id),
+            ChatClientCommand::UnblockClient(client_id) => self.unblock_client(client_id),
+            ChatClientCommand::CancelMessage(message_id) => self.cancel_message_by_id(message_id),
+            ChatClientCommand::GetHistory(peer) => {
+                self.send_event(ChatClientEvent::History(
+                    self.id,
+                    self.history.with_peer(peer),
+                ));
+            }
+            ChatClientCommand::GetConversation(peer) => {
+                self.send_event(ChatClientEvent::Conversation(
+                    self.id,
+                    self.conversations.get(peer),
+                ));
+            }
+            ChatClientCommand::GetFullHistory => {
+                self.send_event(ChatClientEvent::History(
+                    self.id,
+                    self.history.entries().to_vec(),
+                ));
+            }
+            ChatClientCommand::ExportHistory(format, path) => {
+                if let Err(e) = self.export_history(format, path) {
+                    self.command_failed(&context, e.to_string());
+                }
+            }
+            ChatClientCommand::ImportHistory(format, path) => {
+                if let Err(e) = self.import_history(format, path) {
+                    self.command_failed(&context, e.to_string());
+                }
+            }
+            ChatClientCommand::Shutdown => {
+                info!(self, "{} [ {} ]: Shutting down", "ℹ".blue(), self.log_tag);
+                self.shutdown = true;
+            }
+            ChatClientCommand::RefreshServers => self.refresh_servers(),
+            ChatClientCommand::GetInFlight => {
+                self.send_event(ChatClientEvent::InFlight(
+                    self.id,
+                    self.packet_cache.in_flight(),
+                ));
+            }
+            ChatClientCommand::GetSendProgress(message_id) => self.report_progress(message_id),
+            ChatClientCommand::PinRoute(destination, hops) => self.pin_route(destination, hops),
+            ChatClientCommand::UnpinRoute(destination) => self.unpin_route(destination),
+            ChatClientCommand::BlacklistNode(node_id) => self.blacklist_node(node_id),
+            ChatClientCommand::WhitelistNode(node_id) => self.whitelist_node(node_id),
+            ChatClientCommand::TraceMessage(message_id) => {
+                self.send_event(ChatClientEvent::MessageTrace(
+                    self.id,
+                    message_id,
+                    self.traces.get(message_id),
+                ));
+            }
+            ChatClientCommand::GetTopology => {
+                self.send_event(ChatClientEvent::TopologySnapshot(
+                    self.id,
+                    self.topology.snapshot(self.blacklisted_nodes()),
+                ));
+            }
             ChatClientCommand::LogNetwork => {
                 self.router.log_network();
             }
+            #[cfg(feature = "fault-injection")]
+            ChatClientCommand::InjectDropNextAck => self.inject_drop_next_ack(),
+            #[cfg(feature = "fault-injection")]
+            ChatClientCommand::InjectCorruptFragment => self.inject_corrupt_fragment(),
+            #[cfg(feature = "fault-injection")]
+            ChatClientCommand::InjectDelay(millis) => self.inject_delay(millis),
+            // rejected by `acknowledge` without the `fault-injection` feature
+            #[cfg(not(feature = "fault-injection"))]
+            ChatClientCommand::InjectDropNextAck
+            | ChatClientCommand::InjectCorruptFragment
+            | ChatClientCommand::InjectDelay(_) => {}
+            // rejected by `acknowledge`, ids are not nested
+            ChatClientCommand::WithId(..) => {}
         }
     }
 }
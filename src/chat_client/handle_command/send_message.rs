@@ -1,81 +1,310 @@
 use super::ChatClient;
+use crate::chat_client::{
+    error::ChatClientError, payload::Payload, reassembly::resize_fragments, send_window::Priority,
+};
 
 use colored::Colorize;
-use log::{error, info};
 
 use messages::{
-    client_commands::ChatClientEvent,
+    client_commands::{
+        ChatClientCommand, ChatClientEvent, CommandContext, MessageDirection, TraceStep,
+    },
     high_level_messages::{ClientMessage, MessageContent},
 };
 
 use wg_2024::network::NodeId;
 
 impl ChatClient {
-    pub(super) fn query_communication_servers(&mut self) {
+    pub(crate) fn query_communication_servers(&mut self) {
         let server_list = &self.router.get_server_list();
         info!(
-            "{} [ ChatClient {} ]: Server list: {:?}",
+            self,
+            "{} [ {} ]: Server list: {:?}",
             "ℹ".blue(),
-            self.id,
+            self.log_tag,
             server_list
         );
-        for server_id in &self.router.get_server_list() {
-            let message_content = MessageContent::FromClient(ClientMessage::GetServerType);
-            info!(
-                "{} [ ChatClient {} ]: Querying server [ Server {} ]",
-                "ℹ".blue(),
-                self.id,
-                server_id,
-            );
-            self.generate_and_send_message(message_content, *server_id);
+        for server_id in self.router.get_server_list() {
+            self.query_server_type(server_id, 0);
+        }
+    }
+
+    /// Sends a chat message to `recipient_id` through the registered
+    /// server, split in chunks if it is oversized and chunking is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client is not registered, if the message is
+    /// oversized and chunking is disabled, or if it cannot be sent.
+    pub(crate) fn send_chat_message(
+        &mut self,
+        recipient_id: NodeId,
+        content: String,
+    ) -> Result<(), ChatClientError> {
+        let server_id = self.registered.ok_or(ChatClientError::NotRegistered)?;
+        if let Some(limit) = self
+            .config
+            .max_message_size
+            .filter(|_| self.is_oversized(&content))
+        {
+            if !self.config.chunk_oversized {
+                return Err(ChatClientError::MessageTooLarge {
+                    size: content.len(),
+                    limit,
+                });
+            }
+            return self.send_chunked_message(recipient_id, content);
         }
+
+        let sequence = self.conversations.next_sequence(recipient_id);
+        let sent = match sequence {
+            Some(sequence) => Payload::ChatMessage {
+                sequence,
+                content: content.clone(),
+            }
+            .encode(),
+            None => content.clone(),
+        };
+        let sent = self.conversations.timestamped(sent);
+        let message_content = MessageContent::FromClient(ClientMessage::SendMessage {
+            recipient_id,
+            content: self.checked_content(recipient_id, sent),
+        });
+        self.generate_and_send_message(message_content, server_id)?;
+        self.conversations
+            .sent(recipient_id, sequence, content.clone());
+        self.history
+            .record(recipient_id, MessageDirection::Sent, content);
+        Ok(())
+    }
+
+    /// Sends `payload` to the client `recipient_id` through the registered
+    /// server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client is not registered, or if the payload
+    /// cannot be sent.
+    pub(crate) fn send_payload(
+        &mut self,
+        recipient_id: NodeId,
+        payload: &Payload,
+    ) -> Result<(), ChatClientError> {
+        let server_id = self.registered.ok_or(ChatClientError::NotRegistered)?;
+
+        let content = match payload {
+            // already checked, or asking for a checked content
+            Payload::Checked { .. } | Payload::ResendRequest { .. } => payload.encode(),
+            _ => self.checked_content(recipient_id, payload.encode()),
+        };
+        let message_content = MessageContent::FromClient(ClientMessage::SendMessage {
+            recipient_id,
+            content,
+        });
+        self.generate_and_send_message(message_content, server_id)
     }
 
-    pub(super) fn generate_and_send_message(
+    /// Fragments `message_content` and sends it to `destination`, or holds
+    /// it back until a flood finds a route to it when auto-discovery is
+    /// enabled.
+    ///
+    /// The route is checked to leave through a current neighbour before
+    /// anything is fragmented.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no route to `destination` through a current
+    /// neighbour is known and auto-discovery is disabled.
+    pub(crate) fn generate_and_send_message(
         &mut self,
         message_content: MessageContent,
         destination: NodeId,
-    ) {
-        if let Ok(source_routing_header) = self.router.get_source_routing_header(destination) {
-            for frag_pack in self.msgfactory.get_message_from_message_content(
+    ) -> Result<(), ChatClientError> {
+        if let Some(source_routing_header) = self.route_through_neighbors(destination) {
+            self.routing_succeeded(destination);
+            let priority = Priority::of(&message_content);
+            let chat_recipient = chat_recipient(&message_content);
+            #[cfg(feature = "compression")]
+            let message_content = self.compressed(message_content);
+            let fragments = self.msgfactory.get_message_from_message_content(
                 message_content,
                 &source_routing_header,
                 destination,
-            ) {
-                self.msgfactory.insert_packet(&frag_pack);
-                self.forward_packet(frag_pack);
+            );
+            let mut fragments = resize_fragments(fragments, self.config.fragment_size);
+            self.spread_over_routes(destination, &source_routing_header, &mut fragments);
+            let session_id = fragments.first().map(|fragment| fragment.session_id);
+            if let Some(session_id) = session_id {
+                self.progress.started(
+                    session_id,
+                    destination,
+                    u64::try_from(fragments.len()).unwrap_or(u64::MAX),
+                );
+                self.start_trace(
+                    session_id,
+                    TraceStep::Fragmented {
+                        destination,
+                        fragments: fragments.len(),
+                    },
+                );
             }
-        }
-    }
-
-    pub(super) fn is_running(&self) -> bool {
-        if !self.running {
+            for frag_pack in &fragments {
+                self.packet_cache.insert(frag_pack);
+            }
+            self.dispatch(destination, fragments, priority);
+            self.enforce_cache_capacity();
+            if let (Some(recipient_id), Some(session_id)) = (chat_recipient, session_id) {
+                self.send_event(ChatClientEvent::MessageSent(recipient_id, session_id));
+            }
+            Ok(())
+        } else if self.config.auto_discovery {
+            info!(
+                self,
+                "{} [ {} ]: No path to destination [ Server {} ], flooding before sending",
+                "ℹ".blue(),
+                self.log_tag,
+                destination
+            );
+            self.send_after_flood(message_content, destination);
+            Ok(())
+        } else {
             error!(
-                "{} [ ChatClient {} ]: Cannot send message, ChatClient is not running",
+                self,
+                "{} [ {} ]: No path to destination [ Server {} ]",
                 "✗".red(),
-                self.id
+                self.log_tag,
+                destination
             );
-            self.controller_send
-                .send(ChatClientEvent::ErrorNotRunning)
-                .unwrap();
-            return false;
+            self.routing_failed(destination);
+            Err(ChatClientError::RoutingFailed(destination))
         }
-        true
     }
 
-    pub(super) fn is_registered(&self) -> bool {
-        if self.registered.is_none() {
-            error!(
-                "{} [ ChatClient {} ]: Cannot send message, not registered to any server",
-                "✗".red(),
-                self.id
-            );
-            self.controller_send
-                .send(ChatClientEvent::ErrorNotRegistered)
-                .unwrap();
-            return false;
+    /// Describes `command` for the events reporting that it failed.
+    pub(super) fn command_context(&self, command: &ChatClientCommand) -> CommandContext {
+        let destination = match command {
+            ChatClientCommand::SendMessageTo(node_id, _)
+            | ChatClientCommand::RegisterTo(node_id)
+            | ChatClientCommand::SendRichMessageTo(node_id, _)
+            | ChatClientCommand::SendAsPersona {
+                recipient: node_id, ..
+            }
+            | ChatClientCommand::SendFileTo(node_id, _)
+            | ChatClientCommand::NotifyTyping(node_id)
+            | ChatClientCommand::Ping(node_id) => Some(*node_id),
+            ChatClientCommand::GetClientList
+            | ChatClientCommand::LogOut
+            | ChatClientCommand::JoinRoom(_)
+            | ChatClientCommand::LeaveRoom(_)
+            | ChatClientCommand::SendRoomMessage(..) => self.registered,
+            _ => None,
+        };
+
+        CommandContext {
+            client_id: self.id,
+            command: command_name(command).to_string(),
+            destination,
         }
+    }
 
-        true
+    /// Tells the controller that the command described by `context` could
+    /// not be carried out.
+    pub(super) fn command_failed(&self, context: &CommandContext, reason: impl Into<String>) {
+        self.send_event(ChatClientEvent::CommandFailed {
+            command: context.clone(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// The name of the variant of `command`, without its arguments.
+fn command_name(command: &ChatClientCommand) -> &'static str {
+    match command {
+        ChatClientCommand::AddPersona(..) => "AddPersona",
+        ChatClientCommand::AddSender(..) => "AddSender",
+        ChatClientCommand::BlacklistNode(..) => "BlacklistNode",
+        ChatClientCommand::BlockClient(..) => "BlockClient",
+        ChatClientCommand::CancelMessage(..) => "CancelMessage",
+        ChatClientCommand::CreateRoom(..) => "CreateRoom",
+        ChatClientCommand::ExportHistory(..) => "ExportHistory",
+        ChatClientCommand::GetClientList => "GetClientList",
+        ChatClientCommand::GetConversation(..) => "GetConversation",
+        ChatClientCommand::GetFullHistory => "GetFullHistory",
+        ChatClientCommand::GetHistory(..) => "GetHistory",
+        ChatClientCommand::GetInFlight => "GetInFlight",
+        ChatClientCommand::GetPersona(..) => "GetPersona",
+        ChatClientCommand::GetSendProgress(..) => "GetSendProgress",
+        ChatClientCommand::GetStats => "GetStats",
+        ChatClientCommand::GetTopology => "GetTopology",
+        ChatClientCommand::GetUsernames => "GetUsernames",
+        ChatClientCommand::HealthCheck => "HealthCheck",
+        ChatClientCommand::ImportHistory(..) => "ImportHistory",
+        ChatClientCommand::InitFlooding => "InitFlooding",
+        ChatClientCommand::InjectCorruptFragment => "InjectCorruptFragment",
+        ChatClientCommand::InjectDelay(..) => "InjectDelay",
+        ChatClientCommand::InjectDropNextAck => "InjectDropNextAck",
+        ChatClientCommand::InjectPacket(..) => "InjectPacket",
+        ChatClientCommand::JoinRoom(..) => "JoinRoom",
+        ChatClientCommand::LeaveRoom(..) => "LeaveRoom",
+        ChatClientCommand::LogNetwork => "LogNetwork",
+        ChatClientCommand::LogOut => "LogOut",
+        ChatClientCommand::MarkRead(..) => "MarkRead",
+        ChatClientCommand::NotifyTyping(..) => "NotifyTyping",
+        ChatClientCommand::PinRoute(..) => "PinRoute",
+        ChatClientCommand::Ping(..) => "Ping",
+        ChatClientCommand::RefreshServers => "RefreshServers",
+        ChatClientCommand::RegisterTo(..) => "RegisterTo",
+        ChatClientCommand::RegisterToBest => "RegisterToBest",
+        ChatClientCommand::RemovePersona(..) => "RemovePersona",
+        ChatClientCommand::RemoveSender(..) => "RemoveSender",
+        ChatClientCommand::ReplaceSender(..) => "ReplaceSender",
+        ChatClientCommand::ReplayEvents(..) => "ReplayEvents",
+        ChatClientCommand::SendAsPersona { .. } => "SendAsPersona",
+        ChatClientCommand::SendFileTo(..) => "SendFileTo",
+        ChatClientCommand::SendMessageTo(..) => "SendMessageTo",
+        ChatClientCommand::SendRichMessageTo(..) => "SendRichMessageTo",
+        ChatClientCommand::SendRoomMessage(..) => "SendRoomMessage",
+        ChatClientCommand::SetLogColors(..) => "SetLogColors",
+        ChatClientCommand::SetStatus(..) => "SetStatus",
+        ChatClientCommand::SetTimeScale(..) => "SetTimeScale",
+        ChatClientCommand::SetUsername(..) => "SetUsername",
+        ChatClientCommand::Shutdown => "Shutdown",
+        ChatClientCommand::StartBot => "StartBot",
+        ChatClientCommand::StartChatClient => "StartChatClient",
+        ChatClientCommand::StopBot => "StopBot",
+        ChatClientCommand::TraceMessage(..) => "TraceMessage",
+        ChatClientCommand::UnblockClient(..) => "UnblockClient",
+        ChatClientCommand::UnpinRoute(..) => "UnpinRoute",
+        ChatClientCommand::WhitelistNode(..) => "WhitelistNode",
+        ChatClientCommand::WithId(..) => "WithId",
+    }
+}
+
+/// The recipient of a chat message typed by the user, `None` for every
+/// other message including the payloads exchanged between clients.
+fn chat_recipient(message_content: &MessageContent) -> Option<NodeId> {
+    match message_content {
+        MessageContent::FromClient(ClientMessage::SendMessage {
+            recipient_id,
+            content,
+        }) if is_chat_text(content) => Some(*recipient_id),
+        _ => None,
+    }
+}
+
+/// Whether `content` carries a chat message, possibly numbered or checked.
+fn is_chat_text(content: &str) -> bool {
+    match Payload::decode(content) {
+        None
+        | Some(
+            Payload::ChatMessage { .. }
+            | Payload::RichMessage { .. }
+            | Payload::AutoReply { .. }
+            | Payload::PersonaMessage { .. },
+        ) => true,
+        Some(Payload::Checked { content, .. } | Payload::Timestamped { content, .. }) => {
+            is_chat_text(&content)
+        }
+        Some(_) => false,
     }
 }
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, ProtocolViolation};
+use wg_2024::packet::{FloodRequest, FloodResponse, Fragment, Packet, PacketType, FRAGMENT_DSIZE};
+
+use crate::{chat_client::config::ConformanceMode, ChatClient};
+
+impl ChatClient {
+    /// Checks a received packet against the rules of the WG 2024 protocol,
+    /// reporting what it breaks with a `ChatClientEvent::ProtocolViolation`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the packet must still be handled: always, unless it breaks
+    /// a rule in `ConformanceMode::Strict`.
+    pub(super) fn conformant(&self, packet: &Packet) -> bool {
+        if self.config.conformance == ConformanceMode::Off {
+            return true;
+        }
+
+        let violations = match &packet.pack_type {
+            PacketType::FloodRequest(flood_request) => flood_request_violations(flood_request),
+            pack_type => {
+                let mut violations = self.routing_header_violations(packet);
+                match pack_type {
+                    PacketType::MsgFragment(fragment) => {
+                        violations.extend(fragment_violations(fragment));
+                    }
+                    PacketType::FloodResponse(flood_response) => {
+                        violations.extend(self.flood_response_violations(packet, flood_response));
+                    }
+                    _ => {}
+                }
+                violations
+            }
+        };
+        if violations.is_empty() {
+            return true;
+        }
+
+        let strict = self.config.conformance == ConformanceMode::Strict;
+        warn!(
+            self,
+            "{} [ {} ]: {} {} breaks the protocol: {}",
+            "!!!".yellow(),
+            self.log_tag,
+            if strict { "Discarding" } else { "Received" },
+            packet.pack_type,
+            violations.join(", ")
+        );
+        self.send_event(ChatClientEvent::ProtocolViolation(ProtocolViolation {
+            session_id: packet.session_id,
+            packet_type: packet.pack_type.to_string(),
+            routing_header: packet.routing_header.to_string(),
+            violations,
+        }));
+        !strict
+    }
+
+    /// A packet other than a `FloodRequest` has its source first, the
+    /// receiving node at `hop_index` and a client as its last hop, and does
+    /// not visit a node twice.
+    fn routing_header_violations(&self, packet: &Packet) -> Vec<String> {
+        let hops = &packet.routing_header.hops;
+        let hop_index = packet.routing_header.hop_index;
+        let mut violations = Vec::new();
+
+        if hops.len() < 2 {
+            violations.push(format!("route of {} hops", hops.len()));
+        }
+        if hop_index == 0 {
+            violations.push("hop_index points at the source".to_string());
+        }
+        match hops.get(hop_index) {
+            None => violations.push(format!(
+                "hop_index {hop_index} is out of bounds for {} hops",
+                hops.len()
+            )),
+            Some(&hop) if hop != self.id => {
+                violations.push(format!("hop_index points at {hop}, not at this client"));
+            }
+            Some(_) => {}
+        }
+        if hops.last() != Some(&self.id) {
+            violations.push("this client is not the last hop".to_string());
+        }
+        let mut visited = HashSet::new();
+        if let Some(hop) = hops.iter().find(|&&hop| !visited.insert(hop)) {
+            violations.push(format!("route visits {hop} twice"));
+        }
+        violations
+    }
+
+    /// A `FloodResponse` comes back along the reverse of its path trace,
+    /// which starts at this client.
+    fn flood_response_violations(
+        &self,
+        packet: &Packet,
+        flood_response: &FloodResponse,
+    ) -> Vec<String> {
+        let path: Vec<_> = flood_response
+            .path_trace
+            .iter()
+            .map(|&(node_id, _)| node_id)
+            .collect();
+        let mut violations = Vec::new();
+
+        if path.first() != Some(&self.id) {
+            violations.push("path trace does not start at this client".to_string());
+        }
+        if !path.iter().rev().eq(packet.routing_header.hops.iter()) {
+            violations.push("route is not the reversed path trace".to_string());
+        }
+        violations
+    }
+}
+
+/// A `FloodRequest` has a path trace starting at its initiator.
+fn flood_request_violations(flood_request: &FloodRequest) -> Vec<String> {
+    match flood_request.path_trace.first() {
+        None => vec!["empty path trace".to_string()],
+        Some(&(node_id, _)) if node_id != flood_request.initiator_id => vec![format!(
+            "path trace starts at {node_id}, not at the initiator {}",
+            flood_request.initiator_id
+        )],
+        Some(_) => Vec::new(),
+    }
+}
+
+/// A fragment lies within its message and declares at most 128 bytes.
+///
+/// Fragments shorter than 128 bytes are conformant wherever they fall in
+/// the message, senders being free to use a smaller fragment size.
+fn fragment_violations(fragment: &Fragment) -> Vec<String> {
+    let mut violations = Vec::new();
+    if fragment.fragment_index >= fragment.total_n_fragments {
+        violations.push(format!(
+            "fragment {} of a message of {} fragments",
+            fragment.fragment_index, fragment.total_n_fragments
+        ));
+    }
+    let length = usize::from(fragment.length);
+    if length > FRAGMENT_DSIZE {
+        violations.push(format!("fragment declares {length} bytes"));
+    }
+    violations
+}
@@ -1,149 +1,208 @@
-use std::{thread, time::Duration};
-
-use super::ChatClient;
+use super::{error::ChatClientError, ChatClient, FloodMode, ValidationPolicy};
 use colored::Colorize;
-use log::{error, info, warn};
 
-use messages::client_commands::ChatClientEvent;
+use messages::client_commands::{ChatClientEvent, TelemetryKind, TraceStep};
 use wg_2024::{
     network::{NodeId, SourceRoutingHeader},
-    packet::{
-        Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType,
-    },
+    packet::{Ack, FloodRequest, FloodResponse, Nack, NackType, NodeType, Packet, PacketType},
 };
+mod conformance;
+mod nack;
 mod read_message;
+mod validation;
 impl ChatClient {
     #[allow(clippy::too_many_lines)]
-    pub(super) fn handle_packet(&mut self, packet: &Packet) {
-        if let PacketType::FloodRequest(mut flood_request) = packet.clone().pack_type {
-            flood_request.path_trace.push((self.id, NodeType::Client));
-
-            let mut routing_header = SourceRoutingHeader::new(
-                flood_request
-                    .clone()
-                    .path_trace
-                    .into_iter()
-                    .map(|(id, _ntype)| id)
-                    .collect(),
-                1,
-            );
-
-            routing_header.hops.reverse();
-
-            if routing_header.hops.last().copied().unwrap() != flood_request.initiator_id {
-                routing_header.hops.push(flood_request.initiator_id);
-            }
-
-            match routing_header.current_hop() {
-                Some(dest) => {
-                    self.send_flood_response(
-                        dest,
-                        &flood_request,
-                        routing_header,
-                        packet.session_id,
-                    );
-                }
-                None => {
-                    error!(
-                        "{} [ ChatClient {} ]: No destination found in routing header",
-                        "✗".red(),
-                        self.id
-                    );
-                }
-            }
-        } else if self.valid_packet(packet.clone()) {
+    pub(super) fn handle_packet(&mut self, packet: Packet) {
+        if !self.conformant(&packet) {
+            return;
+        }
+        if let PacketType::FloodRequest(flood_request) = packet.pack_type {
+            self.process_flood_request(flood_request, packet.session_id);
+        } else if self.well_formed(&packet) && self.valid_packet(&packet) {
             // the client received a packet
-            match packet.clone().pack_type {
-                PacketType::MsgFragment(fragment) => self.process_fragment(&fragment, packet),
-                PacketType::Ack(ack) => self.msgfactory.received_ack(ack, packet.session_id),
-                PacketType::Nack(nack) => self.process_nack(&nack, packet),
+            match &packet.pack_type {
+                PacketType::MsgFragment(_) => self.process_fragment(packet),
+                PacketType::Ack(ack) => self.process_ack(ack, &packet),
+                PacketType::Nack(nack) => self.process_nack(nack, &packet),
                 PacketType::FloodResponse(flood_response) => {
-                    info!("[CHATCLIENT {}]: {}", self.id, flood_response);
-                    self.process_flood_response(&flood_response);
+                    packet_info!(self, "[ {} ]: {}", self.log_tag, flood_response);
+                    self.process_flood_response(flood_response);
                 }
-                PacketType::FloodRequest(_) => unreachable!(),
+                // handled before the routing header is checked
+                PacketType::FloodRequest(_) => {}
             }
         }
     }
 
-    fn valid_packet(&self, packet: Packet) -> bool {
+    /// Whether `packet` is addressed to this client, or is handled anyway
+    /// as the `ValidationPolicy` of its type says.
+    fn valid_packet(&mut self, packet: &Packet) -> bool {
         if self.id == packet.routing_header.hops[packet.routing_header.hop_index]
             && packet.routing_header.hop_index == packet.routing_header.len() - 1
         {
-            info!(
-                "{} [ ChatClient {} ]: received a packet from [ Node {} ]",
+            packet_info!(
+                self,
+                "{} [ {} ]: received a packet from [ Node {} ]",
                 "✓".green(),
-                self.id,
+                self.log_tag,
                 packet.routing_header.hops[packet.routing_header.hop_index - 1]
             );
-            true
-        } else {
-            error!(
-                "{} [ ChatClient {} ]: does not correspond to the Node indicated by the `hop_index` or it's not the destination, routing_header: {} packetype: {}",
-                "✗".red(),
-                self.id,
-                packet.routing_header,
-                packet.pack_type
-            );
+            return true;
+        }
 
-            if let PacketType::MsgFragment(frag) = packet.clone().pack_type {
-                self.send_nack(packet, Some(frag), NackType::UnexpectedRecipient(self.id));
-            } else {
-                self.controller_send
-                    .send(ChatClientEvent::ControllerShortcut(packet))
-                    .unwrap();
+        match self.config.validation.of(&packet.pack_type) {
+            ValidationPolicy::Strict => {
+                error!(
+                    self,
+                    "{} [ {} ]: does not correspond to the Node indicated by the `hop_index` or it's not the destination, routing_header: {} packetype: {}",
+                    "✗".red(),
+                    self.log_tag,
+                    packet.routing_header,
+                    packet.pack_type
+                );
+
+                if let PacketType::MsgFragment(frag) = &packet.pack_type {
+                    self.stats.validation.nacked += 1;
+                    // failures are logged, the packet is discarded either way
+                    let _ = self.send_nack(
+                        packet.clone(),
+                        frag.fragment_index,
+                        NackType::UnexpectedRecipient(self.id),
+                    );
+                } else {
+                    self.stats.validation.shortcut += 1;
+                    self.send_event(ChatClientEvent::ControllerShortcut(packet.clone()));
+                }
+                false
             }
+            ValidationPolicy::Lenient => {
+                packet_info!(
+                    self,
+                    "{} [ {} ]: Dropping {} not addressed to this client, routing_header: {}",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    packet.pack_type,
+                    packet.routing_header
+                );
+                self.stats.validation.dropped += 1;
+                false
+            }
+            ValidationPolicy::Permissive => {
+                packet_info!(
+                    self,
+                    "{} [ {} ]: Handling {} not addressed to this client, routing_header: {}",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    packet.pack_type,
+                    packet.routing_header
+                );
+                self.stats.validation.processed += 1;
+                true
+            }
+        }
+    }
 
-            false
+    /// Hands `packet` to the next hop of its routing header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header has no next hop, or if the next hop
+    /// is not a connected neighbour and no other one leads to the
+    /// destination. The packet was then Nacked or handed to the controller,
+    /// so callers that have nothing else to do about it may ignore it.
+    pub(crate) fn forward_packet(&mut self, packet: Packet) -> Result<(), ChatClientError> {
+        if packet.routing_header.current_hop().is_none() {
+            error!(
+                self,
+                "{} [ {} ]: Cannot send the {}, no next hop in routing header: {}",
+                "✗".red(),
+                self.log_tag,
+                packet.pack_type,
+                packet.routing_header
+            );
+            return Err(ChatClientError::MalformedPacket(format!(
+                "no next hop in routing header {}",
+                packet.routing_header
+            )));
         }
+        #[cfg(feature = "fault-injection")]
+        let Some(packet) = self.perturb(packet) else {
+            return Ok(());
+        };
+        self.send_to_next_hop(packet)
     }
 
-    pub(crate) fn forward_packet(&self, packet: Packet) -> bool {
-        let destination = packet.routing_header.hops[packet.routing_header.hop_index];
-        let packet_type = packet.pack_type.clone();
+    /// Hands `packet` to the next hop of its routing header, as
+    /// [`ChatClient::forward_packet`] does once the injected faults are
+    /// applied.
+    ///
+    /// # Errors
+    ///
+    /// See [`ChatClient::forward_packet`].
+    pub(super) fn send_to_next_hop(&mut self, packet: Packet) -> Result<(), ChatClientError> {
+        let Some(destination) = packet.routing_header.current_hop() else {
+            return Err(ChatClientError::MalformedPacket(format!(
+                "no next hop in routing header {}",
+                packet.routing_header
+            )));
+        };
+        let session_id = packet.session_id;
+        let packet_type = packet.pack_type.to_string();
+        // the packet is moved into the channel, keep what is accounted once
+        // it is sent
+        let sent_fragment = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                Some((fragment.fragment_index, packet.routing_header.hops.clone()))
+            }
+            _ => None,
+        };
 
         // Try sending to the destination drone
         if let Some(sender) = self.packet_send.get(&destination) {
-            match sender.send(packet.clone()) {
+            match sender.send(packet) {
                 Ok(()) => {
-                    info!(
-                        "{} [ ChatClient {} ]: was sent a {} packet to [ Node {} ]",
+                    if let Some((fragment_index, hops)) = sent_fragment {
+                        self.fragment_forwarded(session_id, fragment_index, &hops, destination);
+                    }
+                    packet_info!(
+                        self,
+                        "{} [ {} ]: was sent a {} packet to [ Node {} ]",
                         "✓".green(),
-                        self.id,
+                        self.log_tag,
                         packet_type,
                         destination
                     );
-                    true
+                    self.emit_telemetry(TelemetryKind::PacketSent {
+                        session_id,
+                        next_hop: destination,
+                        packet_type,
+                    });
+                    Ok(())
                 }
                 Err(e) => {
                     error!(
-                        "{} [ ChatClient {} ]: Failed to send the {} to [ Node {} ]: {}",
+                        self,
+                        "{} [ {} ]: Failed to send the {} to [ Node {} ]: {}",
                         "✗".red(),
-                        self.id,
+                        self.log_tag,
                         packet_type,
                         destination,
                         e
                     );
 
-                    warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
-
-                    self.controller_send
-                        .send(ChatClientEvent::ControllerShortcut(packet))
-                        .unwrap();
-
-                    warn!(
-                        "└─>{} [ ChatClient {} ]: {} sent to Simulation Controller",
-                        "!!!".yellow(),
-                        self.id,
-                        packet_type,
-                    );
-
-                    false
+                    let packet = e.0;
+                    self.neighbor_down(destination);
+                    if self.is_first_hop(&packet) && self.fail_over(&packet) {
+                        return Ok(());
+                    }
+                    self.controller_shortcut(packet)?;
+                    Err(ChatClientError::NeighborUnavailable(destination))
                 }
             }
         } else {
-            if let PacketType::MsgFragment(fragment) = packet_type {
+            if let Some((fragment_index, _)) = sent_fragment {
                 error!(
+                    self,
                     "{} [ ChatClient {} ]: does not exist in the path",
                     "✗".red(),
                     destination
@@ -151,37 +210,69 @@ impl ChatClient {
 
                 self.send_nack(
                     packet,
-                    Some(fragment),
+                    fragment_index,
                     NackType::ErrorInRouting(destination),
-                );
+                )?;
             } else {
                 error!(
-                    "{} [ ChatClient {} ]: Failed to send the {}: No connection to [ Node {} ]",
+                    self,
+                    "{} [ {} ]: Failed to send the {}: No connection to [ Node {} ]",
                     "✗".red(),
-                    self.id,
+                    self.log_tag,
                     packet_type,
                     destination
                 );
 
-                warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
+                self.controller_shortcut(packet)?;
+            }
+
+            Err(ChatClientError::NeighborUnavailable(destination))
+        }
+    }
 
-                self.controller_send
-                    .send(ChatClientEvent::ControllerShortcut(packet))
-                    .unwrap();
+    /// Hands a packet that cannot be sent to the controller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller disconnected, which loses the
+    /// packet.
+    fn controller_shortcut(&mut self, packet: Packet) -> Result<(), ChatClientError> {
+        let packet_type = packet.pack_type.to_string();
+        self.relayed_by_controller(&packet);
+        warn!(
+            self,
+            "├─>{} Sending to Simulation Controller...",
+            "!!!".yellow()
+        );
 
-                warn!(
-                    "└─>{} [ ChatClient {} ]: {} sent to Simulation Controller",
-                    "!!!".yellow(),
-                    self.id,
-                    packet_type,
-                );
-            }
+        self.send_event(ChatClientEvent::ControllerShortcut(packet));
 
-            false
+        warn!(
+            self,
+            "└─>{} [ {} ]: {} sent to Simulation Controller",
+            "!!!".yellow(),
+            self.log_tag,
+            packet_type
+        );
+        if self.controller_gone.get() {
+            return Err(ChatClientError::ControllerGone);
         }
+        Ok(())
     }
 
-    fn send_nack(&self, mut packet: Packet, fragment: Option<Fragment>, nack_type: NackType) {
+    /// Sends a Nack for `packet` back along the hops it came through.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header has no previous hop, or if the
+    /// previous hop is not a connected neighbour, in which case the Nack
+    /// was handed to the controller.
+    fn send_nack(
+        &mut self,
+        mut packet: Packet,
+        fragment_index: u64,
+        nack_type: NackType,
+    ) -> Result<(), ChatClientError> {
         packet
             .routing_header
             .hops
@@ -195,13 +286,22 @@ impl ChatClient {
 
         packet.routing_header.hop_index = 1;
 
-        let prev_hop = packet.routing_header.current_hop().unwrap();
+        let Some(prev_hop) = packet.routing_header.current_hop() else {
+            error!(
+                self,
+                "{} [ {} ]: Cannot send a Nack, no previous hop in routing header: {}",
+                "✗".red(),
+                self.log_tag,
+                packet.routing_header
+            );
+            return Err(ChatClientError::MalformedPacket(format!(
+                "no previous hop in routing header {}",
+                packet.routing_header
+            )));
+        };
 
         let nack = Nack {
-            fragment_index: match fragment {
-                Some(frag) => frag.fragment_index,
-                None => 0,
-            }, // Default fragment index for non-fragmented NACKs
+            fragment_index,
             nack_type,
         };
 
@@ -209,65 +309,165 @@ impl ChatClient {
 
         if let Some(sender) = self.packet_send.get(&prev_hop) {
             // Send the NACK to the previous hop
-            match sender.send(packet.clone()) {
+            match sender.send(packet) {
                 Ok(()) => {
                     warn!(
-                        "{} Nack was sent from [ ChatClient {} ] to [ Drone {} ]",
+                        self,
+                        "{} Nack was sent from [ {} ] to [ Drone {} ]",
                         "!!!".yellow(),
-                        self.id,
+                        self.log_tag,
                         prev_hop
                     );
+                    Ok(())
                 }
                 Err(e) => {
                     // Handle failure to send the NACK, send to the simulation controller instead
                     warn!(
-                        "{} [ ChatClient {} ]: Failed to send the Nack to [ Drone {} ]: {}",
+                        self,
+                        "{} [ {} ]: Failed to send the Nack to [ Drone {} ]: {}",
                         "✗".red(),
-                        self.id,
+                        self.log_tag,
                         prev_hop,
                         e
                     );
 
-                    warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
+                    warn!(
+                        self,
+                        "├─>{} Sending to Simulation Controller...",
+                        "!!!".yellow()
+                    );
 
                     //there is an error in sending the packet, the drone should send the packet to the simulation controller
-                    self.controller_send
-                        .send(ChatClientEvent::ControllerShortcut(packet))
-                        .unwrap();
+                    self.send_event(ChatClientEvent::ControllerShortcut(e.0));
                     warn!(
-                        "└─>{} [ ChatClient {} ]: sent A Nack to the Simulation Controller",
+                        self,
+                        "└─>{} [ {} ]: sent A Nack to the Simulation Controller",
                         "!!!".yellow(),
-                        self.id
+                        self.log_tag
                     );
+                    self.neighbor_down(prev_hop);
+                    Err(ChatClientError::NeighborUnavailable(prev_hop))
                 }
             }
         } else {
             // If no connection to the previous hop, send the NACK to the simulation controller
             error!(
-                "{} [ ChatClient {} ]: Failed to send the Nack: No connection to {}",
+                self,
+                "{} [ {} ]: Failed to send the Nack: No connection to {}",
                 "✗".red(),
-                self.id,
+                self.log_tag,
                 prev_hop
             );
 
-            warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
+            warn!(
+                self,
+                "├─>{} Sending to Simulation Controller...",
+                "!!!".yellow()
+            );
 
             // Create the NACK (same logic as above)
 
             // Send to the simulation controller
-            self.controller_send
-                .send(ChatClientEvent::ControllerShortcut(packet))
-                .unwrap();
+            self.send_event(ChatClientEvent::ControllerShortcut(packet));
             warn!(
-                "└─>{} [ ChatClient {} ]: sent A Nack to the Simulation Controller",
+                self,
+                "└─>{} [ {} ]: sent A Nack to the Simulation Controller",
                 "!!!".yellow(),
-                self.id
+                self.log_tag
             );
+            Err(ChatClientError::NeighborUnavailable(prev_hop))
         }
     }
 
-    fn send_flood_response(
+    fn process_flood_request(&mut self, mut flood_request: FloodRequest, session_id: u64) {
+        let first = self.first_flood_request(&flood_request);
+        let previous_hop = flood_request.path_trace.last().map(|&(id, _)| id);
+
+        match self.config.flood_mode {
+            FloodMode::RespondOnly if !first => {
+                packet_info!(
+                    self,
+                    "{} [ {} ]: Ignoring duplicate FloodRequest with flood_id: {} from [ Node {} ]",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    flood_request.flood_id,
+                    flood_request.initiator_id
+                );
+            }
+            FloodMode::PerSpec
+                if first && self.packet_send.keys().any(|&id| Some(id) != previous_hop) =>
+            {
+                flood_request.path_trace.push((self.id, NodeType::Client));
+                self.forward_flood_request(&flood_request, session_id, previous_hop);
+            }
+            _ => {
+                flood_request.path_trace.push((self.id, NodeType::Client));
+                self.respond_to_flood_request(&flood_request, session_id);
+            }
+        }
+    }
+
+    fn forward_flood_request(
         &self,
+        flood_request: &FloodRequest,
+        session_id: u64,
+        previous_hop: Option<NodeId>,
+    ) {
+        for (&neighbor, sender) in &self.packet_send {
+            if Some(neighbor) == previous_hop {
+                continue;
+            }
+
+            let packet = Packet {
+                pack_type: PacketType::FloodRequest(flood_request.clone()),
+                routing_header: SourceRoutingHeader::empty_route(),
+                session_id,
+            };
+            if sender.send(packet).is_err() {
+                error!(
+                    self,
+                    "{} [ {} ]: Failed to forward the FloodRequest to [ Node {} ]",
+                    "✗".red(),
+                    self.log_tag,
+                    neighbor
+                );
+            }
+        }
+    }
+
+    fn respond_to_flood_request(&mut self, flood_request: &FloodRequest, session_id: u64) {
+        let mut routing_header = SourceRoutingHeader::new(
+            flood_request
+                .path_trace
+                .iter()
+                .map(|&(id, _ntype)| id)
+                .collect(),
+            1,
+        );
+
+        routing_header.hops.reverse();
+
+        if routing_header.hops.last() != Some(&flood_request.initiator_id) {
+            routing_header.hops.push(flood_request.initiator_id);
+        }
+
+        match routing_header.current_hop() {
+            Some(dest) => {
+                self.send_flood_response(dest, flood_request, routing_header, session_id);
+            }
+            None => {
+                error!(
+                    self,
+                    "{} [ {} ]: No destination found in routing header",
+                    "✗".red(),
+                    self.log_tag
+                );
+            }
+        }
+    }
+
+    fn send_flood_response(
+        &mut self,
         dest_node: NodeId,
         flood_request: &FloodRequest,
         routing_header: SourceRoutingHeader,
@@ -285,260 +485,274 @@ impl ChatClient {
         };
 
         if let Some(sender) = self.packet_send.get(&dest_node) {
-            match sender.send(new_packet.clone()) {
-                Ok(()) => info!(
-                    "{} [ ChatClient {} ]: sent the FloodResponse to [ Node {} ]",
+            match sender.send(new_packet) {
+                Ok(()) => packet_info!(
+                    self,
+                    "{} [ {} ]: sent the FloodResponse to [ Node {} ]",
                     "✓".green(),
-                    self.id,
-                    dest_node,
+                    self.log_tag,
+                    dest_node
                 ),
                 Err(e) => {
                     error!(
-                        "{} [ ChatClient {} ]: Failed to send the FloodResponse to [ Node {} ]: {}",
+                        self,
+                        "{} [ {} ]: Failed to send the FloodResponse to [ Node {} ]: {}",
                         "✗".red(),
-                        self.id,
+                        self.log_tag,
                         dest_node,
                         e
                     );
 
-                    warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
+                    warn!(
+                        self,
+                        "├─>{} Sending to Simulation Controller...",
+                        "!!!".yellow()
+                    );
 
-                    self.controller_send
-                        .send(ChatClientEvent::ControllerShortcut(new_packet))
-                        .unwrap();
+                    self.send_event(ChatClientEvent::ControllerShortcut(e.0));
 
                     warn!(
-                        "└─>{} [ ChatClient {} ]: FloodResponse sent to Simulation Controller",
+                        self,
+                        "└─>{} [ {} ]: FloodResponse sent to Simulation Controller",
                         "!!!".yellow(),
-                        self.id
+                        self.log_tag
                     );
+                    self.neighbor_down(dest_node);
                 }
             }
         } else {
             // Handle the case where there is no connection to the destination drone
             error!(
-                "{} [ ChatClient {} ]: Failed to send the FloodResponse: No connection to [ Node {} ]",
+                self,
+                "{} [ {} ]: Failed to send the FloodResponse: No connection to [ Node {} ]",
                 "✗".red(),
-                self.id,
+                self.log_tag,
                 dest_node
             );
 
-            warn!("├─>{} Sending to Simulation Controller...", "!!!".yellow());
+            warn!(
+                self,
+                "├─>{} Sending to Simulation Controller...",
+                "!!!".yellow()
+            );
 
-            self.controller_send
-                .send(ChatClientEvent::ControllerShortcut(new_packet))
-                .unwrap();
+            self.send_event(ChatClientEvent::ControllerShortcut(new_packet));
 
             warn!(
-                "└─>{} [ ChatClient {} ]: FloodResponse sent to Simulation Controller",
+                self,
+                "└─>{} [ {} ]: FloodResponse sent to Simulation Controller",
                 "!!!".yellow(),
-                self.id
+                self.log_tag
             );
         }
     }
 
     fn process_flood_response(&mut self, flood_response: &FloodResponse) {
+        if !self.valid_path_trace(flood_response) {
+            return;
+        }
+        self.correlate_flood_response(flood_response);
         self.router.handle_flood_response(flood_response);
-        info!(
-            "{} [ ChatClient {} ]: Processed FloodResponse with flood_id: {}",
+        self.invalidate_routes();
+        self.topology.add_path_trace(&flood_response.path_trace);
+        self.links_seen(&flood_response.path_trace);
+        packet_info!(
+            self,
+            "{} [ {} ]: Processed FloodResponse with flood_id: {}",
             "✓".green(),
-            self.id,
+            self.log_tag,
             flood_response.flood_id
         );
+        self.check_lost_server();
+        self.flush_pending_messages();
+        self.resume_outbox();
+        self.flood_response_received(flood_response);
     }
 
-    fn process_fragment(&mut self, fragment: &Fragment, packet: &Packet) {
-        let mut path = packet.clone().routing_header.hops;
+    /// Acknowledges a received fragment and adds it to the
+    /// message it belongs to, unless its source is quarantined.
+    ///
+    /// Fragments of a source sending too fast are Nacked as `Dropped`
+    /// instead, to be sent again later.
+    fn process_fragment(&mut self, packet: Packet) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
 
-        path.reverse();
+        if self.throttled(&packet) {
+            let fragment_index = fragment.fragment_index;
+            let mut dropped = packet;
+            // the Nack starts at this client, which the header ends with
+            dropped.routing_header.hop_index += 1;
+            // failures are logged, the source retransmits once its Ack times out
+            let _ = self.send_nack(dropped, fragment_index, NackType::Dropped);
+            return;
+        }
 
-        let ack_packet = Packet {
-            routing_header: SourceRoutingHeader {
-                hop_index: 1,
-                hops: path,
-            },
-            session_id: packet.session_id,
-            pack_type: PacketType::Ack(Ack {
-                fragment_index: fragment.fragment_index,
-            }),
+        self.ack_fragment(
+            packet.session_id,
+            packet.routing_header.hops.iter().rev().copied().collect(),
+            fragment.fragment_index,
+        );
+
+        if !self.quarantined(&packet) {
+            self.accept_fragment(packet);
+        }
+    }
+
+    /// Adds an acknowledged fragment to the message it belongs to.
+    pub(super) fn accept_fragment(&mut self, packet: Packet) {
+        let PacketType::MsgFragment(fragment) = packet.pack_type else {
+            return;
         };
 
-        self.forward_packet(ack_packet);
+        let Some(source_id) = packet.routing_header.source() else {
+            return;
+        };
+        self.start_trace(
+            packet.session_id,
+            TraceStep::FragmentReceived {
+                fragment_index: fragment.fragment_index,
+                source: source_id,
+            },
+        );
 
-        let source_id = packet.routing_header.source().unwrap();
+        self.push_fragment(&fragment, packet.session_id, source_id);
+        while let Some((source_id, session_id, message)) = self.poll_complete() {
+            let Some(message) = message else {
+                continue;
+            };
+            self.trace(session_id, TraceStep::Reassembled);
+            self.flush_acks(source_id, session_id);
+            info!(self, "[ {} ] THERE IS A MESSAGE TO READ", self.log_tag);
+            self.message_buffer.push_back(message);
+            self.read_message();
+        }
+    }
 
-        if let Some(message) =
-            self.msgfactory
-                .received_fragment(fragment.clone(), packet.session_id, source_id)
+    /// Accounts for a fragment handed to the neighbour `next_hop`.
+    fn fragment_forwarded(
+        &mut self,
+        session_id: u64,
+        fragment_index: u64,
+        hops: &[NodeId],
+        next_hop: NodeId,
+    ) {
+        self.topology.fragment_sent(hops);
+        if let Some(&peer) = hops.last() {
+            self.packet_cache
+                .mark_sent(peer, session_id, fragment_index);
+        }
+        self.stats.fragments_sent += 1;
+        self.trace(
+            session_id,
+            TraceStep::FragmentSent {
+                fragment_index,
+                next_hop,
+            },
+        );
+    }
+
+    /// Settles the fragment an Ack refers to. Acks matching no fragment in
+    /// flight, stale, duplicated or misrouted, are only counted and logged.
+    fn process_ack(&mut self, ack: &Ack, packet: &Packet) {
+        self.stats.acks_received += 1;
+        let Some(peer) = packet.routing_header.source() else {
+            return;
+        };
+        if self
+            .packet_cache
+            .take(peer, packet.session_id, ack.fragment_index)
+            .is_none()
         {
-            info!("[CHATCLIENT {}] THERE IS A MESSAGE TO READ", self.id);
-            self.message_buffer.push(message);
-            self.read_message();
+            self.spurious_ack(ack, packet, peer);
+            return;
         }
 
-        let _ = self
-            .msgfactory
-            .take_packet(packet.session_id, fragment.fragment_index);
+        self.cancel_retransmission(peer, packet.session_id, ack.fragment_index);
+        self.shortcut_settled(peer, packet.session_id, ack.fragment_index, true);
+        self.progress_acked(peer, packet.session_id);
+        self.fragment_acked(peer, packet.session_id, ack.fragment_index);
+        self.trace(
+            packet.session_id,
+            TraceStep::Acked {
+                fragment_index: ack.fragment_index,
+            },
+        );
+        self.path_succeeded(peer);
+        self.fragment_settled(peer);
+        if !self.packet_cache.has_message(peer, packet.session_id) {
+            self.send_event(ChatClientEvent::MessageFullyAcked(packet.session_id, peer));
+        }
     }
-    #[allow(clippy::too_many_lines)]
-    fn process_nack(&mut self, nack: &Nack, packet: &Packet) {
-        let nack_src = packet.routing_header.source().unwrap();
 
-        match nack.clone().nack_type {
-            NackType::ErrorInRouting(unreachable_node) => {
-                error!(
-                    "{} [ ChatClient {} ]: Received a Nack indicating an error in the routing",
-                    "✗".red(),
-                    self.id
-                );
+    fn spurious_ack(&mut self, ack: &Ack, packet: &Packet, peer: NodeId) {
+        self.stats.spurious_acks += 1;
+        let header = &packet.routing_header;
+        let previous_hop = header
+            .hop_index
+            .checked_sub(1)
+            .and_then(|index| header.hops.get(index))
+            .map_or_else(|| "?".to_string(), ToString::to_string);
+        warn!(
+            self,
+            "{} [ {} ]: Ack from [ Node {} ] for fragment {} of session {} matches no fragment in flight, handed over by [ Node {} ] along {:?}",
+            "!!!".yellow(),
+            self.log_tag,
+            peer,
+            ack.fragment_index,
+            packet.session_id,
+            previous_hop,
+            header.hops
+        );
+    }
 
-                self.router.dropped_fragment(unreachable_node);
-
-                if let Some(incorrect_packet) = self
-                    .msgfactory
-                    .take_packet(packet.session_id, nack.fragment_index)
-                {
-                    let dest = incorrect_packet.routing_header.destination().unwrap();
-
-                    self.router.drone_crashed(unreachable_node);
-
-                    if let Ok(new_routing_header) = self.router.get_source_routing_header(dest) {
-                        let new_packet = Packet {
-                            routing_header: new_routing_header,
-                            ..incorrect_packet
-                        };
-                        self.msgfactory.insert_packet(&new_packet);
-
-                        info!(
-                            "{} [ ChatClient {} ]: Forwarding packet with session_id: {} and fragment_index: {} to [ CommunicationServer {} ]",
-                            "✓".green(),
-                            self.id,
-                            new_packet.session_id,
-                            nack.fragment_index,
-                            dest
-                        );
-
-                        self.forward_packet(new_packet);
-                    } else {
-                        self.forward_packet(incorrect_packet.clone());
-                        error!(
-                            "{} [ ChatClient {} ]: No path to destination [ CommunicationServer {} ]",
-                            "✗".red(),
-                            self.id,
-                            dest
-                        );
-                    }
-                }
-            }
-            NackType::DestinationIsDrone => {
-                // se la destinazione è un drone non sono in grado di risalire al vero destinatario è quindi impossibile inviare il messaggio
-                // non dovrebbe accadere in ogni caso
-                error!(
-                    "{} [ ChatClient {} ]: Received a Nack indicating that the destination is a drone",
-                    "✗".red(),
-                    self.id
-                );
-            }
-            NackType::Dropped => {
-                self.router.dropped_fragment(nack_src);
-
-                if let Some((dropped_packet, requests)) = self
-                    .msgfactory
-                    .get_packet(packet.session_id, nack.fragment_index)
-                {
-                    if requests > 30 {
-                        info!(
-                            "{} [ ChatClient {} ]: Reinitializing network due to excessive dropped requests",
-                            "ℹ".blue(),
-                            self.id
-                        );
-                        let requests = self.router.get_flood_requests(self.packet_send.len());
-                        for (sender, request) in self.packet_send.values().zip(requests) {
-                            if sender.send(request).is_err() {
-                                error!(
-                                    "{} [ ChatClient {} ]: Failed to send floodrequest",
-                                    "✓".green(),
-                                    self.id
-                                );
-                            }
-                        }
-                        thread::sleep(Duration::from_secs(2));
-                    }
-                    error!(
-                            "{} [ ChatClient {} ]: Packet with session_id: {} and fragment_index: {} has been dropped",
-                            "✗".red(),
-                            self.id,
-                            dropped_packet.session_id,
-                            nack.fragment_index
-                        );
-
-                    let destination = dropped_packet.routing_header.destination().unwrap();
-
-                    if let Ok(new_routing_header) =
-                        self.router.get_source_routing_header(destination)
-                    {
-                        let packet_to_resend = Packet {
-                            routing_header: new_routing_header,
-                            ..dropped_packet
-                        };
-
-                        self.msgfactory.insert_packet(&packet_to_resend);
-
-                        info!(
-                            "{} [ ChatClient {} ]: Forwarding packet with session_id: {} and fragment_index: {} to [ Server {} ]",
-                            "✓".green(),
-                            self.id,
-                            packet_to_resend.session_id,
-                            nack.fragment_index,
-                            destination
-                            );
-
-                        self.forward_packet(packet_to_resend);
-                    } else {
-                        self.forward_packet(dropped_packet.clone());
-                        error!(
-                                "{} [ ChatClient {} ]: No available path to destination [ CommunicationServer {} ]",
-                                "✗".red(),
-                                self.id,
-                                destination
-                            );
-                    }
-                }
-            }
-            NackType::UnexpectedRecipient(problematic_node) => {
-                self.router.dropped_fragment(problematic_node);
-                error!(
-                    "{} [ ChatClient {} ]: Received a Nack indicating that the recipient was unexpected",
-                    "✗".red(),
-                    self.id
-                );
+    pub(super) fn reroute_packet(&mut self, packet: Packet) {
+        let Some(destination) = packet.routing_header.destination() else {
+            error!(
+                self,
+                "{} [ {} ]: Cannot resend packet with session_id: {}, empty routing header",
+                "✗".red(),
+                self.log_tag,
+                packet.session_id
+            );
+            return;
+        };
+        self.stats.retransmissions += 1;
+
+        if let Some(new_routing_header) = self.route_to(destination) {
+            let packet_to_resend = Packet {
+                routing_header: new_routing_header,
+                ..packet
+            };
+
+            self.packet_cache.insert(&packet_to_resend);
+            self.stats.reroutes += 1;
+            self.emit_telemetry(TelemetryKind::Rerouted {
+                session_id: packet_to_resend.session_id,
+                destination,
+            });
+
+            packet_info!(
+                self,
+                "{} [ {} ]: Forwarding packet with session_id: {} to [ Server {} ]",
+                "✓".green(),
+                self.log_tag,
+                packet_to_resend.session_id,
+                destination
+            );
 
-                if let Some(incorrect_packet) = self
-                    .msgfactory
-                    .take_packet(packet.session_id, nack.fragment_index)
-                {
-                    let dest = incorrect_packet.routing_header.destination().unwrap();
-
-                    if let Ok(new_routing_header) = self.router.get_source_routing_header(dest) {
-                        let new_packet = Packet {
-                            pack_type: incorrect_packet.pack_type,
-                            routing_header: new_routing_header,
-                            session_id: incorrect_packet.session_id,
-                        };
-                        self.msgfactory.insert_packet(&new_packet);
-                        info!(
-                            "{} [ ChatClient {} ]: Forwarding packet with session_id: {} and fragment_index: {} to [ Server {} ]",
-                            "✓".green(),
-                            self.id,
-                            new_packet.session_id,
-                            nack.fragment_index,
-                            dest
-                        );
-
-                        self.forward_packet(new_packet);
-                    } // this the case where a drone receives a packet and hops[hop_index] is not equal to the drone id
-                }
-            }
+            let _ = self.forward_packet(packet_to_resend);
+        } else {
+            let _ = self.forward_packet(packet);
+            error!(
+                self,
+                "{} [ {} ]: No available path to destination [ CommunicationServer {} ]",
+                "✗".red(),
+                self.log_tag,
+                destination
+            );
+            self.routing_failed(destination);
         }
     }
 }
@@ -0,0 +1,208 @@
+use colored::Colorize;
+use messages::client_commands::{TelemetryKind, TraceStep};
+use wg_2024::{
+    network::NodeId,
+    packet::{Nack, NackType, Packet},
+};
+
+use crate::{chat_client::nack_strategy::NackAction, ChatClient};
+
+impl ChatClient {
+    /// Learns from a Nack what it says about the network, then lets the
+    /// `NackStrategy` decide what happens to the Nacked fragment.
+    pub(super) fn process_nack(&mut self, nack: &Nack, packet: &Packet) {
+        let Some(nack_src) = packet.routing_header.source() else {
+            return;
+        };
+        let peer = self
+            .packet_cache
+            .nacked_peer(packet.session_id, nack.fragment_index, nack_src);
+
+        self.count_nack(&nack.nack_type);
+        self.emit_telemetry(TelemetryKind::NackReceived {
+            session_id: packet.session_id,
+            fragment_index: nack.fragment_index,
+            nack_type: format!("{:?}", nack.nack_type),
+        });
+        self.trace(
+            packet.session_id,
+            TraceStep::Nacked {
+                fragment_index: nack.fragment_index,
+                nack_type: format!("{:?}", nack.nack_type),
+            },
+        );
+
+        match nack.nack_type {
+            NackType::ErrorInRouting(unreachable_node) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Received a Nack indicating an error in the routing",
+                    "✗".red(),
+                    self.log_tag
+                );
+
+                self.router.dropped_fragment(unreachable_node);
+                if let Some(peer) = peer {
+                    self.path_failed(peer);
+                    self.link_failed(nack_src, unreachable_node);
+                }
+                self.invalidate_routes();
+            }
+            NackType::DestinationIsDrone => {
+                error!(
+                    self,
+                    "{} [ {} ]: Received a Nack indicating that the destination is a drone",
+                    "✗".red(),
+                    self.log_tag
+                );
+                if let Some(peer) = peer {
+                    self.route_ended_at_drone(peer, packet.session_id, nack.fragment_index);
+                }
+            }
+            NackType::Dropped => {
+                self.router.dropped_fragment(nack_src);
+                self.invalidate_routes();
+                // the dropper reports the Nack itself, the fragment was lost
+                // on the link it arrived from along the route it was sent on
+                let previous = peer
+                    .and_then(|peer| {
+                        let hops = self.packet_cache.route_of(
+                            peer,
+                            packet.session_id,
+                            nack.fragment_index,
+                        )?;
+                        let position = hops.iter().position(|&hop| hop == nack_src)?;
+                        position.checked_sub(1).map(|previous| hops[previous])
+                    })
+                    .or_else(|| packet.routing_header.hops.get(1).copied());
+                if let Some(previous) = previous {
+                    self.topology.fragment_dropped(previous, nack_src);
+                }
+
+                if let Some(peer) = peer {
+                    self.path_failed(peer);
+                }
+            }
+            NackType::UnexpectedRecipient(problematic_node) => {
+                self.router.dropped_fragment(problematic_node);
+                self.invalidate_routes();
+                error!(
+                    self,
+                    "{} [ {} ]: Received a Nack indicating that the recipient was unexpected",
+                    "✗".red(),
+                    self.log_tag
+                );
+            }
+        }
+
+        // fragments are cached under their destination
+        if let Some(peer) = peer {
+            self.shortcut_settled(peer, packet.session_id, nack.fragment_index, false);
+            self.react_to_nack(
+                peer,
+                packet.session_id,
+                &nack.nack_type,
+                nack.fragment_index,
+            );
+        }
+    }
+
+    /// Recovers from a fragment for `peer` that reached a drone at the end
+    /// of its route.
+    ///
+    /// The destination of the cached fragment is what the message was
+    /// meant for, so unless the topology says it is a drone, the route was
+    /// stale or wrong. The fragment is then rerouted by the `NackStrategy`
+    /// along a route computed anew, and the message is only given up when
+    /// the destination itself is a drone.
+    fn route_ended_at_drone(&mut self, peer: NodeId, session_id: u64, fragment_index: u64) {
+        if self.topology.is_drone(peer) {
+            error!(
+                self,
+                "{} [ {} ]: [ Node {} ] is a drone, message with session_id: {} cannot be delivered",
+                "✗".red(),
+                self.log_tag,
+                peer,
+                session_id
+            );
+            self.give_up_message(peer, session_id);
+            return;
+        }
+
+        let hops = self
+            .packet_cache
+            .route_of(peer, session_id, fragment_index)
+            .map(<[NodeId]>::to_vec)
+            .unwrap_or_default();
+        warn!(
+            self,
+            "{} [ {} ]: Route {:?} toward [ Node {} ] ended at a drone, computing a new one",
+            "!!!".yellow(),
+            self.log_tag,
+            hops,
+            peer
+        );
+        if self.is_pinned(peer) {
+            self.unpin_route(peer);
+        }
+        self.invalidate_routes();
+        self.path_failed(peer);
+    }
+
+    /// Applies the `NackStrategy` to the fragment a Nack refers to.
+    fn react_to_nack(
+        &mut self,
+        peer: NodeId,
+        session_id: u64,
+        nack_type: &NackType,
+        fragment_index: u64,
+    ) {
+        if self.retransmission_scheduled(peer, session_id, fragment_index) {
+            warn!(
+                self,
+                "{} [ {} ]: Ignoring Nack for packet with session_id: {} and fragment_index: {}, already scheduled for retransmission",
+                "!!!".yellow(),
+                self.log_tag,
+                session_id,
+                fragment_index
+            );
+            return;
+        }
+        let Some((nacked_packet, nacks)) =
+            self.packet_cache.nacked(peer, session_id, fragment_index)
+        else {
+            return;
+        };
+
+        match self.nack_strategy.on_nack(nack_type, nacks) {
+            NackAction::RetrySamePath => {
+                self.schedule_retransmission(peer, fragment_index, nacked_packet, nacks, false);
+            }
+            NackAction::Reroute => {
+                self.schedule_retransmission(peer, fragment_index, nacked_packet, nacks, true);
+            }
+            NackAction::Reflood => {
+                info!(
+                    self,
+                    "{} [ {} ]: Reinitializing network due to excessive dropped requests",
+                    "ℹ".blue(),
+                    self.log_tag
+                );
+                self.start_flood();
+                self.schedule_retransmission(peer, fragment_index, nacked_packet, nacks, true);
+            }
+            NackAction::GiveUp => {
+                error!(
+                    self,
+                    "{} [ {} ]: Giving up on packet with session_id: {} and fragment_index: {} after {} Nacks",
+                    "✗".red(),
+                    self.log_tag,
+                    session_id,
+                    fragment_index,
+                    nacks
+                );
+                self.give_up_message(peer, session_id);
+            }
+        }
+    }
+}
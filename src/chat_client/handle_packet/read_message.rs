@@ -1,131 +1,308 @@
 use colored::Colorize;
-use log::{error, info};
 use messages::{
-    client_commands::ChatClientEvent,
-    high_level_messages::{MessageContent, ServerMessage, ServerType},
+    client_commands::{ChatClientEvent, MessageDirection},
+    high_level_messages::{Message, MessageContent, ServerMessage, ServerType},
 };
 
-use crate::ChatClient;
+use wg_2024::network::NodeId;
+
+use crate::{
+    chat_client::{
+        control_requests::ControlRequest, payload::Payload, server_health::ServerHealth,
+        transfer::FileManifest,
+    },
+    ChatClient,
+};
 
 impl ChatClient {
-    #[allow(clippy::too_many_lines)]
+    /// Reads every reassembled message, in the order they were completed.
+    ///
+    /// Reading a message may complete others, for instance by releasing
+    /// quarantined fragments, which are then read by the same drain.
     pub(super) fn read_message(&mut self) {
-        if let Some(message) = self.message_buffer.pop() {
-            if message.destination_id != self.id {
-                //destinazione sbagliata
-                error!(
-                    "{} [ ChatClient {} ]: Received a message with incorrect destination ID: {}",
-                    "✗".red(),
-                    self.id,
-                    message.destination_id
-                );
-                return;
-            }
-            if let MessageContent::FromServer(server_message) = message.content {
-                match server_message {
-                    ServerMessage::ServerType(server_type) => {
-                        if let ServerType::Chat = server_type {
-                            self.communication_server_list.push(message.source_id);
-                            info!(
-                                "{} [ ChatClient {} ]: Discovered communication server [ CommunicationServer {} ]",
-                                "✓".green(),
-                                self.id,
-                                message.source_id
-                            );
-                        }
-                    }
-                    ServerMessage::ClientList(client_list) => {
-                        self.client_list = client_list;
+        if self.message_buffer.is_empty() {
+            info!(
+                self,
+                "{} [ {} ]: No messages to read",
+                "ℹ".blue(),
+                self.log_tag
+            );
+            return;
+        }
+        while let Some(message) = self.message_buffer.pop_front() {
+            self.read_one_message(message);
+        }
+    }
 
+    #[allow(clippy::too_many_lines)]
+    fn read_one_message(&mut self, message: Message) {
+        if message.destination_id != self.id {
+            //destinazione sbagliata
+            error!(
+                self,
+                "{} [ {} ]: Received a message with incorrect destination ID: {}",
+                "✗".red(),
+                self.log_tag,
+                message.destination_id
+            );
+            return;
+        }
+        if let MessageContent::FromServer(server_message) = message.content {
+            self.server_answered(message.source_id);
+            match server_message {
+                ServerMessage::ServerType(server_type) => {
+                    self.server_type_received(message.source_id);
+                    // servers already known only get their entry refreshed
+                    if matches!(server_type, ServerType::Chat)
+                        && self.communication_server_list.confirmed(message.source_id)
+                    {
                         info!(
-                            "{} [ ChatClient {} ]: Updated client list: {:?}",
-                            "ℹ".blue(),
-                            self.id,
-                            self.client_list
+                            self,
+                            "{} [ {} ]: Discovered communication server [ CommunicationServer {} ]",
+                            "✓".green(),
+                            self.log_tag,
+                            message.source_id
                         );
-
-                        self.controller_send
-                            .send(ChatClientEvent::ClientList(
-                                self.id,
-                                self.client_list.clone(),
-                            ))
-                            .unwrap();
                     }
-                    ServerMessage::MessageReceived { sender_id, content } => {
+                }
+                ServerMessage::ClientList(mut client_list) => {
+                    client_list.retain(|&id| id != self.id);
+                    self.communication_server_list
+                        .clients_listed(message.source_id, client_list.len());
+                    let (newcomers, departed) = self.peers.listed(message.source_id, &client_list);
+                    self.announce_username(&newcomers);
+                    for peer in departed {
                         info!(
-                            "{} [ ChatClient {} ]: Message received from [ Client {} ]: {}",
-                            "✓".green(),
-                            self.id,
-                            sender_id,
-                            content
+                            self,
+                            "{} [ {} ]: [ Client {} ] left [ CommunicationServer {} ]",
+                            "ℹ".blue(),
+                            self.log_tag,
+                            peer,
+                            message.source_id
                         );
-
-                        self.controller_send
-                            .send(ChatClientEvent::MessageReceived(
-                                sender_id, self.id, content,
-                            ))
-                            .unwrap();
+                        self.send_event(ChatClientEvent::PeerLeft(peer));
                     }
-                    ServerMessage::UnreachableClient(client_id) => {
+
+                    info!(
+                        self,
+                        "{} [ {} ]: Updated client list: {:?}",
+                        "ℹ".blue(),
+                        self.log_tag,
+                        client_list
+                    );
+
+                    self.send_event(ChatClientEvent::ClientList(self.id, self.peers.save()));
+                }
+                ServerMessage::MessageReceived { sender_id, content } => {
+                    if self.is_blocked(sender_id) {
                         info!(
-                            "{} [ ChatClient {} ]: Client {} is unreachable",
-                            "!!!".yellow(),
-                            self.id,
-                            client_id
+                            self,
+                            "{} [ {} ]: Dropping message from blocked [ Client {} ]",
+                            "ℹ".blue(),
+                            self.log_tag,
+                            sender_id
                         );
+                        return;
+                    }
+                    self.peers.seen(sender_id, message.source_id);
+                    self.content_received(sender_id, content);
+                }
+                ServerMessage::UnreachableClient(client_id) => {
+                    info!(
+                        self,
+                        "{} [ {} ]: Client {} is unreachable",
+                        "!!!".yellow(),
+                        self.log_tag,
+                        client_id
+                    );
 
-                        self.client_list.retain(|&id| id != client_id);
+                    self.peers.remove(client_id);
+                    self.schedule_client_list_refresh(&format!(
+                        "client {client_id} is unreachable"
+                    ));
 
-                        self.controller_send
-                            .send(ChatClientEvent::UnreachableClient(client_id))
-                            .unwrap();
+                    self.send_event(ChatClientEvent::UnreachableClient(client_id));
+                    self.send_event(ChatClientEvent::ClientList(self.id, self.peers.save()));
+                }
+                ServerMessage::SuccessfulRegistration => {
+                    if !self.control_answered(
+                        message.source_id,
+                        message.session_id,
+                        ControlRequest::Registration,
+                    ) {
+                        return;
                     }
-                    ServerMessage::SuccessfulRegistration => {
-                        self.registered = Some(message.source_id);
-                        info!(
-                            "{} [ ChatClient {} ]: Successfully registered to the server [ CommunicationServer {} ]",
-                            "✓".green(),
-                            self.id,
-                            message.source_id
-                        );
-                        self.controller_send
-                            .send(ChatClientEvent::SuccessfulRegistration(message.source_id))
-                            .unwrap();
+                    self.registered = Some(message.source_id);
+                    self.server_health = ServerHealth::default();
+                    info!(
+                        self,
+                        "{} [ {} ]: Successfully registered to the server [ CommunicationServer {} ]",
+                        "✓".green(),
+                        self.log_tag,
+                        message.source_id
+                    );
+                    self.send_event(ChatClientEvent::SuccessfulRegistration(message.source_id));
+                    self.registration_confirmed(message.source_id);
+                    self.bot_think();
+                    self.flush_pending_messages();
+                }
+                ServerMessage::SuccessfullLogOut => {
+                    if !self.control_answered(
+                        message.source_id,
+                        message.session_id,
+                        ControlRequest::Logout,
+                    ) {
+                        return;
                     }
-                    ServerMessage::SuccessfullLogOut => {
-                        self.registered = None;
+                    if self.registered != Some(message.source_id) {
+                        // the server left behind when moving to another one
                         info!(
-                            "{} [ ChatClient {} ]: Successfully logged out from the server [ CommunicationServer {} ]",
-                            "✓".green(),
-                            self.id,
+                            self,
+                            "{} [ {} ]: Logged out from the previous server [ CommunicationServer {} ]",
+                            "ℹ".blue(),
+                            self.log_tag,
                             message.source_id
                         );
-                        self.controller_send
-                            .send(ChatClientEvent::SuccessfulLogOut)
-                            .unwrap();
-                    }
-                    _ => {
-                        error!(
-                            "{} [ ChatClient {} ]: Received a message intended for a web browser",
-                            "✗".red(),
-                            self.id
-                        );
+                        return;
                     }
+                    self.registered = None;
+                    info!(
+                        self,
+                        "{} [ {} ]: Successfully logged out from the server [ CommunicationServer {} ]",
+                        "✓".green(),
+                        self.log_tag,
+                        message.source_id
+                    );
+                    self.send_event(ChatClientEvent::SuccessfulLogOut);
+                }
+                _ => {
+                    error!(
+                        self,
+                        "{} [ {} ]: Received a message intended for a web browser",
+                        "✗".red(),
+                        self.log_tag
+                    );
                 }
-            } else {
-                error!(
-                    "{} [ ChatClient {} ]: Received a message from an unexpected source: [ Client {} ]",
-                    "✗".red(),
-                    self.id,
-                    message.source_id
-                );
             }
         } else {
-            info!(
-                "{} [ ChatClient {} ]: No messages to read",
-                "ℹ".blue(),
-                self.id
+            error!(
+                self,
+                "{} [ {} ]: Received a message from an unexpected source: [ Client {} ]",
+                "✗".red(),
+                self.log_tag,
+                message.source_id
             );
         }
     }
+
+    /// Reads a content relayed from another client, either plain chat text
+    /// or a `Payload`.
+    pub(in crate::chat_client) fn content_received(&mut self, sender_id: NodeId, content: String) {
+        let Some(content) = self.decompressed(sender_id, content) else {
+            return;
+        };
+        if let Some(payload) = Payload::decode(&content) {
+            self.read_payload(sender_id, payload);
+            return;
+        }
+        self.chat_text_received(sender_id, None, content);
+    }
+
+    /// Reads plain chat text, sent at `sent_at` on the sender's clock if
+    /// it carried a timestamp.
+    pub(in crate::chat_client) fn chat_text_received(
+        &mut self,
+        sender_id: NodeId,
+        sent_at: Option<u64>,
+        content: String,
+    ) {
+        info!(
+            self,
+            "{} [ {} ]: Message received from [ Client {} ]: {}",
+            "✓".green(),
+            self.log_tag,
+            sender_id,
+            content
+        );
+
+        let Some(timestamps) =
+            self.conversation_received(sender_id, None, sent_at, content.clone())
+        else {
+            return;
+        };
+        self.count_unread(sender_id);
+        self.bot_think();
+        self.auto_reply(sender_id, &content);
+        self.history.record_at(
+            timestamps.normalized,
+            sender_id,
+            MessageDirection::Received,
+            content.clone(),
+        );
+        self.send_event(ChatClientEvent::MessageReceived(
+            sender_id, self.id, content, timestamps,
+        ));
+    }
+
+    pub(in crate::chat_client) fn read_payload(&mut self, sender_id: NodeId, payload: Payload) {
+        match payload {
+            Payload::JoinRoom { room_id } => self.room_member_joined(sender_id, room_id),
+            Payload::LeaveRoom { room_id } => self.room_member_left(sender_id, &room_id),
+            Payload::RoomMessage { room_id, content } => {
+                self.room_message_received(sender_id, room_id, content);
+            }
+            Payload::FileManifest {
+                transfer_id,
+                name,
+                size,
+                chunks,
+                checksum,
+            } => self.file_manifest_received(
+                sender_id,
+                transfer_id,
+                FileManifest {
+                    name,
+                    size,
+                    chunks,
+                    checksum,
+                },
+            ),
+            Payload::FileChunk {
+                transfer_id,
+                index,
+                data,
+            } => self.file_chunk_received(sender_id, transfer_id, index, data),
+            Payload::Status { status } => self.peer_status_received(sender_id, status),
+            Payload::Typing => self.peer_typing(sender_id),
+            Payload::Username { username } => self.username_received(sender_id, &username),
+            Payload::ChatMessage { sequence, content } => {
+                self.sequenced_message_received(sender_id, sequence, None, content);
+            }
+            Payload::Timestamped { sent_at, content } => {
+                self.timestamped_received(sender_id, sent_at, content);
+            }
+            Payload::Checked {
+                message_id,
+                checksum,
+                content,
+            } => self.checked_content_received(sender_id, message_id, checksum, content),
+            Payload::ResendRequest { message_id } => self.resend_requested(sender_id, message_id),
+            Payload::ChatChunk {
+                message_id,
+                index,
+                total,
+                content,
+            } => self.chunk_received(sender_id, message_id, index, total, content),
+            Payload::Ping { probe_id } => self.ping_received(sender_id, probe_id),
+            Payload::Pong { probe_id } => self.pong_received(sender_id, probe_id),
+            Payload::RichMessage {
+                message_id,
+                payload,
+            } => self.rich_message_received(sender_id, message_id, payload),
+            Payload::AutoReply { content } => self.auto_reply_received(sender_id, content),
+            Payload::PersonaMessage { from, to, content } => {
+                self.persona_message_received(sender_id, from, to, content);
+            }
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+use messages::client_commands::TelemetryKind;
+use wg_2024::packet::{FloodResponse, NackType, Packet, PacketType};
+
+use crate::ChatClient;
+
+impl ChatClient {
+    /// Whether the routing header of a received packet can be followed: a
+    /// hop index pointing inside the hop list, past its source.
+    ///
+    /// Malformed fragments are answered with a Nack when the header still
+    /// tells which node they came from; everything else is only logged.
+    pub(super) fn well_formed(&mut self, packet: &Packet) -> bool {
+        let header = &packet.routing_header;
+        if header.hop_index > 0 && header.hop_index < header.hops.len() {
+            return true;
+        }
+
+        error!(
+            self,
+            "{} [ {} ]: Discarding {} with malformed routing header: {}",
+            "✗".red(),
+            self.log_tag,
+            packet.pack_type,
+            header
+        );
+
+        if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+            if (1..=header.hops.len()).contains(&header.hop_index) {
+                // failures are logged, the packet is discarded either way
+                let _ = self.send_nack(
+                    packet.clone(),
+                    fragment.fragment_index,
+                    NackType::UnexpectedRecipient(self.id),
+                );
+            }
+        }
+
+        false
+    }
+
+    /// Whether the path trace of a `FloodResponse` can describe a route
+    /// from this client: not empty, going through this client, and never
+    /// visiting a node twice.
+    ///
+    /// Rejected responses are logged and reported as a
+    /// `TelemetryKind::FloodResponseRejected`, and never reach the router.
+    pub(super) fn valid_path_trace(&self, flood_response: &FloodResponse) -> bool {
+        let path_trace = &flood_response.path_trace;
+        let mut visited = HashSet::new();
+        let problem = if path_trace.is_empty() {
+            Some("empty path trace".to_string())
+        } else if !path_trace.iter().any(|&(node_id, _)| node_id == self.id) {
+            Some("path trace does not go through this client".to_string())
+        } else {
+            path_trace
+                .iter()
+                .find(|&&(node_id, _)| !visited.insert(node_id))
+                .map(|(node_id, _)| format!("path trace visits node {node_id} twice"))
+        };
+
+        let Some(reason) = problem else {
+            return true;
+        };
+        warn!(
+            self,
+            "{} [ {} ]: Discarding FloodResponse with flood_id {}: {}",
+            "!!!".yellow(),
+            self.log_tag,
+            flood_response.flood_id,
+            reason
+        );
+        self.emit_telemetry(TelemetryKind::FloodResponseRejected {
+            flood_id: flood_response.flood_id,
+            reason,
+        });
+        false
+    }
+}
@@ -0,0 +1,25 @@
+use messages::client_commands::{ChatClientEvent, QueueDepths};
+
+use super::{send_window::SendWindow, ChatClient};
+
+impl ChatClient {
+    /// Tells the controller that the event loop is alive, along with what
+    /// it is waiting on, so that a wedged client can be told apart from an
+    /// idle one.
+    pub(super) fn report_health(&self) {
+        let queue_depths = QueueDepths {
+            commands: self.controller_recv.len(),
+            packets: self.packet_recv.len(),
+            pending_messages: self.pending_messages.len(),
+            undispatched_fragments: self.undispatched(),
+            windowed_fragments: self.send_windows.values().map(SendWindow::queued).sum(),
+        };
+        self.send_event(ChatClientEvent::Healthy {
+            uptime: self.created_at.elapsed(),
+            queue_depths,
+            registered: self.registered,
+            known_servers: self.communication_server_list.ids(),
+            pending_fragments: self.packet_cache.len(),
+        });
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, HistoryEntry, HistoryFormat, MessageDirection};
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// Log of every chat message sent or received by a `ChatClient`.
+///
+/// It is loaded from the client's `Storage` on creation and written back
+/// to it when the client shuts down.
+#[derive(Debug, Default)]
+pub(super) struct MessageHistory {
+    entries: Vec<HistoryEntry>,
+}
+
+impl MessageHistory {
+    /// Replaces the entries by the ones loaded from the storage.
+    pub(super) fn load(&mut self, entries: Vec<HistoryEntry>) {
+        self.entries = entries;
+    }
+
+    pub(super) fn record(&mut self, peer: NodeId, direction: MessageDirection, content: String) {
+        self.record_at(now_millis(), peer, direction, content);
+    }
+
+    /// Logs a message at `timestamp` instead of now, after the entries of
+    /// the same time or earlier.
+    pub(super) fn record_at(
+        &mut self,
+        timestamp: u64,
+        peer: NodeId,
+        direction: MessageDirection,
+        content: String,
+    ) {
+        let position = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.timestamp <= timestamp)
+            .map_or(0, |position| position + 1);
+        self.entries.insert(
+            position,
+            HistoryEntry {
+                timestamp,
+                peer,
+                direction,
+                content,
+            },
+        );
+    }
+
+    pub(super) fn with_peer(&self, peer: NodeId) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.peer == peer)
+            .cloned()
+            .collect()
+    }
+
+    pub(super) fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Writes every entry to `path`.
+    pub(super) fn export(&self, format: HistoryFormat, path: &Path) -> io::Result<()> {
+        let exported = match format {
+            HistoryFormat::Json => serde_json::to_string_pretty(&self.entries)?,
+            HistoryFormat::Plaintext => self.entries.iter().map(transcript_line).collect(),
+        };
+        fs::write(path, exported)
+    }
+
+    /// Adds the entries read from `path` that are not in the history yet,
+    /// keeping the history in chronological order.
+    ///
+    /// # Returns
+    ///
+    /// The number of entries added.
+    pub(super) fn import(&mut self, format: HistoryFormat, path: &Path) -> io::Result<usize> {
+        let read = fs::read_to_string(path)?;
+        let imported: Vec<HistoryEntry> = match format {
+            HistoryFormat::Json => serde_json::from_str(&read)?,
+            HistoryFormat::Plaintext => read
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(parse_transcript_line)
+                .collect::<io::Result<_>>()?,
+        };
+
+        let mut known: HashSet<_> = self.entries.iter().map(dedup_key).collect();
+        let before = self.entries.len();
+        self.entries.extend(
+            imported
+                .into_iter()
+                .filter(|entry| known.insert(dedup_key(entry))),
+        );
+        // stable, so entries of the same millisecond keep their order
+        self.entries.sort_by_key(|entry| entry.timestamp);
+        Ok(self.entries.len() - before)
+    }
+}
+
+/// Entries are the same message when they were logged at the same time,
+/// with the same peer and in the same direction, with the same content.
+fn dedup_key(entry: &HistoryEntry) -> (u64, NodeId, bool, String) {
+    (
+        entry.timestamp,
+        entry.peer,
+        entry.direction == MessageDirection::Sent,
+        entry.content.clone(),
+    )
+}
+
+/// A line of a plaintext transcript, `<timestamp> to|from <peer>: <content>`
+/// with the backslashes and line breaks of the content escaped.
+fn transcript_line(entry: &HistoryEntry) -> String {
+    let direction = match entry.direction {
+        MessageDirection::Sent => "to",
+        MessageDirection::Received => "from",
+    };
+    let content = entry
+        .content
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!(
+        "{} {} {}: {}\n",
+        entry.timestamp, direction, entry.peer, content
+    )
+}
+
+fn parse_transcript_line(line: &str) -> io::Result<HistoryEntry> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("not a transcript line: {line}"),
+        )
+    };
+
+    let (header, content) = line.split_once(": ").ok_or_else(invalid)?;
+    let mut fields = header.split(' ');
+    let timestamp = fields.next().and_then(|field| field.parse().ok());
+    let direction = match fields.next() {
+        Some("to") => Some(MessageDirection::Sent),
+        Some("from") => Some(MessageDirection::Received),
+        _ => None,
+    };
+    let peer = fields.next().and_then(|field| field.parse().ok());
+    let (Some(timestamp), Some(direction), Some(peer), None) =
+        (timestamp, direction, peer, fields.next())
+    else {
+        return Err(invalid());
+    };
+
+    let mut unescaped = String::with_capacity(content.len());
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(HistoryEntry {
+        timestamp,
+        peer,
+        direction,
+        content: unescaped,
+    })
+}
+
+/// Milliseconds since the Unix epoch.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| {
+            u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+        })
+}
+
+impl ChatClient {
+    /// Writes the whole history to `path`, for offline analysis.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub(super) fn export_history(&self, format: HistoryFormat, path: PathBuf) -> io::Result<()> {
+        self.history.export(format, &path)?;
+        let entries = self.history.entries().len();
+        info!(
+            self,
+            "{} [ {} ]: Exported {} history entries to {}",
+            "✓".green(),
+            self.log_tag,
+            entries,
+            path.display()
+        );
+        self.send_event(ChatClientEvent::HistoryExported(path, entries));
+        Ok(())
+    }
+
+    /// Merges the history exported to `path` into the current one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not an exported
+    /// history.
+    pub(super) fn import_history(
+        &mut self,
+        format: HistoryFormat,
+        path: PathBuf,
+    ) -> io::Result<()> {
+        let added = self.history.import(format, &path)?;
+        info!(
+            self,
+            "{} [ {} ]: Imported {} new history entries from {}",
+            "✓".green(),
+            self.log_tag,
+            added,
+            path.display()
+        );
+        self.send_event(ChatClientEvent::HistoryImported(path, added));
+        Ok(())
+    }
+}
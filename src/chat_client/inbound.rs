@@ -0,0 +1,293 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::{client_commands::ChatClientEvent, high_level_messages::Message};
+use wg_2024::{
+    network::NodeId,
+    packet::{Fragment, FRAGMENT_DSIZE},
+};
+
+use super::{reassembly::fragment_of, ChatClient};
+
+/// Time after which the fragments of a message that never completed are
+/// discarded.
+const REASSEMBLY_TTL: Duration = Duration::from_secs(90);
+
+/// The fragments of a message received so far.
+#[derive(Debug)]
+struct PartialMessage {
+    fragments: BTreeMap<u64, Vec<u8>>,
+    total: u64,
+    bytes: usize,
+    started: Instant,
+}
+
+/// What became of a fragment handed to [`InboundAssembler::push_fragment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Pushed {
+    /// The fragment was added to its message.
+    Added,
+    /// The fragment was added, but declares a total different from the
+    /// fragments before it. `awaited` is the largest total declared.
+    TotalChanged { previous: u64, awaited: u64 },
+    /// The message was already completed, the fragment is a late duplicate.
+    Duplicate,
+    /// The index of the fragment is not below the total it declares.
+    OutOfRange,
+}
+
+/// An incomplete message evicted because its source holds too many.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Evicted {
+    pub(super) session_id: u64,
+    /// Incomplete messages the source held before the eviction.
+    pub(super) sessions: usize,
+    /// Bytes the source buffered before the eviction.
+    pub(super) bytes: usize,
+}
+
+/// Puts incoming messages back together, keyed by source and session id.
+///
+/// Fragments may arrive in any order. Senders may split their messages in
+/// fragments smaller than the protocol's 128 bytes, so fragments are joined
+/// in index order by their declared length. A fragment declaring a total
+/// different from the others' does not cut the message short: the largest
+/// total is awaited.
+///
+/// Completed messages are handed out by [`InboundAssembler::poll_complete`]
+/// in the order they completed, and incomplete ones are dropped by
+/// [`InboundAssembler::purge_stale`] once they are too old.
+#[derive(Debug, Default)]
+pub(super) struct InboundAssembler {
+    partial: HashMap<(NodeId, u64), PartialMessage>,
+    /// Messages already put back together, whose late duplicates are
+    /// only acknowledged.
+    completed: HashMap<(NodeId, u64), Instant>,
+    /// The data of the completed messages not polled yet.
+    ready: VecDeque<(NodeId, u64, Vec<u8>)>,
+}
+
+impl InboundAssembler {
+    /// Adds `fragment` to the message `session_id` of `source_id`, moving
+    /// the message to the completed ones once all of its fragments arrived.
+    pub(super) fn push_fragment(
+        &mut self,
+        source_id: NodeId,
+        session_id: u64,
+        fragment: &Fragment,
+    ) -> Pushed {
+        let key = (source_id, session_id);
+        if self.completed.contains_key(&key) {
+            return Pushed::Duplicate;
+        }
+        if fragment.fragment_index >= fragment.total_n_fragments {
+            return Pushed::OutOfRange;
+        }
+
+        let length = usize::from(fragment.length).min(FRAGMENT_DSIZE);
+        let partial = self.partial.entry(key).or_insert_with(|| PartialMessage {
+            fragments: BTreeMap::new(),
+            total: fragment.total_n_fragments,
+            bytes: 0,
+            started: Instant::now(),
+        });
+
+        let previous = partial.total;
+        partial.total = partial.total.max(fragment.total_n_fragments);
+        if let Some(replaced) = partial
+            .fragments
+            .insert(fragment.fragment_index, fragment.data[..length].to_vec())
+        {
+            partial.bytes -= replaced.len();
+        }
+        partial.bytes += length;
+        let awaited = partial.total;
+        let complete = (0..awaited).all(|index| partial.fragments.contains_key(&index));
+
+        if complete {
+            if let Some(partial) = self.partial.remove(&key) {
+                self.completed.insert(key, Instant::now());
+                let data = partial.fragments.into_values().flatten().collect();
+                self.ready.push_back((source_id, session_id, data));
+            }
+        }
+        if fragment.total_n_fragments == previous {
+            Pushed::Added
+        } else {
+            Pushed::TotalChanged { previous, awaited }
+        }
+    }
+
+    /// Takes the oldest completed message not polled yet, with its source
+    /// and session id.
+    pub(super) fn poll_complete(&mut self) -> Option<(NodeId, u64, Vec<u8>)> {
+        self.ready.pop_front()
+    }
+
+    /// Drops the incomplete messages started at least `ttl` ago, and
+    /// forgets the completed ones as old.
+    ///
+    /// # Returns
+    ///
+    /// The source and session id of every message dropped.
+    pub(super) fn purge_stale(&mut self, ttl: Duration) -> Vec<(NodeId, u64)> {
+        self.completed
+            .retain(|_, completed_at| completed_at.elapsed() < ttl);
+        let stale: Vec<(NodeId, u64)> = self
+            .partial
+            .iter()
+            .filter(|(_, partial)| partial.started.elapsed() >= ttl)
+            .map(|(&key, _)| key)
+            .collect();
+        for key in &stale {
+            self.partial.remove(key);
+        }
+        stale
+    }
+
+    /// Evicts the oldest incomplete messages of `source_id` while it holds
+    /// more than `max_sessions` of them, or more than `max_bytes`.
+    pub(super) fn evict_over_limits(
+        &mut self,
+        source_id: NodeId,
+        max_sessions: usize,
+        max_bytes: usize,
+    ) -> Vec<Evicted> {
+        let mut evicted = Vec::new();
+        loop {
+            let of_source = self
+                .partial
+                .iter()
+                .filter(|&(&(source, _), _)| source == source_id);
+            let (sessions, bytes) = of_source
+                .clone()
+                .fold((0, 0), |(sessions, bytes), (_, partial)| {
+                    (sessions + 1, bytes + partial.bytes)
+                });
+            if sessions <= max_sessions && bytes <= max_bytes {
+                return evicted;
+            }
+            let Some(oldest) = of_source
+                .min_by_key(|(_, partial)| partial.started)
+                .map(|(&key, _)| key)
+            else {
+                return evicted;
+            };
+
+            self.partial.remove(&oldest);
+            evicted.push(Evicted {
+                session_id: oldest.1,
+                sessions,
+                bytes,
+            });
+        }
+    }
+}
+
+impl ChatClient {
+    /// Hands a received fragment to the assembler, logging the fragments
+    /// it drops or finds inconsistent, then evicts the oldest incomplete
+    /// messages of `source_id` past the configured limits.
+    pub(super) fn push_fragment(
+        &mut self,
+        fragment: &Fragment,
+        session_id: u64,
+        source_id: NodeId,
+    ) {
+        match self.inbound.push_fragment(source_id, session_id, fragment) {
+            Pushed::Added | Pushed::Duplicate => {}
+            Pushed::TotalChanged { previous, awaited } => {
+                warn!(
+                    self,
+                    "{} [ {} ]: Fragment {} of session {} declares {} fragments instead of {}, awaiting {}",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    fragment.fragment_index,
+                    session_id,
+                    fragment.total_n_fragments,
+                    previous,
+                    awaited
+                );
+            }
+            Pushed::OutOfRange => {
+                warn!(
+                    self,
+                    "{} [ {} ]: Dropping fragment {} of session {} from [ Node {} ], which declares only {} fragments",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    fragment.fragment_index,
+                    session_id,
+                    source_id,
+                    fragment.total_n_fragments
+                );
+                return;
+            }
+        }
+
+        let evicted = self.inbound.evict_over_limits(
+            source_id,
+            self.config.reassembly_sessions,
+            self.config.reassembly_bytes,
+        );
+        for Evicted {
+            session_id,
+            sessions,
+            bytes,
+        } in evicted
+        {
+            warn!(
+                self,
+                "{} [ {} ]: [ Node {} ] holds {} incomplete sessions and {} bytes, evicting session {}",
+                "!!!".yellow(),
+                self.log_tag,
+                source_id,
+                sessions,
+                bytes,
+                session_id
+            );
+            self.send_event(ChatClientEvent::ReassemblyEvicted(source_id, session_id));
+        }
+    }
+
+    /// Takes the oldest message the assembler completed, handing its data
+    /// to the message factory in 128-byte fragments.
+    ///
+    /// # Returns
+    ///
+    /// The source and session id of the message, with the message itself
+    /// if the factory could decode it.
+    pub(super) fn poll_complete(&mut self) -> Option<(NodeId, u64, Option<Message>)> {
+        let (source_id, session_id, data) = self.inbound.poll_complete()?;
+        let chunks: Vec<&[u8]> = data.chunks(FRAGMENT_DSIZE).collect();
+        let total = u64::try_from(chunks.len()).unwrap_or(u64::MAX);
+        let mut message = None;
+        for (chunk, fragment_index) in chunks.into_iter().zip(0..) {
+            message = self.msgfactory.received_fragment(
+                fragment_of(chunk, fragment_index, total),
+                session_id,
+                source_id,
+            );
+        }
+        Some((source_id, session_id, message))
+    }
+
+    /// Discards the messages that did not complete within
+    /// [`REASSEMBLY_TTL`], telling the controller about each one.
+    pub(super) fn expire_reassemblies(&mut self) {
+        let ttl = self.scaled(REASSEMBLY_TTL);
+        for (source_id, session_id) in self.inbound.purge_stale(ttl) {
+            warn!(
+                self,
+                "{} [ {} ]: Discarded the incomplete session {} from [ Node {} ]",
+                "!!!".yellow(),
+                self.log_tag,
+                session_id,
+                source_id
+            );
+            self.send_event(ChatClientEvent::ReassemblyEvicted(source_id, session_id));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::{network::NodeId, packet::Packet};
+
+use super::ChatClient;
+
+/// Window over which the fragments of a source are counted.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct SourceWindow {
+    started: Instant,
+    fragments: u32,
+    throttled: bool,
+}
+
+/// Fragments received from every source during the current
+/// [`RATE_WINDOW`], so that a source sending more than the configured
+/// rate cannot starve the commands of the controller.
+#[derive(Debug, Default)]
+pub(super) struct InboundRate {
+    windows: HashMap<NodeId, SourceWindow>,
+}
+
+impl ChatClient {
+    /// Counts a received fragment against the rate of its source.
+    ///
+    /// # Returns
+    ///
+    /// Whether the fragment must be dropped, its source having sent more
+    /// fragments than allowed during the current window.
+    pub(super) fn throttled(&mut self, packet: &Packet) -> bool {
+        let Some(limit) = self.config.inbound_rate_limit else {
+            return false;
+        };
+        let Some(source_id) = packet.routing_header.source() else {
+            return false;
+        };
+
+        let window_length = self.scaled(RATE_WINDOW);
+        let window = self
+            .inbound_rate
+            .windows
+            .entry(source_id)
+            .or_insert_with(|| SourceWindow {
+                started: Instant::now(),
+                fragments: 0,
+                throttled: false,
+            });
+        if window.started.elapsed() >= window_length {
+            *window = SourceWindow {
+                started: Instant::now(),
+                fragments: 0,
+                throttled: false,
+            };
+        }
+        window.fragments = window.fragments.saturating_add(1);
+        if window.fragments <= limit {
+            return false;
+        }
+        if window.throttled {
+            return true;
+        }
+
+        window.throttled = true;
+        warn!(
+            self,
+            "{} [ {} ]: [ Node {} ] sent more than {} fragments in {:?}, dropping the next ones",
+            "!!!".yellow(),
+            self.log_tag,
+            source_id,
+            limit,
+            RATE_WINDOW
+        );
+        self.send_event(ChatClientEvent::SourceThrottled(source_id));
+        true
+    }
+
+    /// Forgets the sources that sent nothing during the last window.
+    pub(super) fn expire_rate_windows(&mut self) {
+        let window_length = self.scaled(RATE_WINDOW);
+        self.inbound_rate
+            .windows
+            .retain(|_, window| window.started.elapsed() < window_length);
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+use messages::client_commands::{ChatClientCommand, TelemetryKind};
+use wg_2024::packet::Packet;
+
+use super::ChatClient;
+
+/// Time a single packet or command may take to handle before it is
+/// reported as a `TelemetryKind::SlowHandler`.
+const SLOW_HANDLER: Duration = Duration::from_millis(50);
+
+impl ChatClient {
+    /// Handles `packet`, accounting for the time it took and the packets
+    /// still waiting behind it.
+    pub(super) fn timed_packet(&mut self, packet: Packet) {
+        let started = Instant::now();
+        self.handle_packet(packet);
+        let elapsed = started.elapsed();
+
+        let backlog = u64::try_from(self.packet_recv.len()).unwrap_or(u64::MAX);
+        let event_loop = &mut self.stats.event_loop;
+        event_loop.packets_handled += 1;
+        event_loop.packet_time += elapsed;
+        event_loop.max_packet_time = event_loop.max_packet_time.max(elapsed);
+        event_loop.packet_backlog = backlog;
+        event_loop.max_packet_backlog = event_loop.max_packet_backlog.max(backlog);
+
+        self.check_slow_handler("packet", elapsed);
+    }
+
+    /// Handles `command`, accounting for the time until its effects were
+    /// carried out.
+    pub(super) fn timed_command(&mut self, command: ChatClientCommand) {
+        let started = Instant::now();
+        self.handle_command(command);
+        let elapsed = started.elapsed();
+
+        let event_loop = &mut self.stats.event_loop;
+        event_loop.commands_handled += 1;
+        event_loop.command_time += elapsed;
+        event_loop.max_command_time = event_loop.max_command_time.max(elapsed);
+
+        self.check_slow_handler("command", elapsed);
+    }
+
+    fn check_slow_handler(&self, handler: &str, elapsed: Duration) {
+        if elapsed >= SLOW_HANDLER {
+            self.emit_telemetry(TelemetryKind::SlowHandler {
+                handler: handler.to_string(),
+                duration: elapsed,
+            });
+        }
+    }
+}
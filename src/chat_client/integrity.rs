@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::{payload::Payload, transfer::checksum, ChatClient};
+
+/// Checked messages kept for the recipients that ask for them again.
+const KEPT_MESSAGES: usize = 64;
+
+/// Checksums on the contents exchanged with other clients.
+///
+/// When enabled, every content sent to a client is wrapped with the
+/// checksum of its text. A recipient finding a mismatch after reassembly
+/// discards the content and asks the sender, once, for the kept copy.
+#[derive(Debug, Default)]
+pub(super) struct Integrity {
+    enabled: bool,
+    sent: HashMap<u64, (NodeId, String)>,
+    order: VecDeque<u64>,
+    requested: HashSet<(NodeId, u64)>,
+}
+
+impl Integrity {
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+}
+
+impl ChatClient {
+    /// `content` as it is sent to `recipient_id`, wrapped with its checksum
+    /// if integrity checks are enabled.
+    pub(super) fn checked_content(&mut self, recipient_id: NodeId, content: String) -> String {
+        if !self.integrity.enabled {
+            return content;
+        }
+
+        let message_id = rand::random::<u64>();
+        if self.integrity.order.len() >= KEPT_MESSAGES {
+            if let Some(oldest) = self.integrity.order.pop_front() {
+                self.integrity.sent.remove(&oldest);
+            }
+        }
+        self.integrity
+            .sent
+            .insert(message_id, (recipient_id, content.clone()));
+        self.integrity.order.push_back(message_id);
+
+        Payload::Checked {
+            message_id,
+            checksum: checksum(content.as_bytes()),
+            content,
+        }
+        .encode()
+    }
+
+    /// Reads a checked content if it matches its checksum, otherwise
+    /// reports it and asks the sender to send it again.
+    pub(super) fn checked_content_received(
+        &mut self,
+        sender_id: NodeId,
+        message_id: u64,
+        expected: u64,
+        content: String,
+    ) {
+        if checksum(content.as_bytes()) == expected {
+            self.content_received(sender_id, content);
+            return;
+        }
+
+        error!(
+            self,
+            "{} [ {} ]: Message {} from [ Client {} ] does not match its checksum",
+            "✗".red(),
+            self.log_tag,
+            message_id,
+            sender_id
+        );
+        self.send_event(ChatClientEvent::CorruptMessage(sender_id, message_id));
+        if self.integrity.requested.insert((sender_id, message_id)) {
+            let _ = self.send_payload(sender_id, &Payload::ResendRequest { message_id });
+        }
+    }
+
+    /// Sends a kept checked content again to the client it was sent to.
+    pub(super) fn resend_requested(&mut self, sender_id: NodeId, message_id: u64) {
+        let Some((recipient_id, content)) = self.integrity.sent.get(&message_id).cloned() else {
+            warn!(
+                self,
+                "{} [ {} ]: [ Client {} ] asked for message {}, which is no longer kept",
+                "!!!".yellow(),
+                self.log_tag,
+                sender_id,
+                message_id
+            );
+            return;
+        };
+        if recipient_id != sender_id {
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Sending message {} to [ Client {} ] again",
+            "ℹ".blue(),
+            self.log_tag,
+            message_id,
+            sender_id
+        );
+        let payload = Payload::Checked {
+            message_id,
+            checksum: checksum(content.as_bytes()),
+            content,
+        };
+        let _ = self.send_payload(sender_id, &payload);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{HashMap, HashSet};
+
+use colored::Colorize;
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::NodeType,
+};
+
+use super::ChatClient;
+
+/// Distinct neighbours that must fail to reach a drone before it is
+/// considered crashed.
+const NODE_DEAD_AFTER: usize = 2;
+
+/// Links reported down by `ErrorInRouting` Nacks.
+///
+/// A single Nack only says that one drone could not reach its next hop,
+/// which may just be a removed channel. The link is avoided until a flood
+/// response crosses it again, and only the drone that several neighbours
+/// failed to reach is dropped from the `Router`.
+#[derive(Debug, Default)]
+pub(super) struct LinkFailures {
+    /// For each unreachable node, the neighbours that reported it.
+    down: HashMap<NodeId, HashSet<NodeId>>,
+}
+
+impl LinkFailures {
+    fn is_down(&self, a: NodeId, b: NodeId) -> bool {
+        self.down
+            .get(&b)
+            .is_some_and(|reporters| reporters.contains(&a))
+            || self
+                .down
+                .get(&a)
+                .is_some_and(|reporters| reporters.contains(&b))
+    }
+
+    fn crosses_down_link(&self, hops: &[NodeId]) -> bool {
+        hops.windows(2).any(|pair| self.is_down(pair[0], pair[1]))
+    }
+}
+
+impl ChatClient {
+    /// Marks the link from `reporter` to `unreachable` as down, declaring
+    /// `unreachable` crashed once enough of its neighbours failed to
+    /// reach it.
+    pub(super) fn link_failed(&mut self, reporter: NodeId, unreachable: NodeId) {
+        self.topology.remove_link(reporter, unreachable);
+        let reporters = self.link_failures.down.entry(unreachable).or_default();
+        reporters.insert(reporter);
+        let reporters = reporters.len();
+
+        if reporters >= NODE_DEAD_AFTER {
+            warn!(
+                self,
+                "{} [ {} ]: [ Node {} ] is unreachable from {} neighbours, considering it crashed",
+                "!!!".yellow(),
+                self.log_tag,
+                unreachable,
+                reporters
+            );
+            self.link_failures.down.remove(&unreachable);
+            self.router.drone_crashed(unreachable);
+            self.topology.remove_node(unreachable);
+        } else {
+            warn!(
+                self,
+                "{} [ {} ]: Link [ Node {} ] -> [ Node {} ] is down",
+                "!!!".yellow(),
+                self.log_tag,
+                reporter,
+                unreachable
+            );
+        }
+    }
+
+    /// Brings back the links a flood response crossed.
+    pub(super) fn links_seen(&mut self, path_trace: &[(NodeId, NodeType)]) {
+        for pair in path_trace.windows(2) {
+            let (a, b) = (pair[0].0, pair[1].0);
+            for (reporter, unreachable) in [(a, b), (b, a)] {
+                if let Some(reporters) = self.link_failures.down.get_mut(&unreachable) {
+                    reporters.remove(&reporter);
+                    if reporters.is_empty() {
+                        self.link_failures.down.remove(&unreachable);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces a route crossing a link reported down by the shortest
+    /// known one that does not, or gives up on it if there is none.
+    pub(super) fn avoid_down_links(
+        &self,
+        destination: NodeId,
+        route: SourceRoutingHeader,
+    ) -> Option<SourceRoutingHeader> {
+        if !self.link_failures.crosses_down_link(&route.hops) {
+            return Some(route);
+        }
+
+        let hops = self
+            .topology
+            .path_avoiding(self.id, destination, self.route_blacklist())?;
+        (!self.link_failures.crosses_down_link(&hops)).then_some(SourceRoutingHeader {
+            hop_index: route.hop_index,
+            hops,
+        })
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    cell::Cell,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crossbeam_channel::Sender;
+use log::{Level, LevelFilter};
+
+/// Where the log lines of a `ChatClient` are written.
+#[derive(Debug, Clone, Default)]
+pub enum LogTarget {
+    /// Through the `log` facade, to whatever logger the simulation installed.
+    #[default]
+    Facade,
+    /// Straight to the standard output.
+    Stdout,
+    /// Appended to a file, created if missing.
+    File(PathBuf),
+    /// Sent as formatted lines on a channel.
+    Channel(Sender<String>),
+}
+
+#[derive(Debug)]
+enum Sink {
+    Facade,
+    Stdout,
+    File(File),
+    Channel(Sender<String>),
+}
+
+/// Writes the log lines of one `ChatClient`, filtered by level and with the
+/// terminal colors stripped when disabled.
+///
+/// The lines logged for every packet handled, with `packet_info!`, have a
+/// level of their own and may be sampled, since at high packet rates they
+/// are most of the logging cost.
+#[derive(Debug)]
+pub(super) struct ClientLogger {
+    sink: Sink,
+    level: LevelFilter,
+    colored: bool,
+    packet_level: LevelFilter,
+    /// One packet line in this many is logged.
+    packet_sampling: u32,
+    packet_lines: Cell<u32>,
+}
+
+impl Default for ClientLogger {
+    fn default() -> Self {
+        Self {
+            sink: Sink::Facade,
+            level: LevelFilter::Trace,
+            colored: true,
+            packet_level: LevelFilter::Trace,
+            packet_sampling: 1,
+            packet_lines: Cell::new(0),
+        }
+    }
+}
+
+impl ClientLogger {
+    pub(super) fn new(target: LogTarget, level: LevelFilter, colored: bool) -> io::Result<Self> {
+        let sink = match target {
+            LogTarget::Facade => Sink::Facade,
+            LogTarget::Stdout => Sink::Stdout,
+            LogTarget::File(path) => {
+                Sink::File(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+            LogTarget::Channel(sender) => Sink::Channel(sender),
+        };
+
+        Ok(Self {
+            sink,
+            level,
+            colored,
+            ..Self::default()
+        })
+    }
+
+    /// Logs the packet lines up to `level`, only one in `sampling` of them.
+    pub(super) fn with_packet_logging(mut self, level: LevelFilter, sampling: u32) -> Self {
+        self.packet_level = level;
+        self.packet_sampling = sampling.max(1);
+        self
+    }
+
+    pub(super) fn set_colored(&mut self, colored: bool) {
+        self.colored = colored;
+    }
+
+    /// Whether a line at `level` would be written, checked before its
+    /// arguments are formatted. Lines for the `log` facade must also pass
+    /// the level of the installed logger.
+    pub(super) fn enabled(&self, level: Level) -> bool {
+        level <= self.level && (!matches!(self.sink, Sink::Facade) || log::log_enabled!(level))
+    }
+
+    /// Whether a packet line at `level` would be written, counting it
+    /// toward the sampling.
+    pub(super) fn packet_enabled(&self, level: Level) -> bool {
+        if !self.enabled(level) || level > self.packet_level {
+            return false;
+        }
+        let lines = self.packet_lines.get();
+        self.packet_lines.set((lines + 1) % self.packet_sampling);
+        lines == 0
+    }
+
+    pub(super) fn log(&self, level: Level, args: fmt::Arguments) {
+        if !self.enabled(level) {
+            return;
+        }
+
+        let line = if self.colored {
+            args.to_string()
+        } else {
+            strip_colors(&args.to_string())
+        };
+
+        match &self.sink {
+            Sink::Facade => log::log!(level, "{line}"),
+            Sink::Stdout => println!("{level:<5} {line}"),
+            Sink::File(file) => {
+                // a failing log file must not take the client down
+                let _ = writeln!(&*file, "{level:<5} {line}");
+            }
+            Sink::Channel(sender) => {
+                let _ = sender.send(format!("{level:<5} {line}"));
+            }
+        }
+    }
+}
+
+/// Removes the ANSI escape sequences added by `colored`.
+fn strip_colors(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // skip up to and including the final byte of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+// the arguments, colors included, are only formatted for lines that are written
+
+macro_rules! error {
+    ($client:expr, $($arg:tt)+) => {
+        if $client.logger.enabled(log::Level::Error) {
+            $client.logger.log(log::Level::Error, format_args!($($arg)+))
+        }
+    };
+}
+
+macro_rules! warn {
+    ($client:expr, $($arg:tt)+) => {
+        if $client.logger.enabled(log::Level::Warn) {
+            $client.logger.log(log::Level::Warn, format_args!($($arg)+))
+        }
+    };
+}
+
+macro_rules! info {
+    ($client:expr, $($arg:tt)+) => {
+        if $client.logger.enabled(log::Level::Info) {
+            $client.logger.log(log::Level::Info, format_args!($($arg)+))
+        }
+    };
+}
+
+/// Logs a line about a single packet, subject to the packet level and
+/// sampling.
+macro_rules! packet_info {
+    ($client:expr, $($arg:tt)+) => {
+        if $client.logger.packet_enabled(log::Level::Info) {
+            $client.logger.log(log::Level::Info, format_args!($($arg)+))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fmt};
+
+    use crossbeam_channel::unbounded;
+    use log::LevelFilter;
+
+    use super::{ClientLogger, LogTarget};
+
+    struct Client {
+        logger: ClientLogger,
+    }
+
+    /// Counts how many times it is formatted.
+    struct Formatted<'a>(&'a Cell<u32>);
+
+    impl fmt::Display for Formatted<'_> {
+        fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.set(self.0.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn facade_lines_are_not_formatted_without_a_logger() {
+        let client = Client {
+            logger: ClientLogger::default(),
+        };
+        let formatted = Cell::new(0);
+
+        error!(client, "{}", Formatted(&formatted));
+        info!(client, "{}", Formatted(&formatted));
+
+        assert_eq!(formatted.get(), 0);
+    }
+
+    #[test]
+    fn lines_above_the_level_are_not_formatted() {
+        let (sender, receiver) = unbounded();
+        let client = Client {
+            logger: ClientLogger::new(LogTarget::Channel(sender), LevelFilter::Warn, false)
+                .expect("channel sinks cannot fail"),
+        };
+        let formatted = Cell::new(0);
+
+        info!(client, "{}", Formatted(&formatted));
+        assert_eq!(formatted.get(), 0);
+        assert!(receiver.is_empty());
+
+        warn!(client, "{}", Formatted(&formatted));
+        assert_eq!(formatted.get(), 1);
+        assert_eq!(receiver.len(), 1);
+    }
+}
@@ -1,18 +1,145 @@
+use ack_batching::AckBatches;
+use ack_gaps::AckGaps;
 use assembler::HighLevelMessageFactory;
+use backoff::Backoff;
+use bot::Bot;
+use chunking::IncomingChunks;
+use client_list_refresh::ClientListRefresh;
+use colored::Colorize;
+use config::ChatClientConfig;
+use control_requests::ControlRequests;
+use conversations::Conversations;
 use crossbeam_channel::{select_biased, Receiver, Sender};
+use dispatch::FragmentDispatch;
+use events::EventLog;
+#[cfg(feature = "fault-injection")]
+use fault_injection::FaultInjection;
+use flood_sessions::FloodSessions;
+use flooding::FloodState;
+use history::MessageHistory;
+use inbound::InboundAssembler;
+use inbound_rate::InboundRate;
+use integrity::Integrity;
+use link_failures::LinkFailures;
+use logging::ClientLogger;
 use messages::{
-    client_commands::{ChatClientCommand, ChatClientEvent},
+    client_commands::{ChatClientCommand, ChatClientEvent, ClientStats},
     high_level_messages::Message,
 };
-use source_routing::Router;
-use std::collections::HashMap;
-use wg_2024::{
-    network::NodeId,
-    packet::{NodeType, Packet},
+use outbox::Outbox;
+use packet_cache::PacketCache;
+use peers::PeerRegistry;
+use pending_messages::PendingMessage;
+use personas::Personas;
+use presence::Presence;
+use probes::Probes;
+use progress::SendProgress;
+use quarantine::SourceQuarantine;
+use route_cache::RouteCache;
+use send_window::SendWindow;
+use server_health::ServerHealth;
+use server_list::ServerList;
+use server_queries::ServerQueries;
+use server_selection::ServerSelector;
+use shortcuts::ShortcutRelays;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
 };
+use topology::Topology;
+use traces::TraceRegistry;
+use transfer::IncomingTransfer;
+use wg_2024::{network::NodeId, packet::Packet};
 
+// `error!`, `warn!` and `info!` writing to the client's own log target
+#[macro_use]
+mod logging;
+
+mod ack_batching;
+mod ack_gaps;
+mod auto_reply;
+mod backoff;
+mod blocklist;
+mod bot;
+mod builder;
+mod cache_eviction;
+mod chunking;
+mod client_list_refresh;
+mod compression;
+mod config;
+mod control_requests;
+mod conversations;
+mod dispatch;
+mod error;
+mod events;
+mod failover;
+#[cfg(feature = "fault-injection")]
+mod fault_injection;
+mod flood_sessions;
+mod flooding;
 mod handle_command;
 mod handle_packet;
+mod health;
+mod history;
+mod inbound;
+mod inbound_rate;
+mod instrumentation;
+mod integrity;
+mod link_failures;
+mod multipath;
+mod nack_strategy;
+mod network_config;
+mod outbox;
+mod packet_cache;
+mod payload;
+mod peers;
+mod pending_messages;
+mod personas;
+mod presence;
+mod probes;
+mod progress;
+mod quarantine;
+mod reassembly;
+mod rich_messages;
+mod rooms;
+mod route_cache;
+mod routing;
+#[cfg(feature = "async")]
+mod run_async;
+mod send_window;
+mod server_health;
+mod server_list;
+mod server_queries;
+mod server_selection;
+mod shortcuts;
+mod snapshot;
+mod stats;
+mod storage;
+mod telemetry;
+mod time_scale;
+mod timers;
+mod topology;
+mod traces;
+mod transfer;
+mod usernames;
+
+pub use builder::ChatClientBuilder;
+pub use config::{
+    AckMode, AutoReply, BotProfile, ConformanceMode, FloodMode, MessageSize, PeerSelection,
+    RetryPolicy, ServerSelection, TimeScale, UnknownSourcePolicy, ValidationPolicies,
+    ValidationPolicy,
+};
+pub use error::ChatClientError;
+pub use logging::LogTarget;
+pub use nack_strategy::{DefaultNackStrategy, NackAction, NackStrategy};
+pub use network_config::{ConfiguredClient, ConfiguredNetwork, NetworkConfig, NodeChannels};
+pub use routing::Routing;
+pub use snapshot::ClientSnapshot;
+pub use storage::{FileStorage, MemoryStorage, Storage};
+
+/// Interval at which the `ChatClient` checks its pending timeouts.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
 
 /// The `ChatClient` struct represents a client in a chat network.
 ///
@@ -23,24 +150,80 @@ mod handle_packet;
 ///
 /// * `new` - Creates a new instance of `ChatClient`.
 /// * `run` - Runs the main event loop for the `ChatClient`.
+/// * `run_async` - Runs the same loop as a future, with the `async` feature.
+/// * `snapshot` - Captures the state of the `ChatClient` as a `ClientSnapshot`.
+/// * `restore` - Puts the state of a `ClientSnapshot` back.
 pub struct ChatClient {
     id: NodeId,
+    log_tag: String,
+    logger: ClientLogger,
+    config: ChatClientConfig,
     running: bool,
+    shutdown: bool,
     registered: Option<NodeId>,
-    client_list: Vec<NodeId>,
+    server_health: ServerHealth,
+    control_requests: ControlRequests,
+    peers: PeerRegistry,
+    client_list_refresh: ClientListRefresh,
+    personas: Personas,
+    blocked_clients: HashSet<NodeId>,
+    username: Option<String>,
     msgfactory: HighLevelMessageFactory,
-    router: Router,
-    communication_server_list: Vec<NodeId>,
-    message_buffer: Vec<Message>,
+    packet_cache: PacketCache,
+    progress: SendProgress,
+    probes: Probes,
+    bot: Bot,
+    quarantine: SourceQuarantine,
+    inbound_rate: InboundRate,
+    outbox: Outbox,
+    send_windows: HashMap<NodeId, SendWindow>,
+    dispatch: FragmentDispatch,
+    backoff: Backoff,
+    ack_batches: AckBatches,
+    ack_gaps: AckGaps,
+    nack_strategy: Box<dyn NackStrategy>,
+    router: Box<dyn Routing>,
+    route_cache: RouteCache,
+    link_failures: LinkFailures,
+    server_queries: ServerQueries,
+    shortcuts: ShortcutRelays,
+    flood: Option<FloodState>,
+    flood_sessions: FloodSessions,
+    flood_requests_seen: HashMap<(u64, NodeId), Instant>,
+    topology: Topology,
+    communication_server_list: ServerList,
+    server_selector: ServerSelector,
+    inbound: InboundAssembler,
+    message_buffer: VecDeque<Message>,
+    history: MessageHistory,
+    storage: Box<dyn Storage>,
+    traces: TraceRegistry,
+    conversations: Conversations,
+    integrity: Integrity,
+    #[cfg(feature = "fault-injection")]
+    faults: FaultInjection,
+    pending_messages: VecDeque<PendingMessage>,
+    rooms: HashMap<String, HashSet<NodeId>>,
+    transfers: HashMap<(NodeId, u64), IncomingTransfer>,
+    incoming_chunks: HashMap<(NodeId, u64), IncomingChunks>,
+    presence: Presence,
+    stats: ClientStats,
+    stats_reported_at: Instant,
+    created_at: Instant,
     controller_send: Sender<ChatClientEvent>,
+    controller_gone: Cell<bool>,
+    event_log: RefCell<EventLog>,
     controller_recv: Receiver<ChatClientCommand>,
     packet_recv: Receiver<Packet>,
     packet_send: HashMap<NodeId, Sender<Packet>>,
+    tick: Receiver<Instant>,
 }
 
 impl ChatClient {
     /// Creates a new instance of `ChatClient`.
     ///
+    /// Use [`ChatClientBuilder`] to customize anything beyond the channels.
+    ///
     /// # Arguments
     ///
     /// * `id` - The unique identifier for the `ChatClient`.
@@ -60,48 +243,55 @@ impl ChatClient {
         packet_recv: Receiver<Packet>,
         packet_send: HashMap<NodeId, Sender<Packet>>,
     ) -> Self {
-        Self {
-            id,
-            msgfactory: HighLevelMessageFactory::new(id, NodeType::Client),
-            router: Router::new(id, NodeType::Client),
-            client_list: Vec::new(),
-            message_buffer: Vec::new(),
-            controller_send,
-            controller_recv,
-            packet_recv,
-            packet_send,
-            running: false,
-            registered: None,
-            communication_server_list: Vec::new(),
-        }
+        ChatClientBuilder::new(id, controller_send, controller_recv, packet_recv)
+            .with_initial_neighbors(packet_send)
+            .build()
     }
 
     /// Runs the main event loop for the `ChatClient`.
     ///
     /// This function continuously listens for incoming commands and packets,
     /// and processes them accordingly. It uses a biased select to prioritize
-    /// receiving commands over packets.
+    /// receiving commands over timeouts and packets, and these over the
+    /// fragments of large messages still waiting to be dispatched.
     ///
-    /// # Panics
-    ///
-    /// This function will run indefinitely and does not return under normal
-    /// operation. It will only stop if the program is terminated.
+    /// The loop stops when a `ChatClientCommand::Shutdown` is received, after
+    /// which the message history and the state are written to the storage,
+    /// and the outbox is saved if it is backed by a file.
     pub fn run(&mut self) {
-        loop {
+        while !self.shutdown && !self.controller_gone.get() {
             select_biased! {
                 recv(self.controller_recv) -> command => {
                     if let Ok(command) = command {
-                        self.handle_command(command);
+                        self.timed_command(command);
+                    } else {
+                        error!(self,
+                            "{} [ {} ]: The controller disconnected, shutting down",
+                            "✗".red(),
+                            self.log_tag
+                        );
+                        self.controller_gone.set(true);
                     }
                 },
 
+                recv(self.tick) -> _ => {
+                    self.handle_tick();
+                },
+
                 recv(self.packet_recv) -> packet => {
                     if let Ok(packet) = packet {
-                        self.handle_packet(&packet);
+                        self.timed_packet(packet);
                     }
                 },
 
+                recv(self.dispatch.wake_recv) -> _ => {
+                    self.dispatch_fragments();
+                },
+
             }
         }
+
+        self.save_storage();
+        self.save_outbox();
     }
 }
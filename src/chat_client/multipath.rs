@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::Packet,
+};
+
+use super::ChatClient;
+
+impl ChatClient {
+    /// Spreads the fragments of a message over up to the configured number
+    /// of node-disjoint routes, the first one being the `Router`'s.
+    ///
+    /// The extra routes are searched on the client's topology mirror so that
+    /// they share no drone with the primary route nor with each other.
+    /// Fragments keep their own index, so their order of arrival does not
+    /// matter, and each cached fragment remembers the route it took.
+    pub(super) fn spread_over_routes(
+        &self,
+        destination: NodeId,
+        primary: &SourceRoutingHeader,
+        fragments: &mut [Packet],
+    ) {
+        if self.config.multipath <= 1 || fragments.len() <= 1 {
+            return;
+        }
+
+        let mut used: HashSet<NodeId> = intermediate_hops(&primary.hops).collect();
+        let mut routes = vec![primary.clone()];
+        while routes.len() < self.config.multipath.min(fragments.len()) {
+            let Some(hops) = self.topology.path_avoiding(self.id, destination, &used) else {
+                break;
+            };
+            used.extend(intermediate_hops(&hops));
+            routes.push(SourceRoutingHeader {
+                hop_index: primary.hop_index,
+                hops,
+            });
+        }
+
+        for (fragment, route) in fragments.iter_mut().zip(routes.iter().cycle()) {
+            fragment.routing_header = route.clone();
+        }
+    }
+}
+
+fn intermediate_hops(hops: &[NodeId]) -> impl Iterator<Item = NodeId> + '_ {
+    hops.iter()
+        .copied()
+        .skip(1)
+        .take(hops.len().saturating_sub(2))
+}
@@ -0,0 +1,69 @@
+use wg_2024::packet::NackType;
+
+use super::RetryPolicy;
+
+/// What the `ChatClient` does with a fragment that was Nacked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NackAction {
+    /// Resend the fragment along the route it was sent on, after a backoff.
+    RetrySamePath,
+    /// Resend the fragment along a freshly computed route, after a backoff.
+    Reroute,
+    /// Flood the network again, then resend the fragment along a new route.
+    Reflood,
+    /// Stop retransmitting the fragment.
+    GiveUp,
+}
+
+/// Decides how the `ChatClient` reacts to each Nack, so that it can be
+/// adapted to how the drones of a network behave.
+///
+/// Set with `ChatClientBuilder::with_nack_strategy`. The client still
+/// updates its view of the network from the Nack before asking.
+pub trait NackStrategy: Send {
+    /// The action to take after a Nack of type `nack_type`.
+    ///
+    /// # Arguments
+    ///
+    /// * `nack_type` - The type of the Nack just received.
+    /// * `nacks` - The Nacks received for this fragment so far, this one included.
+    fn on_nack(&self, nack_type: &NackType, nacks: u32) -> NackAction;
+}
+
+/// The `NackStrategy` used unless another one is set, driven by the
+/// client's `RetryPolicy`.
+///
+/// Every Nacked fragment is rerouted, including the ones whose route ended
+/// at a drone: the client already gave up on the destinations its topology
+/// knows to be drones. Fragments dropped more than `reflood_after` times
+/// trigger a flood first, and fragments Nacked more than
+/// `max_retransmissions` times are given up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultNackStrategy {
+    retry_policy: RetryPolicy,
+}
+
+impl DefaultNackStrategy {
+    /// Creates a `DefaultNackStrategy` following `retry_policy`.
+    #[must_use]
+    pub fn new(retry_policy: RetryPolicy) -> Self {
+        Self { retry_policy }
+    }
+}
+
+impl NackStrategy for DefaultNackStrategy {
+    fn on_nack(&self, nack_type: &NackType, nacks: u32) -> NackAction {
+        if self
+            .retry_policy
+            .max_retransmissions
+            .is_some_and(|max| nacks > max)
+        {
+            return NackAction::GiveUp;
+        }
+
+        match nack_type {
+            NackType::Dropped if nacks > self.retry_policy.reflood_after => NackAction::Reflood,
+            _ => NackAction::Reroute,
+        }
+    }
+}
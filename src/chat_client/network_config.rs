@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::{config::Config, network::NodeId, packet::Packet};
+
+use super::ChatClientBuilder;
+
+/// A network described by a `wg_2024` TOML config, to spin up
+/// `ChatClient`s outside of the full simulation.
+///
+/// # Methods
+///
+/// * `from_file` - Reads the config from a TOML file.
+/// * `from_toml` - Parses the config from a TOML string.
+/// * `from_config` - Wraps an already parsed `Config`.
+/// * `build` - Creates every channel and a `ChatClientBuilder` per client.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    config: Config,
+}
+
+/// The channels of a network built from a [`NetworkConfig`].
+///
+/// Every client comes with a [`ChatClientBuilder`] already connected to its
+/// drones, and every drone and server with the channels it needs to be
+/// started by the caller.
+pub struct ConfiguredNetwork {
+    /// The clients, by id.
+    pub clients: HashMap<NodeId, ConfiguredClient>,
+    /// The channels of the drones and servers, by id.
+    pub nodes: HashMap<NodeId, NodeChannels>,
+}
+
+/// A client of a [`ConfiguredNetwork`], with the controller side of its
+/// channels.
+pub struct ConfiguredClient {
+    /// Builds the client, connected to its drones.
+    pub builder: ChatClientBuilder,
+    /// Sends commands to the client.
+    pub commands: Sender<ChatClientCommand>,
+    /// Receives the events of the client.
+    pub events: Receiver<ChatClientEvent>,
+}
+
+/// The packet channels of a drone or server of a [`ConfiguredNetwork`].
+#[derive(Debug)]
+pub struct NodeChannels {
+    /// Receives the packets sent to the node.
+    pub packet_recv: Receiver<Packet>,
+    /// Reaches each neighbour of the node.
+    pub packet_send: HashMap<NodeId, Sender<Packet>>,
+}
+
+impl NetworkConfig {
+    /// Reads a config from a TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a valid config,
+    /// or connects nodes that are not declared in it.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a config from a TOML string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a valid config, or connects
+    /// nodes that are not declared in it.
+    pub fn from_toml(toml: &str) -> io::Result<Self> {
+        let config =
+            toml::from_str(toml).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Self::from_config(config)
+    }
+
+    /// Wraps an already parsed `Config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config declares a node twice, or connects
+    /// nodes that are not declared in it.
+    pub fn from_config(config: Config) -> io::Result<Self> {
+        let network = Self { config };
+        network.validate()?;
+        Ok(network)
+    }
+
+    /// Creates a channel per node and a [`ChatClientBuilder`] per client,
+    /// connected to the drones the config lists for it.
+    ///
+    /// Links are used in both directions, whichever of their two nodes
+    /// lists them.
+    #[must_use]
+    pub fn build(self) -> ConfiguredNetwork {
+        let channels: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)> =
+            self.ids().map(|id| (id, unbounded())).collect();
+
+        let mut neighbors: HashMap<NodeId, HashMap<NodeId, Sender<Packet>>> =
+            self.ids().map(|id| (id, HashMap::new())).collect();
+        for (a, b) in self.links() {
+            if let Some(a_neighbors) = neighbors.get_mut(&a) {
+                a_neighbors.insert(b, channels[&b].0.clone());
+            }
+            if let Some(b_neighbors) = neighbors.get_mut(&b) {
+                b_neighbors.insert(a, channels[&a].0.clone());
+            }
+        }
+
+        let mut network = ConfiguredNetwork {
+            clients: HashMap::new(),
+            nodes: HashMap::new(),
+        };
+        let clients: HashSet<NodeId> = self.config.client.iter().map(|client| client.id).collect();
+        for (id, (_, packet_recv)) in channels {
+            let packet_send = neighbors.remove(&id).unwrap_or_default();
+
+            if clients.contains(&id) {
+                let (commands, controller_recv) = unbounded();
+                let (controller_send, events) = unbounded();
+                let builder =
+                    ChatClientBuilder::new(id, controller_send, controller_recv, packet_recv)
+                        .with_initial_neighbors(packet_send);
+                network.clients.insert(
+                    id,
+                    ConfiguredClient {
+                        builder,
+                        commands,
+                        events,
+                    },
+                );
+            } else {
+                network.nodes.insert(
+                    id,
+                    NodeChannels {
+                        packet_recv,
+                        packet_send,
+                    },
+                );
+            }
+        }
+
+        network
+    }
+
+    fn ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.config
+            .drone
+            .iter()
+            .map(|drone| drone.id)
+            .chain(self.config.client.iter().map(|client| client.id))
+            .chain(self.config.server.iter().map(|server| server.id))
+    }
+
+    fn links(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        let drones = self.config.drone.iter().flat_map(|drone| {
+            drone
+                .connected_node_ids
+                .iter()
+                .map(move |&neighbor| (drone.id, neighbor))
+        });
+        let clients = self.config.client.iter().flat_map(|client| {
+            client
+                .connected_drone_ids
+                .iter()
+                .map(move |&drone| (client.id, drone))
+        });
+        let servers = self.config.server.iter().flat_map(|server| {
+            server
+                .connected_drone_ids
+                .iter()
+                .map(move |&drone| (server.id, drone))
+        });
+        drones.chain(clients).chain(servers)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let mut ids = HashSet::new();
+        if let Some(duplicate) = self.ids().find(|&id| !ids.insert(id)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("node {duplicate} is declared more than once"),
+            ));
+        }
+
+        if let Some((a, b)) = self
+            .links()
+            .find(|(a, b)| a == b || !ids.contains(a) || !ids.contains(b))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("link {a} - {b} refers to an unknown node or to itself"),
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use std::{fs, io, path::PathBuf};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use wg_2024::packet::Packet;
+
+use super::ChatClient;
+
+/// An unacknowledged fragment as stored in the outbox file.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedFragment {
+    packet: Packet,
+    nacks: u32,
+}
+
+/// The outbound fragments kept across restarts of the client.
+///
+/// The fragments still waiting for their Ack are written to the file on
+/// `ChatClientCommand::Shutdown`. The ones read back when the client is
+/// built wait for a flood to find a route to their destination, and are
+/// then sent again along it.
+#[derive(Debug, Default)]
+pub(super) struct Outbox {
+    file: Option<PathBuf>,
+    restored: Vec<SavedFragment>,
+}
+
+impl Outbox {
+    fn with_file(path: PathBuf) -> io::Result<Self> {
+        let restored = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            file: Some(path),
+            restored,
+        })
+    }
+}
+
+impl ChatClient {
+    pub(super) fn load_outbox(&mut self, path: PathBuf) {
+        let display = path.display().to_string();
+        match Outbox::with_file(path) {
+            Ok(outbox) => {
+                info!(
+                    self,
+                    "{} [ {} ]: Loaded {} outbound fragments from {}",
+                    "✓".green(),
+                    self.log_tag,
+                    outbox.restored.len(),
+                    display
+                );
+                self.outbox = outbox;
+            }
+            Err(e) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Failed to load the outbox from {}: {}",
+                    "✗".red(),
+                    self.log_tag,
+                    display,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Writes the fragments still waiting for their Ack, along with the
+    /// restored ones not sent again yet, to the outbox file.
+    pub(super) fn save_outbox(&self) {
+        let Some(path) = &self.outbox.file else {
+            return;
+        };
+
+        let fragments: Vec<SavedFragment> = self
+            .packet_cache
+            .saved()
+            .into_iter()
+            .map(|(packet, nacks)| SavedFragment { packet, nacks })
+            .chain(self.outbox.restored.iter().map(|saved| SavedFragment {
+                packet: saved.packet.clone(),
+                nacks: saved.nacks,
+            }))
+            .collect();
+
+        let result = serde_json::to_string(&fragments)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(path, json));
+        match result {
+            Ok(()) => info!(
+                self,
+                "{} [ {} ]: Saved {} outbound fragments to {}",
+                "✓".green(),
+                self.log_tag,
+                fragments.len(),
+                path.display()
+            ),
+            Err(e) => error!(
+                self,
+                "{} [ {} ]: Failed to save the outbox to {}: {}",
+                "✗".red(),
+                self.log_tag,
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    /// Sends the restored fragments again once a route to their
+    /// destination is known, keeping the others for the next flood.
+    pub(super) fn resume_outbox(&mut self) {
+        if self.outbox.restored.is_empty() {
+            return;
+        }
+
+        let restored = std::mem::take(&mut self.outbox.restored);
+        let mut resumed = 0;
+        for saved in restored {
+            let routable = saved
+                .packet
+                .routing_header
+                .destination()
+                .is_some_and(|destination| self.route_to(destination).is_some());
+            if !routable {
+                self.outbox.restored.push(saved);
+                continue;
+            }
+
+            self.packet_cache.restore(&saved.packet, saved.nacks);
+            self.reroute_packet(saved.packet);
+            resumed += 1;
+        }
+
+        if resumed > 0 {
+            info!(
+                self,
+                "{} [ {} ]: Resumed {} outbound fragments from the last run",
+                "ℹ".blue(),
+                self.log_tag,
+                resumed
+            );
+        }
+    }
+}
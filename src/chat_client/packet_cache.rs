@@ -0,0 +1,307 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use messages::client_commands::{InFlightFragment, InFlightMessage};
+use wg_2024::{
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+/// An outbound fragment waiting for its Ack.
+#[derive(Debug, Clone)]
+pub(super) struct CachedPacket {
+    pub(super) packet: Packet,
+    pub(super) nacks: u32,
+    /// When the fragment was first sent, kept across retransmissions.
+    pub(super) cached_at: Instant,
+    /// When the fragment was last handed to a neighbour, `None` while it
+    /// waits in its send window.
+    pub(super) sent_at: Option<Instant>,
+}
+
+/// Outbound fragments that have not been acknowledged yet.
+///
+/// Session ids are only unique per sender, so fragments are identified by
+/// the peer they are destined to together with their session id and
+/// fragment index; entries toward different servers never collide.
+#[derive(Debug, Default)]
+pub(super) struct PacketCache {
+    packets: HashMap<(u64, u64), HashMap<NodeId, CachedPacket>>,
+}
+
+impl PacketCache {
+    /// Caches `packet` under its destination, keeping the Nack count of
+    /// the fragment it replaces when it is a retransmission.
+    pub(super) fn insert(&mut self, packet: &Packet) {
+        let (Some(peer), PacketType::MsgFragment(fragment)) =
+            (packet.routing_header.destination(), &packet.pack_type)
+        else {
+            return;
+        };
+
+        let by_peer = self
+            .packets
+            .entry((packet.session_id, fragment.fragment_index))
+            .or_default();
+        let (nacks, cached_at, sent_at) = by_peer
+            .get(&peer)
+            .map_or((0, Instant::now(), None), |cached| {
+                (cached.nacks, cached.cached_at, cached.sent_at)
+            });
+        by_peer.insert(
+            peer,
+            CachedPacket {
+                packet: packet.clone(),
+                nacks,
+                cached_at,
+                sent_at,
+            },
+        );
+    }
+
+    /// Caches a fragment read back from the outbox with the Nack count it
+    /// had when the client was shut down.
+    pub(super) fn restore(&mut self, packet: &Packet, nacks: u32) {
+        self.insert(packet);
+        if let (Some(peer), PacketType::MsgFragment(fragment)) =
+            (packet.routing_header.destination(), &packet.pack_type)
+        {
+            if let Some(cached) = self
+                .packets
+                .get_mut(&(packet.session_id, fragment.fragment_index))
+                .and_then(|by_peer| by_peer.get_mut(&peer))
+            {
+                cached.nacks = nacks;
+            }
+        }
+    }
+
+    /// Resolves the destination of the fragment a Nack refers to.
+    ///
+    /// Nacks come from the drone that failed to deliver the fragment, so
+    /// among the fragments sharing the session id and index, the one whose
+    /// route goes through `nack_src` is preferred.
+    pub(super) fn nacked_peer(
+        &self,
+        session_id: u64,
+        fragment_index: u64,
+        nack_src: NodeId,
+    ) -> Option<NodeId> {
+        let by_peer = self.packets.get(&(session_id, fragment_index))?;
+        by_peer
+            .iter()
+            .find(|(_, cached)| cached.packet.routing_header.hops.contains(&nack_src))
+            .or_else(|| by_peer.iter().next())
+            .map(|(&peer, _)| peer)
+    }
+
+    /// Returns a copy of the cached fragment, counting one more Nack for it.
+    pub(super) fn nacked(
+        &mut self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) -> Option<(Packet, u32)> {
+        let cached = self
+            .packets
+            .get_mut(&(session_id, fragment_index))?
+            .get_mut(&peer)?;
+        cached.nacks += 1;
+        Some((cached.packet.clone(), cached.nacks))
+    }
+
+    /// The hops of the route the cached fragment was last sent along.
+    pub(super) fn route_of(
+        &self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) -> Option<&[NodeId]> {
+        self.packets
+            .get(&(session_id, fragment_index))?
+            .get(&peer)
+            .map(|cached| &cached.packet.routing_header.hops[..])
+    }
+
+    pub(super) fn get(
+        &self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) -> Option<&Packet> {
+        self.packets
+            .get(&(session_id, fragment_index))?
+            .get(&peer)
+            .map(|cached| &cached.packet)
+    }
+
+    /// Records that the cached fragment was just handed to a neighbour.
+    pub(super) fn mark_sent(&mut self, peer: NodeId, session_id: u64, fragment_index: u64) {
+        if let Some(cached) = self
+            .packets
+            .get_mut(&(session_id, fragment_index))
+            .and_then(|by_peer| by_peer.get_mut(&peer))
+        {
+            cached.sent_at = Some(Instant::now());
+        }
+    }
+
+    /// Copies of the fragments of a message with an index below `below`
+    /// that were sent at least `max_age` ago, along with their index.
+    pub(super) fn lingering(
+        &self,
+        peer: NodeId,
+        session_id: u64,
+        below: u64,
+        max_age: Duration,
+    ) -> Vec<(u64, Packet)> {
+        self.packets
+            .iter()
+            .filter(|&(&(session, fragment_index), _)| {
+                session == session_id && fragment_index < below
+            })
+            .filter_map(|(&(_, fragment_index), by_peer)| {
+                let cached = by_peer.get(&peer)?;
+                cached
+                    .sent_at
+                    .is_some_and(|sent_at| sent_at.elapsed() >= max_age)
+                    .then(|| (fragment_index, cached.packet.clone()))
+            })
+            .collect()
+    }
+
+    pub(super) fn take(
+        &mut self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+    ) -> Option<Packet> {
+        let key = (session_id, fragment_index);
+        let by_peer = self.packets.get_mut(&key)?;
+        let cached = by_peer.remove(&peer);
+        if by_peer.is_empty() {
+            self.packets.remove(&key);
+        }
+        cached.map(|cached| cached.packet)
+    }
+
+    /// Number of fragments waiting for their Ack.
+    pub(super) fn len(&self) -> usize {
+        self.packets.values().map(HashMap::len).sum()
+    }
+
+    /// The peer and session id of the message whose oldest fragment has
+    /// been waiting the longest.
+    pub(super) fn oldest_message(&self) -> Option<(NodeId, u64)> {
+        self.iter()
+            .min_by_key(|(_, _, cached)| cached.cached_at)
+            .map(|(peer, session_id, _)| (peer, session_id))
+    }
+
+    /// The peers and session ids of the messages with a fragment older than
+    /// `max_age`.
+    pub(super) fn expired_messages(&self, max_age: Duration) -> Vec<(NodeId, u64)> {
+        let mut expired: Vec<(NodeId, u64)> = self
+            .iter()
+            .filter(|(_, _, cached)| cached.cached_at.elapsed() >= max_age)
+            .map(|(peer, session_id, _)| (peer, session_id))
+            .collect();
+        expired.sort_unstable();
+        expired.dedup();
+        expired
+    }
+
+    /// Whether fragments of a message are still waiting for their Ack.
+    pub(super) fn has_message(&self, peer: NodeId, session_id: u64) -> bool {
+        self.iter()
+            .any(|(to, session, _)| to == peer && session == session_id)
+    }
+
+    /// The peers that still have fragments of session `session_id` to
+    /// acknowledge.
+    pub(super) fn peers_of(&self, session_id: u64) -> Vec<NodeId> {
+        let mut peers: Vec<NodeId> = self
+            .iter()
+            .filter(|&(_, session, _)| session == session_id)
+            .map(|(peer, _, _)| peer)
+            .collect();
+        peers.sort_unstable();
+        peers.dedup();
+        peers
+    }
+
+    /// Drops every fragment of a message, returning how many were cached.
+    pub(super) fn remove_message(&mut self, peer: NodeId, session_id: u64) -> usize {
+        let mut removed = 0;
+        self.packets.retain(|&(session, _), by_peer| {
+            if session == session_id && by_peer.remove(&peer).is_some() {
+                removed += 1;
+            }
+            !by_peer.is_empty()
+        });
+        removed
+    }
+
+    /// Copies of the fragments whose route starts with `first_hop`, along
+    /// with their fragment index.
+    pub(super) fn routed_through(&self, first_hop: NodeId) -> Vec<(u64, Packet)> {
+        self.packets
+            .iter()
+            .flat_map(|(&(_, fragment_index), by_peer)| {
+                by_peer
+                    .values()
+                    .filter(|cached| cached.packet.routing_header.hops.get(1) == Some(&first_hop))
+                    .map(move |cached| (fragment_index, cached.packet.clone()))
+            })
+            .collect()
+    }
+
+    /// Copies of every cached fragment with its Nack count.
+    pub(super) fn saved(&self) -> Vec<(Packet, u32)> {
+        self.iter()
+            .map(|(_, _, cached)| (cached.packet.clone(), cached.nacks))
+            .collect()
+    }
+
+    /// The fragments awaiting their Ack grouped by destination and
+    /// session, with how often each was retransmitted.
+    pub(super) fn in_flight(&self) -> Vec<InFlightMessage> {
+        let mut messages: BTreeMap<(NodeId, u64), (Vec<InFlightFragment>, Instant)> =
+            BTreeMap::new();
+        for (&(session_id, fragment_index), by_peer) in &self.packets {
+            for (&peer, cached) in by_peer {
+                let (fragments, oldest) = messages
+                    .entry((peer, session_id))
+                    .or_insert_with(|| (Vec::new(), cached.cached_at));
+                fragments.push(InFlightFragment {
+                    fragment_index,
+                    retransmissions: cached.nacks,
+                });
+                *oldest = (*oldest).min(cached.cached_at);
+            }
+        }
+
+        messages
+            .into_iter()
+            .map(|((destination, session_id), (mut fragments, oldest))| {
+                fragments.sort_by_key(|fragment| fragment.fragment_index);
+                InFlightMessage {
+                    destination,
+                    session_id,
+                    fragments,
+                    oldest_age: oldest.elapsed(),
+                }
+            })
+            .collect()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (NodeId, u64, &CachedPacket)> {
+        self.packets.iter().flat_map(|(&(session_id, _), by_peer)| {
+            by_peer
+                .iter()
+                .map(move |(&peer, cached)| (peer, session_id, cached))
+        })
+    }
+}
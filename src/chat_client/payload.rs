@@ -0,0 +1,121 @@
+use messages::client_commands::{ChatPayload, PeerStatus};
+use serde::{Deserialize, Serialize};
+
+/// Marks chat contents that carry a [`Payload`] instead of plain text.
+const PAYLOAD_PREFIX: &str = "\u{1}rustastic:";
+
+/// Client-to-client data carried inside `ClientMessage::SendMessage`.
+///
+/// The `CommunicationServer` only relays strings between clients, so every
+/// feature that needs peers to exchange structured data encodes it as a
+/// prefixed JSON string. Contents without the prefix are plain chat text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum Payload {
+    JoinRoom {
+        room_id: String,
+    },
+    LeaveRoom {
+        room_id: String,
+    },
+    RoomMessage {
+        room_id: String,
+        content: String,
+    },
+    FileManifest {
+        transfer_id: u64,
+        name: String,
+        size: u64,
+        chunks: u32,
+        checksum: u64,
+    },
+    FileChunk {
+        transfer_id: u64,
+        index: u32,
+        #[serde(with = "base64_bytes")]
+        data: Vec<u8>,
+    },
+    Status {
+        status: PeerStatus,
+    },
+    Typing,
+    Username {
+        username: String,
+    },
+    ChatMessage {
+        sequence: u64,
+        content: String,
+    },
+    Checked {
+        message_id: u64,
+        checksum: u64,
+        content: String,
+    },
+    ResendRequest {
+        message_id: u64,
+    },
+    /// A chat message, possibly a `ChatMessage`, with the time it was sent
+    /// on the sender's clock, in milliseconds since the Unix epoch.
+    Timestamped {
+        sent_at: u64,
+        content: String,
+    },
+    ChatChunk {
+        message_id: u64,
+        index: u32,
+        total: u32,
+        content: String,
+    },
+    Ping {
+        probe_id: u64,
+    },
+    Pong {
+        probe_id: u64,
+    },
+    RichMessage {
+        message_id: u64,
+        payload: ChatPayload,
+    },
+    /// A chat message sent by a client in auto-reply mode, which is never
+    /// replied to automatically so that two such clients do not loop.
+    AutoReply {
+        content: String,
+    },
+    /// A chat message between two personas, `0` standing for the client
+    /// itself.
+    PersonaMessage {
+        from: u16,
+        to: u16,
+        content: String,
+    },
+}
+
+impl Payload {
+    pub(crate) fn encode(&self) -> String {
+        format!(
+            "{PAYLOAD_PREFIX}{}",
+            serde_json::to_string(self).expect("payloads are always serializable")
+        )
+    }
+
+    pub(crate) fn decode(content: &str) -> Option<Self> {
+        serde_json::from_str(content.strip_prefix(PAYLOAD_PREFIX)?).ok()
+    }
+}
+
+/// Serializes bytes as a base64 string rather than as a JSON array of
+/// numbers, which is several times larger.
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use messages::client_commands::{ChatClientEvent, PeerInfo, PeerStatus};
+use wg_2024::network::NodeId;
+
+use super::{history::now_millis, ChatClient};
+
+/// The other clients this client knows about.
+///
+/// Peers are learned from the client lists sent by the server and from
+/// the messages relayed by it. Each one keeps when it was last heard
+/// from, the server it was learned from, its username and status, and
+/// how many of its messages have not been read yet.
+#[derive(Debug, Default)]
+pub(super) struct PeerRegistry {
+    peers: BTreeMap<NodeId, PeerInfo>,
+}
+
+impl PeerRegistry {
+    /// Peers restored from a `ClientSnapshot`.
+    pub(super) fn from_saved(peers: Vec<PeerInfo>) -> Self {
+        Self {
+            peers: peers.into_iter().map(|peer| (peer.id, peer)).collect(),
+        }
+    }
+
+    pub(super) fn save(&self) -> Vec<PeerInfo> {
+        self.peers.values().cloned().collect()
+    }
+
+    pub(super) fn contains(&self, id: NodeId) -> bool {
+        self.peers.contains_key(&id)
+    }
+
+    pub(super) fn ids(&self) -> Vec<NodeId> {
+        self.peers.keys().copied().collect()
+    }
+
+    /// Replaces the known peers with the client list sent by `server`,
+    /// keeping what is known about the ones still listed.
+    ///
+    /// # Returns
+    ///
+    /// The peers that were not known before, and the known ones that are
+    /// no longer listed.
+    pub(super) fn listed(&mut self, server: NodeId, ids: &[NodeId]) -> (Vec<NodeId>, Vec<NodeId>) {
+        let departed: Vec<NodeId> = self
+            .peers
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect();
+        self.peers.retain(|id, _| ids.contains(id));
+
+        let mut newcomers = Vec::new();
+        for &id in ids {
+            let peer = self.peers.entry(id).or_insert_with(|| {
+                newcomers.push(id);
+                new_peer(id)
+            });
+            peer.server = Some(server);
+        }
+        (newcomers, departed)
+    }
+
+    pub(super) fn remove(&mut self, id: NodeId) {
+        self.peers.remove(&id);
+    }
+
+    /// Records that `id` was just heard from through `server`.
+    pub(super) fn seen(&mut self, id: NodeId, server: NodeId) {
+        let peer = self.peers.entry(id).or_insert_with(|| new_peer(id));
+        peer.server = Some(server);
+        peer.last_seen = Some(now_millis());
+    }
+
+    /// Counts a message of `id` as unread.
+    ///
+    /// # Returns
+    ///
+    /// The messages of `id` now unread, `None` if it is not a known peer.
+    pub(super) fn message_received(&mut self, id: NodeId) -> Option<u32> {
+        let peer = self.peers.get_mut(&id)?;
+        peer.unread = peer.unread.saturating_add(1);
+        Some(peer.unread)
+    }
+
+    /// Sets the messages of `id` as read.
+    ///
+    /// # Returns
+    ///
+    /// Whether some of them were unread.
+    pub(super) fn mark_read(&mut self, id: NodeId) -> bool {
+        self.peers
+            .get_mut(&id)
+            .is_some_and(|peer| std::mem::take(&mut peer.unread) > 0)
+    }
+
+    /// Sets the username of `id`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the username changed.
+    pub(super) fn set_username(&mut self, id: NodeId, username: &str) -> bool {
+        let peer = self.peers.entry(id).or_insert_with(|| new_peer(id));
+        if peer.username.as_deref() == Some(username) {
+            return false;
+        }
+        peer.username = Some(username.to_string());
+        true
+    }
+
+    /// Sets the status of `id`.
+    ///
+    /// # Returns
+    ///
+    /// The status it had before.
+    pub(super) fn set_status(&mut self, id: NodeId, status: PeerStatus) -> PeerStatus {
+        let peer = self.peers.entry(id).or_insert_with(|| new_peer(id));
+        std::mem::replace(&mut peer.status, status)
+    }
+
+    /// The peers that are not offline yet, but were last heard from more
+    /// than `timeout_millis` milliseconds ago.
+    pub(super) fn silent(&self, timeout_millis: u64) -> Vec<NodeId> {
+        let now = now_millis();
+        self.peers
+            .values()
+            .filter(|peer| {
+                peer.status != PeerStatus::Offline
+                    && peer
+                        .last_seen
+                        .is_none_or(|seen| now.saturating_sub(seen) >= timeout_millis)
+            })
+            .map(|peer| peer.id)
+            .collect()
+    }
+
+    /// The usernames of the peers that announced one.
+    pub(super) fn usernames(&self) -> impl Iterator<Item = (NodeId, &String)> {
+        self.peers
+            .values()
+            .filter_map(|peer| peer.username.as_ref().map(|username| (peer.id, username)))
+    }
+}
+
+impl ChatClient {
+    /// Counts a message of `sender_id` as unread, telling the controller
+    /// how many are now.
+    pub(super) fn count_unread(&mut self, sender_id: NodeId) {
+        if let Some(count) = self.peers.message_received(sender_id) {
+            self.send_event(ChatClientEvent::UnreadCountChanged(sender_id, count));
+        }
+    }
+
+    pub(super) fn mark_read(&mut self, peer: NodeId) {
+        if self.peers.mark_read(peer) {
+            self.send_event(ChatClientEvent::UnreadCountChanged(peer, 0));
+        }
+    }
+}
+
+fn new_peer(id: NodeId) -> PeerInfo {
+    PeerInfo {
+        id,
+        server: None,
+        last_seen: None,
+        username: None,
+        status: PeerStatus::Offline,
+        unread: 0,
+    }
+}
@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use serde::{Deserialize, Serialize};
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// How long a chat message may wait for a registration or a route before
+/// it is discarded.
+const PENDING_MESSAGE_TTL: Duration = Duration::from_secs(90);
+
+/// A chat message that could not be sent yet.
+#[derive(Debug)]
+pub(super) struct PendingMessage {
+    recipient_id: NodeId,
+    content: String,
+    queued_at: Instant,
+}
+
+/// A [`PendingMessage`] as stored in a `ClientSnapshot`, with its age in
+/// place of the `Instant` it was queued at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct SavedPendingMessage {
+    recipient_id: NodeId,
+    content: String,
+    age: Duration,
+}
+
+impl ChatClient {
+    pub(super) fn queue_message(&mut self, recipient_id: NodeId, content: String) {
+        self.expire_pending_messages();
+
+        info!(
+            self,
+            "{} [ {} ]: Queued message to [ ChatClient {} ] until it can be sent",
+            "ℹ".blue(),
+            self.log_tag,
+            recipient_id
+        );
+        self.send_event(ChatClientEvent::MessageQueued(
+            recipient_id,
+            content.clone(),
+        ));
+        self.pending_messages.push_back(PendingMessage {
+            recipient_id,
+            content,
+            queued_at: Instant::now(),
+        });
+    }
+
+    pub(super) fn flush_pending_messages(&mut self) {
+        self.expire_pending_messages();
+
+        if self.registered.is_none() || self.pending_messages.is_empty() {
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Flushing {} queued messages",
+            "ℹ".blue(),
+            self.log_tag,
+            self.pending_messages.len()
+        );
+
+        while let Some(message) = self.pending_messages.pop_front() {
+            if self
+                .send_chat_message(message.recipient_id, message.content.clone())
+                .is_err()
+            {
+                // still no route to the server, the rest would fail as well
+                self.pending_messages.push_front(message);
+                return;
+            }
+
+            self.send_event(ChatClientEvent::MessageFlushed(
+                message.recipient_id,
+                message.content,
+            ));
+        }
+    }
+
+    pub(super) fn save_pending_messages(&self) -> Vec<SavedPendingMessage> {
+        self.pending_messages
+            .iter()
+            .map(|message| SavedPendingMessage {
+                recipient_id: message.recipient_id,
+                content: message.content.clone(),
+                age: message.queued_at.elapsed(),
+            })
+            .collect()
+    }
+
+    /// Puts the saved messages back in the queue, keeping the time they
+    /// already spent waiting so they still expire on schedule.
+    pub(super) fn restore_pending_messages(&mut self, saved: Vec<SavedPendingMessage>) {
+        let now = Instant::now();
+        self.pending_messages = saved
+            .into_iter()
+            .map(|message| PendingMessage {
+                recipient_id: message.recipient_id,
+                content: message.content,
+                queued_at: now.checked_sub(message.age).unwrap_or(now),
+            })
+            .collect();
+        self.expire_pending_messages();
+    }
+
+    fn expire_pending_messages(&mut self) {
+        let ttl = self.scaled(PENDING_MESSAGE_TTL);
+        while self
+            .pending_messages
+            .front()
+            .is_some_and(|message| message.queued_at.elapsed() >= ttl)
+        {
+            let Some(message) = self.pending_messages.pop_front() else {
+                break;
+            };
+            warn!(
+                self,
+                "{} [ {} ]: Queued message to [ ChatClient {} ] expired",
+                "!!!".yellow(),
+                self.log_tag,
+                message.recipient_id
+            );
+            self.send_event(ChatClientEvent::QueuedMessageExpired(
+                message.recipient_id,
+                message.content,
+            ));
+        }
+    }
+}
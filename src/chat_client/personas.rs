@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, MessageDirection, PersonaInfo};
+use wg_2024::network::NodeId;
+
+use super::{error::ChatClientError, history::MessageHistory, payload::Payload, ChatClient};
+
+/// The persona standing for the client itself, which every client has.
+pub(super) const MAIN_PERSONA: u16 = 0;
+
+#[derive(Debug, Default)]
+struct Persona {
+    name: String,
+    /// The personas of other clients it exchanged messages with.
+    peers: BTreeSet<(NodeId, u16)>,
+    history: MessageHistory,
+}
+
+/// The chat identities hosted by this client on top of its own, so that a
+/// simulation can run many of them without a thread per identity.
+///
+/// Personas share the packet channels and the registration of the client,
+/// servers only knowing nodes, and tell each other apart with the persona
+/// ids carried by `Payload::PersonaMessage`. Each one keeps its own peers
+/// and history.
+#[derive(Debug, Default)]
+pub(super) struct Personas {
+    personas: BTreeMap<u16, Persona>,
+}
+
+impl ChatClient {
+    pub(super) fn has_persona(&self, persona: u16) -> bool {
+        persona == MAIN_PERSONA || self.personas.personas.contains_key(&persona)
+    }
+
+    /// Hosts `persona` under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the persona is already hosted or is the main
+    /// persona.
+    pub(super) fn add_persona(
+        &mut self,
+        persona: u16,
+        name: String,
+    ) -> Result<(), ChatClientError> {
+        if self.has_persona(persona) {
+            return Err(ChatClientError::PersonaExists(persona));
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Hosting persona {} ({})",
+            "✓".green(),
+            self.log_tag,
+            persona,
+            name
+        );
+        self.personas.personas.insert(
+            persona,
+            Persona {
+                name,
+                ..Persona::default()
+            },
+        );
+        Ok(())
+    }
+
+    pub(super) fn remove_persona(&mut self, persona: u16) {
+        if let Some(removed) = self.personas.personas.remove(&persona) {
+            info!(
+                self,
+                "{} [ {} ]: No longer hosting persona {} ({})",
+                "ℹ".blue(),
+                self.log_tag,
+                persona,
+                removed.name
+            );
+        }
+    }
+
+    /// Sends `content` from the local `persona` to `recipient_persona` of
+    /// `recipient_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `persona` is not hosted, if the client is not
+    /// registered, or if the message cannot be sent.
+    pub(super) fn send_as_persona(
+        &mut self,
+        persona: u16,
+        recipient_id: NodeId,
+        recipient_persona: u16,
+        content: String,
+    ) -> Result<(), ChatClientError> {
+        if !self.has_persona(persona) {
+            return Err(ChatClientError::UnknownPersona(persona));
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Sending as persona {} to persona {} of [ ChatClient {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            persona,
+            recipient_persona,
+            recipient_id
+        );
+        self.send_payload(
+            recipient_id,
+            &Payload::PersonaMessage {
+                from: persona,
+                to: recipient_persona,
+                content: content.clone(),
+            },
+        )?;
+
+        if let Some(local) = self.personas.personas.get_mut(&persona) {
+            local.peers.insert((recipient_id, recipient_persona));
+            local
+                .history
+                .record(recipient_id, MessageDirection::Sent, content);
+        } else {
+            self.history
+                .record(recipient_id, MessageDirection::Sent, content);
+        }
+        Ok(())
+    }
+
+    pub(super) fn persona_message_received(
+        &mut self,
+        sender_id: NodeId,
+        from: u16,
+        to: u16,
+        content: String,
+    ) {
+        if !self.has_persona(to) {
+            warn!(
+                self,
+                "{} [ {} ]: Dropping a message of [ Client {} ] to unknown persona {}",
+                "!!!".yellow(),
+                self.log_tag,
+                sender_id,
+                to
+            );
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Persona {} received a message from persona {} of [ Client {} ]: {}",
+            "✓".green(),
+            self.log_tag,
+            to,
+            from,
+            sender_id,
+            content
+        );
+        if let Some(local) = self.personas.personas.get_mut(&to) {
+            local.peers.insert((sender_id, from));
+            local
+                .history
+                .record(sender_id, MessageDirection::Received, content.clone());
+        } else {
+            self.count_unread(sender_id);
+            self.history
+                .record(sender_id, MessageDirection::Received, content.clone());
+        }
+        self.send_event(ChatClientEvent::PersonaMessageReceived {
+            persona: to,
+            from: (sender_id, from),
+            content,
+        });
+    }
+
+    /// Sends the peers and the history of a hosted persona.
+    pub(super) fn send_persona_info(&self, persona: u16) {
+        if let Some(local) = self.personas.personas.get(&persona) {
+            self.send_event(ChatClientEvent::Persona(PersonaInfo {
+                id: persona,
+                name: local.name.clone(),
+                peers: local.peers.iter().copied().collect(),
+                history: local.history.entries().to_vec(),
+            }));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, PeerStatus};
+use wg_2024::network::NodeId;
+
+use super::{payload::Payload, ChatClient};
+
+/// How often the client's own status is sent to every known peer.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time without a heartbeat after which a peer is considered offline.
+const PRESENCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Minimum time between two typing notifications to the same peer.
+const TYPING_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Status of the client, the status of its peers is kept in the
+/// `PeerRegistry`.
+#[derive(Debug)]
+pub(super) struct Presence {
+    status: PeerStatus,
+    last_heartbeat: Option<Instant>,
+    typing_sent: HashMap<NodeId, Instant>,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Self {
+            status: PeerStatus::Online,
+            last_heartbeat: None,
+            typing_sent: HashMap::new(),
+        }
+    }
+}
+
+impl ChatClient {
+    pub(super) fn set_status(&mut self, status: PeerStatus) {
+        info!(
+            self,
+            "{} [ {} ]: Status set to {:?}",
+            "ℹ".blue(),
+            self.log_tag,
+            status
+        );
+        self.presence.status = status;
+        self.send_heartbeat();
+    }
+
+    pub(super) fn notify_typing(&mut self, peer: NodeId) {
+        if self
+            .presence
+            .typing_sent
+            .get(&peer)
+            .is_some_and(|sent| sent.elapsed() < self.scaled(TYPING_INTERVAL))
+        {
+            return;
+        }
+
+        if self.send_payload(peer, &Payload::Typing).is_ok() {
+            self.presence.typing_sent.insert(peer, Instant::now());
+        }
+    }
+
+    /// Sends the client's status to every known peer, called from the run
+    /// loop every [`HEARTBEAT_INTERVAL`] and whenever the status changes.
+    pub(super) fn send_heartbeat(&mut self) {
+        if !self.running || self.registered.is_none() {
+            return;
+        }
+
+        let payload = Payload::Status {
+            status: self.presence.status,
+        };
+        for peer in self.peers.ids() {
+            let _ = self.send_payload(peer, &payload);
+        }
+        self.presence.last_heartbeat = Some(Instant::now());
+    }
+
+    pub(super) fn check_presence(&mut self) {
+        if self
+            .presence
+            .last_heartbeat
+            .is_none_or(|sent| sent.elapsed() >= self.scaled(HEARTBEAT_INTERVAL))
+        {
+            self.send_heartbeat();
+        }
+
+        let timeout = u64::try_from(self.scaled(PRESENCE_TIMEOUT).as_millis()).unwrap_or(u64::MAX);
+        for peer in self.peers.silent(timeout) {
+            self.peers.set_status(peer, PeerStatus::Offline);
+            self.send_event(ChatClientEvent::PeerStatusChanged(
+                peer,
+                PeerStatus::Offline,
+            ));
+        }
+    }
+
+    pub(super) fn peer_status_received(&mut self, sender_id: NodeId, status: PeerStatus) {
+        if self.peers.set_status(sender_id, status) != status {
+            info!(
+                self,
+                "{} [ {} ]: [ Client {} ] is now {:?}",
+                "ℹ".blue(),
+                self.log_tag,
+                sender_id,
+                status
+            );
+            self.send_event(ChatClientEvent::PeerStatusChanged(sender_id, status));
+        }
+    }
+
+    pub(super) fn peer_typing(&self, sender_id: NodeId) {
+        self.send_event(ChatClientEvent::PeerTyping(sender_id));
+    }
+}
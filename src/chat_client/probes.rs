@@ -0,0 +1,172 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::{
+    client_commands::ChatClientEvent,
+    high_level_messages::{ClientMessage, MessageContent},
+};
+use wg_2024::network::NodeId;
+
+use super::{error::ChatClientError, payload::Payload, ChatClient};
+
+/// Time a probe is given to be answered before it is reported lost.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Inverse weight of a new sample in the smoothed round-trip time, as TCP
+/// computes it.
+const RTT_SMOOTHING: u32 = 8;
+
+/// Round-trip time probes sent with `ChatClientCommand::Ping`.
+///
+/// A server is probed with a `GetServerType`, answered by any message of
+/// the server, and a client with a `Payload::Ping` it echoes back as a
+/// `Payload::Pong`. Both go through the normal message path, so they
+/// measure what chat messages experience.
+#[derive(Debug, Default)]
+pub(super) struct Probes {
+    /// Probes to servers waiting for an answer, by server.
+    servers: HashMap<NodeId, Instant>,
+    /// Probes to clients waiting for their echo, by probe id.
+    peers: HashMap<u64, (NodeId, Instant)>,
+    /// The smoothed round-trip time to every node that answered a probe.
+    rtt: BTreeMap<NodeId, Duration>,
+}
+
+impl ChatClient {
+    /// Whether `destination` can be probed, being a server or a known
+    /// client.
+    pub(super) fn is_probeable(&self, destination: NodeId) -> bool {
+        self.communication_server_list.contains(destination) || self.peers.contains(destination)
+    }
+
+    /// Sends a round-trip time probe to `destination`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the probe cannot be sent.
+    pub(super) fn ping(&mut self, destination: NodeId) -> Result<(), ChatClientError> {
+        info!(
+            self,
+            "{} [ {} ]: Probing [ Node {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            destination
+        );
+
+        if self.communication_server_list.contains(destination) {
+            self.probes.servers.insert(destination, Instant::now());
+            self.communication_server_list.queried(destination);
+            let message_content = MessageContent::FromClient(ClientMessage::GetServerType);
+            let sent = self.generate_and_send_message(message_content, destination);
+            if sent.is_err() {
+                self.probes.servers.remove(&destination);
+            }
+            return sent;
+        }
+
+        let probe_id = rand::random::<u64>();
+        self.probes
+            .peers
+            .insert(probe_id, (destination, Instant::now()));
+        let sent = self.send_payload(destination, &Payload::Ping { probe_id });
+        if sent.is_err() {
+            self.probes.peers.remove(&probe_id);
+        }
+        sent
+    }
+
+    /// Echoes a probe of `sender_id` back.
+    pub(super) fn ping_received(&mut self, sender_id: NodeId, probe_id: u64) {
+        let _ = self.send_payload(sender_id, &Payload::Pong { probe_id });
+    }
+
+    pub(super) fn pong_received(&mut self, sender_id: NodeId, probe_id: u64) {
+        if let Some(&(destination, sent_at)) = self.probes.peers.get(&probe_id) {
+            if destination == sender_id {
+                self.probes.peers.remove(&probe_id);
+                self.rtt_sample(sender_id, sent_at.elapsed());
+            }
+        }
+    }
+
+    /// Any message from a server answers the probe sent to it.
+    pub(super) fn server_probe_answered(&mut self, server_id: NodeId) {
+        if let Some(sent_at) = self.probes.servers.remove(&server_id) {
+            self.rtt_sample(server_id, sent_at.elapsed());
+        }
+    }
+
+    /// Reports the probes that were not answered within [`PROBE_TIMEOUT`].
+    pub(super) fn expire_probes(&mut self) {
+        let timeout = self.scaled(PROBE_TIMEOUT);
+        let mut lost: Vec<NodeId> = self
+            .probes
+            .servers
+            .iter()
+            .filter(|(_, sent_at)| sent_at.elapsed() >= timeout)
+            .map(|(&server_id, _)| server_id)
+            .collect();
+        self.probes
+            .servers
+            .retain(|_, sent_at| sent_at.elapsed() < timeout);
+        self.probes.peers.retain(|_, &mut (destination, sent_at)| {
+            let expired = sent_at.elapsed() >= timeout;
+            if expired {
+                lost.push(destination);
+            }
+            !expired
+        });
+
+        for destination in lost {
+            warn!(
+                self,
+                "{} [ {} ]: Probe to [ Node {} ] was not answered within {:?}",
+                "!!!".yellow(),
+                self.log_tag,
+                destination,
+                PROBE_TIMEOUT
+            );
+            self.send_event(ChatClientEvent::PingTimedOut(destination));
+        }
+    }
+
+    /// Sends the smoothed round-trip times measured so far.
+    pub(super) fn report_round_trip_times(&self) {
+        if !self.probes.rtt.is_empty() {
+            self.send_event(ChatClientEvent::RoundTripTimes(
+                self.probes
+                    .rtt
+                    .iter()
+                    .map(|(&node_id, &rtt)| (node_id, rtt))
+                    .collect(),
+            ));
+        }
+    }
+
+    fn rtt_sample(&mut self, destination: NodeId, sample: Duration) {
+        let smoothed = *self
+            .probes
+            .rtt
+            .entry(destination)
+            .and_modify(|rtt| *rtt = (*rtt * (RTT_SMOOTHING - 1) + sample) / RTT_SMOOTHING)
+            .or_insert(sample);
+
+        info!(
+            self,
+            "{} [ {} ]: Round trip to [ Node {} ] took {:?}, {:?} on average",
+            "✓".green(),
+            self.log_tag,
+            destination,
+            sample,
+            smoothed
+        );
+        self.send_event(ChatClientEvent::RoundTripTime {
+            destination,
+            sample,
+            smoothed,
+        });
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// How far along the Acks of one message are.
+#[derive(Debug)]
+struct MessageProgress {
+    destination: NodeId,
+    acked: u64,
+    total: u64,
+}
+
+/// The progress of the messages in flight, keyed by their message id,
+/// the session id of their fragments.
+///
+/// `SendProgress` events are only emitted on their own for messages of
+/// more than one fragment, smaller ones are reported on request.
+#[derive(Debug, Default)]
+pub(super) struct SendProgress {
+    messages: HashMap<u64, MessageProgress>,
+}
+
+impl SendProgress {
+    pub(super) fn started(&mut self, message_id: u64, destination: NodeId, total: u64) {
+        self.messages.insert(
+            message_id,
+            MessageProgress {
+                destination,
+                acked: 0,
+                total,
+            },
+        );
+    }
+
+    pub(super) fn contains(&self, message_id: u64) -> bool {
+        self.messages.contains_key(&message_id)
+    }
+
+    pub(super) fn forget(&mut self, destination: NodeId, message_id: u64) {
+        if self
+            .messages
+            .get(&message_id)
+            .is_some_and(|progress| progress.destination == destination)
+        {
+            self.messages.remove(&message_id);
+        }
+    }
+}
+
+impl ChatClient {
+    /// Counts an acknowledged fragment of `message_id` toward `destination`.
+    pub(super) fn progress_acked(&mut self, destination: NodeId, message_id: u64) {
+        let Some(progress) = self.progress.messages.get_mut(&message_id) else {
+            return;
+        };
+        if progress.destination != destination {
+            return;
+        }
+
+        progress.acked += 1;
+        let (acked, total) = (progress.acked, progress.total);
+        if acked >= total {
+            self.progress.messages.remove(&message_id);
+        }
+        if total > 1 {
+            self.send_event(ChatClientEvent::SendProgress(message_id, acked, total));
+        }
+    }
+
+    pub(super) fn report_progress(&self, message_id: u64) {
+        if let Some(progress) = self.progress.messages.get(&message_id) {
+            self.send_event(ChatClientEvent::SendProgress(
+                message_id,
+                progress.acked,
+                progress.total,
+            ));
+        }
+    }
+}
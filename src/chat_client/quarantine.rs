@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::{network::NodeId, packet::Packet};
+
+use super::{config::UnknownSourcePolicy, ChatClient};
+
+/// Time an unknown source is given to become known before its fragments
+/// are discarded. Rejected sources are reported again after as long.
+const QUARANTINE_TTL: Duration = Duration::from_secs(30);
+
+/// Fragments held back per unknown source, past which new ones are
+/// discarded.
+const QUARANTINE_CAPACITY: usize = 256;
+
+#[derive(Debug)]
+struct HeldSource {
+    since: Instant,
+    fragments: Vec<Packet>,
+}
+
+/// Sources of fragments the client never heard of, handled according to
+/// the configured `UnknownSourcePolicy`.
+#[derive(Debug, Default)]
+pub(super) struct SourceQuarantine {
+    /// Quarantined sources, with their fragments in arrival order.
+    held: HashMap<NodeId, HeldSource>,
+    /// Rejected sources, by when they were last reported.
+    rejected: HashMap<NodeId, Instant>,
+}
+
+impl ChatClient {
+    /// Whether the client heard of `source_id` as a server, a client, a
+    /// neighbour or a node of its topology.
+    fn is_known_source(&self, source_id: NodeId) -> bool {
+        self.communication_server_list.contains(source_id)
+            || self.peers.contains(source_id)
+            || self.packet_send.contains_key(&source_id)
+            || self.topology.contains(source_id)
+    }
+
+    /// Holds back or discards an acknowledged fragment coming from an
+    /// unknown source.
+    ///
+    /// # Returns
+    ///
+    /// Whether the fragment was taken away, `false` if it must be handled
+    /// as usual.
+    pub(super) fn quarantined(&mut self, packet: &Packet) -> bool {
+        let policy = self.config.unknown_sources;
+        if policy == UnknownSourcePolicy::Accept {
+            return false;
+        }
+        let Some(source_id) = packet.routing_header.source() else {
+            return false;
+        };
+        if self.is_known_source(source_id) {
+            return false;
+        }
+
+        match policy {
+            UnknownSourcePolicy::Accept => false,
+            UnknownSourcePolicy::Reject => {
+                let ttl = self.scaled(QUARANTINE_TTL);
+                let reported = self.quarantine.rejected.get(&source_id);
+                if reported.is_none_or(|reported_at| reported_at.elapsed() >= ttl) {
+                    self.quarantine.rejected.insert(source_id, Instant::now());
+                    warn!(
+                        self,
+                        "{} [ {} ]: Rejecting the fragments of unknown [ Node {} ]",
+                        "!!!".yellow(),
+                        self.log_tag,
+                        source_id
+                    );
+                    self.send_event(ChatClientEvent::UnknownSource(source_id));
+                }
+                true
+            }
+            UnknownSourcePolicy::Quarantine { flood } => {
+                if let Some(held) = self.quarantine.held.get_mut(&source_id) {
+                    if held.fragments.len() < QUARANTINE_CAPACITY {
+                        held.fragments.push(packet.clone());
+                    }
+                    return true;
+                }
+
+                warn!(
+                    self,
+                    "{} [ {} ]: Quarantining the fragments of unknown [ Node {} ]",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    source_id
+                );
+                self.quarantine.held.insert(
+                    source_id,
+                    HeldSource {
+                        since: Instant::now(),
+                        fragments: vec![packet.clone()],
+                    },
+                );
+                self.send_event(ChatClientEvent::UnknownSource(source_id));
+                if flood {
+                    self.start_flood();
+                }
+                true
+            }
+        }
+    }
+
+    /// Handles the fragments of the quarantined sources that became known,
+    /// and discards the ones of the sources that stayed unknown for
+    /// [`QUARANTINE_TTL`].
+    pub(super) fn check_quarantine(&mut self) {
+        let ttl = self.scaled(QUARANTINE_TTL);
+        self.quarantine
+            .rejected
+            .retain(|_, reported_at| reported_at.elapsed() < ttl);
+        if self.quarantine.held.is_empty() {
+            return;
+        }
+
+        let settled: Vec<NodeId> = self
+            .quarantine
+            .held
+            .iter()
+            .filter(|&(&source_id, held)| {
+                self.is_known_source(source_id) || held.since.elapsed() >= ttl
+            })
+            .map(|(&source_id, _)| source_id)
+            .collect();
+
+        for source_id in settled {
+            let Some(held) = self.quarantine.held.remove(&source_id) else {
+                continue;
+            };
+            if self.is_known_source(source_id) {
+                info!(
+                    self,
+                    "{} [ {} ]: [ Node {} ] is now known, releasing {} quarantined fragments",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    source_id,
+                    held.fragments.len()
+                );
+                for packet in held.fragments {
+                    self.accept_fragment(packet);
+                }
+            } else {
+                warn!(
+                    self,
+                    "{} [ {} ]: [ Node {} ] stayed unknown, discarding {} quarantined fragments",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    source_id,
+                    held.fragments.len()
+                );
+            }
+        }
+    }
+}
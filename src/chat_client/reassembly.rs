@@ -0,0 +1,41 @@
+use wg_2024::packet::{Fragment, Packet, PacketType, FRAGMENT_DSIZE};
+
+/// Splits the fragments of a message anew so that each carries at most
+/// `size` bytes, keeping their session id and routing header.
+pub(super) fn resize_fragments(fragments: Vec<Packet>, size: usize) -> Vec<Packet> {
+    let Some(template) = fragments.first().cloned() else {
+        return fragments;
+    };
+    if size >= FRAGMENT_DSIZE {
+        return fragments;
+    }
+
+    let mut data = Vec::new();
+    for packet in &fragments {
+        if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+            data.extend_from_slice(&fragment.data[..usize::from(fragment.length)]);
+        }
+    }
+
+    let chunks: Vec<&[u8]> = data.chunks(size).collect();
+    let total = u64::try_from(chunks.len()).unwrap_or(u64::MAX);
+    chunks
+        .into_iter()
+        .zip(0..)
+        .map(|(chunk, fragment_index)| Packet {
+            pack_type: PacketType::MsgFragment(fragment_of(chunk, fragment_index, total)),
+            ..template.clone()
+        })
+        .collect()
+}
+
+pub(super) fn fragment_of(chunk: &[u8], fragment_index: u64, total_n_fragments: u64) -> Fragment {
+    let mut data = [0; FRAGMENT_DSIZE];
+    data[..chunk.len()].copy_from_slice(chunk);
+    Fragment {
+        fragment_index,
+        total_n_fragments,
+        length: u8::try_from(chunk.len()).unwrap_or(u8::MAX),
+        data,
+    }
+}
@@ -0,0 +1,107 @@
+use colored::Colorize;
+use messages::client_commands::{ChatClientEvent, ChatPayload, MessageDirection};
+use wg_2024::network::NodeId;
+
+use super::{error::ChatClientError, payload::Payload, ChatClient};
+
+/// The text a rich message is recorded as in the history.
+fn history_text(payload: &ChatPayload) -> Option<&str> {
+    match payload {
+        ChatPayload::Text(text) | ChatPayload::Formatted { text, .. } => Some(text),
+        ChatPayload::Reaction { .. } => None,
+    }
+}
+
+impl ChatClient {
+    /// Sends a structured chat message to `recipient_id`, under a new
+    /// message id reported with `ChatClientEvent::RichMessageSent` so that
+    /// the controller can react to it later.
+    ///
+    /// Plain chat text keeps going through `SendMessageTo` as a bare string,
+    /// which every client understands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client is not registered, or if the message
+    /// cannot be sent.
+    pub(super) fn send_rich_message(
+        &mut self,
+        recipient_id: NodeId,
+        payload: ChatPayload,
+    ) -> Result<(), ChatClientError> {
+        let message_id = rand::random::<u64>();
+        info!(
+            self,
+            "{} [ {} ]: Sending rich message {} to [ ChatClient {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            message_id,
+            recipient_id
+        );
+
+        let text = history_text(&payload).map(str::to_string);
+        self.send_payload(
+            recipient_id,
+            &Payload::RichMessage {
+                message_id,
+                payload,
+            },
+        )?;
+        if let Some(text) = text {
+            self.history
+                .record(recipient_id, MessageDirection::Sent, text);
+        }
+        self.send_event(ChatClientEvent::RichMessageSent(recipient_id, message_id));
+        Ok(())
+    }
+
+    pub(super) fn rich_message_received(
+        &mut self,
+        sender_id: NodeId,
+        message_id: u64,
+        payload: ChatPayload,
+    ) {
+        if let ChatPayload::Reaction {
+            message_id,
+            reaction,
+        } = payload
+        {
+            info!(
+                self,
+                "{} [ {} ]: [ Client {} ] reacted to message {} with {}",
+                "✓".green(),
+                self.log_tag,
+                sender_id,
+                message_id,
+                reaction
+            );
+            self.send_event(ChatClientEvent::ReactionReceived {
+                from: sender_id,
+                message_id,
+                reaction,
+            });
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Rich message {} received from [ Client {} ]: {:?}",
+            "✓".green(),
+            self.log_tag,
+            message_id,
+            sender_id,
+            payload
+        );
+        self.count_unread(sender_id);
+        self.bot_think();
+        if let Some(text) = history_text(&payload) {
+            self.history
+                .record(sender_id, MessageDirection::Received, text.to_string());
+        }
+        self.send_event(ChatClientEvent::MessageReceivedRich {
+            from: sender_id,
+            message_id,
+            payload,
+        });
+    }
+}
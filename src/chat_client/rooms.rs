@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::{payload::Payload, ChatClient};
+
+impl ChatClient {
+    pub(super) fn create_room(&mut self, room_id: String) {
+        if self.rooms.contains_key(&room_id) {
+            warn!(
+                self,
+                "{} [ {} ]: Room {} already exists",
+                "!!!".yellow(),
+                self.log_tag,
+                room_id
+            );
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Created room {}",
+            "✓".green(),
+            self.log_tag,
+            room_id
+        );
+        self.rooms.insert(room_id, HashSet::new());
+    }
+
+    pub(super) fn join_room(&mut self, room_id: String) {
+        let peers = self.peers.ids();
+
+        info!(
+            self,
+            "{} [ {} ]: Joining room {}, announcing to {:?}",
+            "ℹ".blue(),
+            self.log_tag,
+            room_id,
+            peers
+        );
+
+        let payload = Payload::JoinRoom {
+            room_id: room_id.clone(),
+        };
+        for peer in peers {
+            let _ = self.send_payload(peer, &payload);
+        }
+        self.rooms.entry(room_id).or_default();
+    }
+
+    pub(super) fn leave_room(&mut self, room_id: &str) {
+        if let Some(members) = self.rooms.remove(room_id) {
+            info!(
+                self,
+                "{} [ {} ]: Leaving room {}",
+                "ℹ".blue(),
+                self.log_tag,
+                room_id
+            );
+            let payload = Payload::LeaveRoom {
+                room_id: room_id.to_string(),
+            };
+            for member in members {
+                let _ = self.send_payload(member, &payload);
+            }
+        } else {
+            error!(
+                self,
+                "{} [ {} ]: Cannot leave room {}, not a member",
+                "✗".red(),
+                self.log_tag,
+                room_id
+            );
+        }
+    }
+
+    pub(super) fn send_room_message(&mut self, room_id: &str, text: String) {
+        if let Some(members) = self.rooms.get(room_id) {
+            let members: Vec<NodeId> = members.iter().copied().collect();
+            info!(
+                self,
+                "{} [ {} ]: Sending message to room {}, members: {:?}",
+                "ℹ".blue(),
+                self.log_tag,
+                room_id,
+                members
+            );
+            let payload = Payload::RoomMessage {
+                room_id: room_id.to_string(),
+                content: text,
+            };
+            for member in members {
+                let _ = self.send_payload(member, &payload);
+            }
+        } else {
+            error!(
+                self,
+                "{} [ {} ]: Cannot send message to room {}, not a member",
+                "✗".red(),
+                self.log_tag,
+                room_id
+            );
+        }
+    }
+
+    pub(super) fn room_member_joined(&mut self, sender_id: NodeId, room_id: String) {
+        let Some(members) = self.rooms.get_mut(&room_id) else {
+            return;
+        };
+
+        // answer only newcomers, so that the joining client learns about us
+        // without the two sides bouncing announcements forever
+        if members.insert(sender_id) {
+            info!(
+                self,
+                "{} [ {} ]: [ Client {} ] joined room {}",
+                "✓".green(),
+                self.log_tag,
+                sender_id,
+                room_id
+            );
+            let _ = self.send_payload(sender_id, &Payload::JoinRoom { room_id });
+        }
+    }
+
+    pub(super) fn room_member_left(&mut self, sender_id: NodeId, room_id: &str) {
+        if let Some(members) = self.rooms.get_mut(room_id) {
+            if members.remove(&sender_id) {
+                info!(
+                    self,
+                    "{} [ {} ]: [ Client {} ] left room {}",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    sender_id,
+                    room_id
+                );
+            }
+        }
+    }
+
+    pub(super) fn room_message_received(
+        &mut self,
+        sender_id: NodeId,
+        room_id: String,
+        content: String,
+    ) {
+        let Some(members) = self.rooms.get_mut(&room_id) else {
+            warn!(
+                self,
+                "{} [ {} ]: Dropping message for room {}, not a member",
+                "!!!".yellow(),
+                self.log_tag,
+                room_id
+            );
+            return;
+        };
+        members.insert(sender_id);
+
+        info!(
+            self,
+            "{} [ {} ]: Room {} message received from [ Client {} ]: {}",
+            "✓".green(),
+            self.log_tag,
+            room_id,
+            sender_id,
+            content
+        );
+
+        self.send_event(ChatClientEvent::RoomMessageReceived(
+            room_id, sender_id, content,
+        ));
+    }
+}
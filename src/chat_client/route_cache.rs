@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+
+use super::ChatClient;
+
+/// Routes computed by the `Router`, kept until the client's view of the
+/// topology changes.
+///
+/// Computing a route walks every path to the destination, so it is only
+/// done again after a flood response, a Nack or a neighbour change.
+/// Routes pinned by the controller take precedence over the `Router`'s
+/// until they are unpinned or stop matching the known topology. Drones
+/// blacklisted by the controller are never crossed, pinned routes
+/// included.
+#[derive(Debug, Default)]
+pub(super) struct RouteCache {
+    routes: HashMap<NodeId, SourceRoutingHeader>,
+    pinned: HashMap<NodeId, Vec<NodeId>>,
+    blacklist: HashSet<NodeId>,
+}
+
+impl ChatClient {
+    pub(super) fn route_to(&mut self, destination: NodeId) -> Option<SourceRoutingHeader> {
+        if let Some(hops) = self.route_cache.pinned.get(&destination) {
+            return Some(SourceRoutingHeader {
+                hop_index: 1,
+                hops: hops.clone(),
+            });
+        }
+        if let Some(route) = self.route_cache.routes.get(&destination) {
+            return Some(route.clone());
+        }
+
+        let route = self.router.get_source_routing_header(destination)?;
+        let route = self.avoid_blacklisted(destination, route)?;
+        let route = self.avoid_lossy_drone(destination, route);
+        let route = self.avoid_down_links(destination, route)?;
+        self.route_cache.routes.insert(destination, route.clone());
+        Some(route)
+    }
+
+    /// The route to `destination`, provided it leaves through a current
+    /// neighbour.
+    ///
+    /// The `Router` may still give a route through a neighbour that is
+    /// gone, as right after a `RemoveSender`, and every fragment sent along
+    /// it would fail at once. Such a route is forgotten, unpinned if it was
+    /// pinned, and replaced by the shortest known path leaving through a
+    /// current neighbour.
+    pub(super) fn route_through_neighbors(
+        &mut self,
+        destination: NodeId,
+    ) -> Option<SourceRoutingHeader> {
+        let route = self.route_to(destination)?;
+        let Some(&first_hop) = route.hops.get(1) else {
+            return Some(route);
+        };
+        if self.packet_send.contains_key(&first_hop) {
+            return Some(route);
+        }
+
+        warn!(
+            self,
+            "{} [ {} ]: Route {:?} to [ {} ] leaves through [ Drone {} ], which is not a neighbour",
+            "!!!".yellow(),
+            self.log_tag,
+            route.hops,
+            destination,
+            first_hop
+        );
+        self.route_cache.routes.remove(&destination);
+        if self.route_cache.pinned.remove(&destination).is_some() {
+            self.send_event(ChatClientEvent::PinnedRouteInvalid(
+                destination,
+                route.hops.clone(),
+            ));
+        }
+
+        let mut excluded = self.route_cache.blacklist.clone();
+        let mut first_hop = first_hop;
+        loop {
+            if first_hop == destination || !excluded.insert(first_hop) {
+                return None;
+            }
+            let hops = self
+                .topology
+                .path_avoiding(self.id, destination, &excluded)?;
+            first_hop = *hops.get(1)?;
+            if self.packet_send.contains_key(&first_hop) {
+                let route = SourceRoutingHeader {
+                    hop_index: route.hop_index,
+                    hops,
+                };
+                self.route_cache.routes.insert(destination, route.clone());
+                return Some(route);
+            }
+        }
+    }
+
+    /// Replaces a route crossing a blacklisted drone by the shortest known
+    /// one that does not, or gives up on it if there is none.
+    fn avoid_blacklisted(
+        &self,
+        destination: NodeId,
+        route: SourceRoutingHeader,
+    ) -> Option<SourceRoutingHeader> {
+        if self.blacklisted_drone(&route.hops).is_none() {
+            return Some(route);
+        }
+
+        let hops =
+            self.topology
+                .path_avoiding(self.id, destination, &self.route_cache.blacklist)?;
+        Some(SourceRoutingHeader {
+            hop_index: route.hop_index,
+            hops,
+        })
+    }
+
+    /// Replaces the `Router`'s route by one avoiding its least reliable
+    /// drone when the topology mirror knows such a route and expects it
+    /// to drop fewer fragments.
+    fn avoid_lossy_drone(
+        &self,
+        destination: NodeId,
+        route: SourceRoutingHeader,
+    ) -> SourceRoutingHeader {
+        let Some(lossy) = self.topology.least_reliable_drone(&route.hops) else {
+            return route;
+        };
+        let mut excluded = self.route_cache.blacklist.clone();
+        excluded.insert(lossy);
+        let Some(hops) = self.topology.path_avoiding(self.id, destination, &excluded) else {
+            return route;
+        };
+
+        if self.topology.route_reliability(&hops) > self.topology.route_reliability(&route.hops) {
+            SourceRoutingHeader {
+                hop_index: route.hop_index,
+                hops,
+            }
+        } else {
+            route
+        }
+    }
+
+    pub(super) fn invalidate_routes(&mut self) {
+        self.route_cache.routes.clear();
+
+        let invalid: Vec<(NodeId, Vec<NodeId>)> = self
+            .route_cache
+            .pinned
+            .iter()
+            .filter(|(&destination, hops)| self.pinned_route_problem(destination, hops).is_some())
+            .map(|(&destination, hops)| (destination, hops.clone()))
+            .collect();
+        for (destination, hops) in invalid {
+            self.route_cache.pinned.remove(&destination);
+            warn!(
+                self,
+                "{} [ {} ]: Unpinning route {:?} to [ {} ], it is no longer valid",
+                "!!!".yellow(),
+                self.log_tag,
+                hops,
+                destination
+            );
+            self.send_event(ChatClientEvent::PinnedRouteInvalid(destination, hops));
+        }
+    }
+
+    /// Forces every packet to `destination` along `hops`, which must start
+    /// at this client and only cross links the topology knows.
+    pub(super) fn pin_route(&mut self, destination: NodeId, hops: Vec<NodeId>) {
+        if let Some(problem) = self.pinned_route_problem(destination, &hops) {
+            error!(
+                self,
+                "{} [ {} ]: Cannot pin route {:?} to [ {} ]: {}",
+                "✗".red(),
+                self.log_tag,
+                hops,
+                destination,
+                problem
+            );
+            self.send_event(ChatClientEvent::PinnedRouteInvalid(destination, hops));
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: Pinned route {:?} to [ {} ]",
+            "✓".green(),
+            self.log_tag,
+            hops,
+            destination
+        );
+        self.route_cache.routes.remove(&destination);
+        self.route_cache.pinned.insert(destination, hops);
+        self.send_event(ChatClientEvent::RoutePinned(destination));
+    }
+
+    /// Lets the `Router` choose the route to `destination` again.
+    pub(super) fn unpin_route(&mut self, destination: NodeId) {
+        if self.route_cache.pinned.remove(&destination).is_some() {
+            info!(
+                self,
+                "{} [ {} ]: Unpinned the route to [ {} ]",
+                "✓".green(),
+                self.log_tag,
+                destination
+            );
+        } else {
+            warn!(
+                self,
+                "{} [ {} ]: No route to [ {} ] is pinned",
+                "!!!".yellow(),
+                self.log_tag,
+                destination
+            );
+        }
+    }
+
+    /// Keeps every route from crossing `node_id`, as long as another route
+    /// is known. The client itself cannot be blacklisted.
+    pub(super) fn blacklist_node(&mut self, node_id: NodeId) {
+        if node_id == self.id || !self.route_cache.blacklist.insert(node_id) {
+            return;
+        }
+        info!(
+            self,
+            "{} [ {} ]: Blacklisted [ Node {} ], routes will avoid it",
+            "✓".green(),
+            self.log_tag,
+            node_id
+        );
+        self.invalidate_routes();
+    }
+
+    /// Lets routes cross `node_id` again.
+    pub(super) fn whitelist_node(&mut self, node_id: NodeId) {
+        if !self.route_cache.blacklist.remove(&node_id) {
+            return;
+        }
+        info!(
+            self,
+            "{} [ {} ]: Whitelisted [ Node {} ]",
+            "✓".green(),
+            self.log_tag,
+            node_id
+        );
+        self.invalidate_routes();
+    }
+
+    pub(super) fn is_blacklisted(&self, node_id: NodeId) -> bool {
+        self.route_cache.blacklist.contains(&node_id)
+    }
+
+    /// The blacklisted nodes, in ascending order.
+    pub(super) fn blacklisted_nodes(&self) -> Vec<NodeId> {
+        let mut blacklisted: Vec<NodeId> = self.route_cache.blacklist.iter().copied().collect();
+        blacklisted.sort_unstable();
+        blacklisted
+    }
+
+    /// The nodes routes must not cross.
+    pub(super) fn route_blacklist(&self) -> &HashSet<NodeId> {
+        &self.route_cache.blacklist
+    }
+
+    /// The first blacklisted node `hops` crosses between its ends, if any.
+    fn blacklisted_drone(&self, hops: &[NodeId]) -> Option<NodeId> {
+        hops.iter()
+            .skip(1)
+            .take(hops.len().saturating_sub(2))
+            .find(|id| self.route_cache.blacklist.contains(id))
+            .copied()
+    }
+
+    pub(super) fn is_pinned(&self, destination: NodeId) -> bool {
+        self.route_cache.pinned.contains_key(&destination)
+    }
+
+    /// Why `hops` cannot be used as the route to `destination`, if it
+    /// cannot.
+    pub(super) fn pinned_route_problem(
+        &self,
+        destination: NodeId,
+        hops: &[NodeId],
+    ) -> Option<String> {
+        if hops.len() < 2 || hops.first() != Some(&self.id) {
+            Some(format!("the route must start at {}", self.id))
+        } else if hops.last() != Some(&destination) {
+            Some(format!("the route must end at {destination}"))
+        } else if !self.packet_send.contains_key(&hops[1]) {
+            Some(format!("{} is not a neighbour", hops[1]))
+        } else if !self.topology.is_known_path(hops) {
+            Some("the route crosses unknown links or nodes that are not drones".to_string())
+        } else {
+            self.blacklisted_drone(hops)
+                .map(|node_id| format!("the route crosses blacklisted node {node_id}"))
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use source_routing::Router;
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodResponse, Packet},
+};
+
+/// Computes the routes of a `ChatClient` from what it learns about the
+/// network, so that other routing strategies can be tried.
+///
+/// Set with `ChatClientBuilder::with_routing`. The `Router` of the
+/// `source_routing` crate is used otherwise. The client keeps its own
+/// topology mirror alongside, and may still replace a route it is given
+/// to avoid lossy drones or links reported down.
+pub trait Routing: Send {
+    /// Records a drone the client is now connected to.
+    fn add_neighbour(&mut self, neighbour: NodeId);
+
+    /// Forgets a drone the client is no longer connected to.
+    fn remove_neighbour(&mut self, neighbour: NodeId);
+
+    /// Starts a flood, returning one `FloodRequest` packet per neighbour.
+    ///
+    /// # Arguments
+    ///
+    /// * `neighbours` - The number of neighbours the requests are sent to.
+    fn get_flood_requests(&mut self, neighbours: usize) -> Vec<Packet>;
+
+    /// The servers discovered by the floods so far.
+    fn get_server_list(&mut self) -> Vec<NodeId>;
+
+    /// The route to `destination`, `None` if it cannot be reached.
+    fn get_source_routing_header(&mut self, destination: NodeId) -> Option<SourceRoutingHeader>;
+
+    /// Learns the path traced by a flood response.
+    fn handle_flood_response(&mut self, flood_response: &FloodResponse);
+
+    /// Records that `node` dropped a fragment, or could not forward it.
+    fn dropped_fragment(&mut self, node: NodeId);
+
+    /// Forgets `node`, which is considered crashed.
+    fn drone_crashed(&mut self, node: NodeId);
+
+    /// Logs the known network, for debugging.
+    fn log_network(&mut self);
+}
+
+impl Routing for Router {
+    fn add_neighbour(&mut self, neighbour: NodeId) {
+        Router::add_neighbour(self, neighbour);
+    }
+
+    fn remove_neighbour(&mut self, neighbour: NodeId) {
+        Router::remove_neighbour(self, neighbour);
+    }
+
+    fn get_flood_requests(&mut self, neighbours: usize) -> Vec<Packet> {
+        Router::get_flood_requests(self, neighbours)
+    }
+
+    fn get_server_list(&mut self) -> Vec<NodeId> {
+        Router::get_server_list(self)
+    }
+
+    fn get_source_routing_header(&mut self, destination: NodeId) -> Option<SourceRoutingHeader> {
+        Router::get_source_routing_header(self, destination).ok()
+    }
+
+    fn handle_flood_response(&mut self, flood_response: &FloodResponse) {
+        Router::handle_flood_response(self, flood_response);
+    }
+
+    fn dropped_fragment(&mut self, node: NodeId) {
+        Router::dropped_fragment(self, node);
+    }
+
+    fn drone_crashed(&mut self, node: NodeId) {
+        Router::drone_crashed(self, node);
+    }
+
+    fn log_network(&mut self) {
+        Router::log_network(self);
+    }
+}
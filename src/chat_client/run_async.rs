@@ -0,0 +1,97 @@
+use colored::Colorize;
+use crossbeam_channel::{never, select_biased, Receiver, RecvError};
+use messages::client_commands::ChatClientCommand;
+use tokio::sync::mpsc;
+use wg_2024::packet::Packet;
+
+use super::{ChatClient, TICK_INTERVAL};
+
+/// What arrived on the channels of a client, handed to `run_async`.
+enum Input {
+    Command(Result<ChatClientCommand, RecvError>),
+    Packet(Packet),
+    Wake,
+}
+
+/// Waits on the channels of a client from a blocking thread, handing what
+/// arrives to `input` in the order `ChatClient::run` would handle it.
+///
+/// Stops once the controller disconnects, or once something arrives after
+/// `input` was dropped.
+fn forward(
+    controller_recv: &Receiver<ChatClientCommand>,
+    mut packet_recv: Receiver<Packet>,
+    wake_recv: &Receiver<()>,
+    input: &mpsc::Sender<Input>,
+) {
+    loop {
+        let received = select_biased! {
+            recv(controller_recv) -> command => Some(Input::Command(command)),
+            recv(packet_recv) -> packet => packet.ok().map(Input::Packet),
+            recv(wake_recv) -> _ => Some(Input::Wake),
+        };
+        let Some(received) = received else {
+            // the drones hung up, keep waiting on the other channels
+            packet_recv = never();
+            continue;
+        };
+
+        let disconnected = matches!(received, Input::Command(Err(_)));
+        if input.blocking_send(received).is_err() || disconnected {
+            return;
+        }
+    }
+}
+
+impl ChatClient {
+    /// Runs the main event loop for the `ChatClient` as a future.
+    ///
+    /// Behaves like [`ChatClient::run`], but handles the channels and
+    /// timers from a tokio task instead of blocking a thread, so many
+    /// clients can share the runtime of an async frontend. The channels
+    /// are waited on by a single thread of the blocking pool per client,
+    /// which hands what arrives to the task.
+    pub async fn run_async(&mut self) {
+        let (input_send, mut input_recv) = mpsc::channel(1);
+        let controller_recv = self.controller_recv.clone();
+        let packet_recv = self.packet_recv.clone();
+        let wake_recv = self.dispatch.wake_recv.clone();
+        tokio::task::spawn_blocking(move || {
+            forward(&controller_recv, packet_recv, &wake_recv, &input_send);
+        });
+        let mut tick = tokio::time::interval(self.scaled(TICK_INTERVAL));
+
+        while !self.shutdown && !self.controller_gone.get() {
+            tokio::select! {
+                biased;
+
+                _ = tick.tick() => {
+                    self.handle_tick();
+                },
+
+                input = input_recv.recv() => match input {
+                    Some(Input::Command(Ok(command))) => {
+                        self.timed_command(command);
+                        // `ChatClientCommand::SetTimeScale` changes the tick
+                        if tick.period() != self.scaled(TICK_INTERVAL) {
+                            tick = tokio::time::interval(self.scaled(TICK_INTERVAL));
+                        }
+                    }
+                    Some(Input::Command(Err(RecvError))) | None => {
+                        error!(self,
+                            "{} [ {} ]: The controller disconnected, shutting down",
+                            "✗".red(),
+                            self.log_tag
+                        );
+                        self.controller_gone.set(true);
+                    }
+                    Some(Input::Packet(packet)) => self.timed_packet(packet),
+                    Some(Input::Wake) => self.dispatch_fragments(),
+                },
+            }
+        }
+
+        self.save_storage();
+        self.save_outbox();
+    }
+}
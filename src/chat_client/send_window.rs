@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use colored::Colorize;
+use messages::high_level_messages::{ClientMessage, MessageContent};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Packet, PacketType},
+};
+
+use super::ChatClient;
+
+/// Order in which queued fragments are released, most urgent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Priority {
+    /// Registration, client list and server queries.
+    Control,
+    /// Chat messages, which may be large.
+    Bulk,
+}
+
+impl Priority {
+    pub(super) fn of(message_content: &MessageContent) -> Self {
+        match message_content {
+            MessageContent::FromClient(ClientMessage::SendMessage { .. }) => Self::Bulk,
+            _ => Self::Control,
+        }
+    }
+}
+
+/// Fragments sent toward one destination that have not been settled yet.
+///
+/// At most `ChatClientConfig::send_window` fragments are in flight at once,
+/// the rest wait in `queued` and are released one by one as Acks arrive,
+/// control traffic ahead of chat messages.
+#[derive(Debug, Default)]
+pub(super) struct SendWindow {
+    in_flight: usize,
+    queued: BTreeMap<Priority, VecDeque<Packet>>,
+}
+
+impl SendWindow {
+    /// Gives `route` to the queued fragments whose route starts with
+    /// `first_hop`, returning their session ids and fragment indexes.
+    fn reroute_queued(
+        &mut self,
+        first_hop: NodeId,
+        route: Option<&SourceRoutingHeader>,
+    ) -> HashSet<(u64, u64)> {
+        let mut rerouted = HashSet::new();
+        for packet in self.queued.values_mut().flatten() {
+            if packet.routing_header.hops.get(1) != Some(&first_hop) {
+                continue;
+            }
+            if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+                rerouted.insert((packet.session_id, fragment.fragment_index));
+            }
+            if let Some(route) = route {
+                packet.routing_header = route.clone();
+            }
+        }
+        rerouted
+    }
+
+    /// Drops the queued fragments of a session, returning how many there were.
+    fn cancel(&mut self, session_id: u64) -> usize {
+        let mut removed = 0;
+        self.queued.retain(|_, packets| {
+            let before = packets.len();
+            packets.retain(|packet| packet.session_id != session_id);
+            removed += before - packets.len();
+            !packets.is_empty()
+        });
+        removed
+    }
+
+    /// Fragments held back behind the window.
+    pub(super) fn queued(&self) -> usize {
+        self.queued.values().map(VecDeque::len).sum()
+    }
+
+    fn pop_next(&mut self) -> Option<Packet> {
+        let mut entry = self.queued.first_entry()?;
+        let packet = entry.get_mut().pop_front();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        packet
+    }
+}
+
+impl ChatClient {
+    /// Sends a new fragment toward `destination`, or holds it back if the
+    /// window toward that destination is full or its circuit is open.
+    pub(super) fn send_windowed(
+        &mut self,
+        destination: NodeId,
+        packet: Packet,
+        priority: Priority,
+    ) {
+        let paused = self.is_circuit_open(destination);
+        let window = self.send_windows.entry(destination).or_default();
+
+        if window.in_flight < self.config.send_window && !paused {
+            window.in_flight += 1;
+            let _ = self.forward_packet(packet);
+        } else {
+            window.queued.entry(priority).or_default().push_back(packet);
+        }
+    }
+
+    /// Frees the window slot of a fragment toward `destination` that was
+    /// either acknowledged or given up on, releasing the next queued one.
+    pub(super) fn fragment_settled(&mut self, destination: NodeId) {
+        if let Some(window) = self.send_windows.get_mut(&destination) {
+            window.in_flight = window.in_flight.saturating_sub(1);
+            self.fill_window(destination);
+        }
+    }
+
+    /// Sends queued fragments toward `destination` until its window is full.
+    pub(super) fn fill_window(&mut self, destination: NodeId) {
+        if self.is_circuit_open(destination) {
+            return;
+        }
+
+        while let Some(window) = self.send_windows.get_mut(&destination) {
+            if window.in_flight >= self.config.send_window {
+                break;
+            }
+            let Some(packet) = window.pop_next() else {
+                break;
+            };
+            window.in_flight += 1;
+
+            packet_info!(
+                self,
+                "{} [ {} ]: Releasing packet with session_id: {} toward [ Node {} ]",
+                "ℹ".blue(),
+                self.log_tag,
+                packet.session_id,
+                destination
+            );
+            let _ = self.forward_packet(packet);
+        }
+
+        if self
+            .send_windows
+            .get(&destination)
+            .is_some_and(|window| window.in_flight == 0 && window.queued.is_empty())
+        {
+            self.send_windows.remove(&destination);
+        }
+    }
+
+    /// Stops sending a message: its queued fragments are dropped and the
+    /// window slots of those in flight are released.
+    pub(super) fn cancel_windowed(
+        &mut self,
+        destination: NodeId,
+        session_id: u64,
+        fragments: usize,
+    ) {
+        let queued = self
+            .send_windows
+            .get_mut(&destination)
+            .map_or(0, |window| window.cancel(session_id));
+
+        for _ in queued..fragments {
+            self.fragment_settled(destination);
+        }
+    }
+
+    /// Moves the fragments routed through `neighbor` onto new routes once it
+    /// has been removed, instead of letting them fail one by one.
+    ///
+    /// Queued and undispatched fragments get the new route before being
+    /// sent, those already sent through `neighbor` and still waiting for
+    /// their Ack are resent.
+    pub(super) fn reroute_around(&mut self, neighbor: NodeId) {
+        let destinations: Vec<NodeId> = self.send_windows.keys().copied().collect();
+        let mut queued = self.reroute_dispatched(neighbor);
+        for destination in destinations {
+            let route = self.route_to(destination);
+            if let Some(window) = self.send_windows.get_mut(&destination) {
+                queued.extend(window.reroute_queued(neighbor, route.as_ref()));
+            }
+        }
+
+        for (fragment_index, packet) in self.packet_cache.routed_through(neighbor) {
+            if !queued.contains(&(packet.session_id, fragment_index)) {
+                info!(
+                    self,
+                    "{} [ {} ]: Rerouting packet with session_id: {} away from removed [ Drone {} ]",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    packet.session_id,
+                    neighbor
+                );
+                self.reroute_packet(packet);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Instant;
+
+use colored::Colorize;
+use messages::{
+    client_commands::ChatClientEvent,
+    high_level_messages::{ClientMessage, MessageContent},
+};
+use wg_2024::network::NodeId;
+
+use super::{control_requests::ControlRequest, ChatClient};
+
+/// Consecutive routing failures toward the registered server after which
+/// the registration is considered stale.
+const MAX_ROUTING_FAILURES: u32 = 3;
+
+/// Tracks whether the registered `CommunicationServer` is still reachable.
+#[derive(Debug, Default)]
+pub(super) struct ServerHealth {
+    failures: u32,
+    lost: Option<NodeId>,
+    reregistration_sent: bool,
+    keep_alive_sent: Option<Instant>,
+    keep_alive_answered: bool,
+    missed_keep_alives: u32,
+}
+
+impl ChatClient {
+    pub(super) fn routing_failed(&mut self, destination: NodeId) {
+        self.server_unroutable(destination);
+        if self.registered == Some(destination) {
+            self.server_health.failures += 1;
+
+            if self.server_health.failures >= MAX_ROUTING_FAILURES {
+                error!(
+                    self,
+                    "{} [ {} ]: Lost [ CommunicationServer {} ] after {} routing failures",
+                    "✗".red(),
+                    self.log_tag,
+                    destination,
+                    self.server_health.failures
+                );
+
+                self.registered = None;
+                self.server_health = ServerHealth {
+                    lost: Some(destination),
+                    ..ServerHealth::default()
+                };
+                self.send_event(ChatClientEvent::ServerLost(destination));
+            }
+        } else if self.server_health.lost == Some(destination) {
+            // the re-registration attempt did not make it, retry on the
+            // next topology update
+            self.server_health.reregistration_sent = false;
+        }
+    }
+
+    pub(super) fn routing_succeeded(&mut self, destination: NodeId) {
+        self.server_routable(destination);
+        if self.registered == Some(destination) {
+            self.server_health.failures = 0;
+        }
+    }
+
+    pub(super) fn check_lost_server(&mut self) {
+        let Some(server_id) = self.server_health.lost else {
+            return;
+        };
+
+        if !self.running
+            || self.config.auto_register.is_some()
+            || self.registered.is_some()
+            || self.server_health.reregistration_sent
+            || self.route_to(server_id).is_none()
+        {
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: [ CommunicationServer {} ] is reachable again, registering",
+            "ℹ".blue(),
+            self.log_tag,
+            server_id
+        );
+        self.server_health.reregistration_sent = true;
+        let _ = self.send_control_request(server_id, ControlRequest::Registration);
+    }
+
+    /// Pings the registered server with a `GetClientList` every keep-alive
+    /// interval, counting the pings it did not answer.
+    pub(super) fn check_keep_alive(&mut self) {
+        let (Some(interval), Some(server_id)) = (self.config.keep_alive, self.registered) else {
+            return;
+        };
+        if self
+            .server_health
+            .keep_alive_sent
+            .is_some_and(|sent| sent.elapsed() < self.scaled(interval))
+        {
+            return;
+        }
+
+        if self.server_health.keep_alive_sent.is_some() && !self.server_health.keep_alive_answered {
+            self.server_health.missed_keep_alives += 1;
+            if self.server_health.missed_keep_alives >= self.config.max_missed_keep_alives {
+                self.server_unreachable(server_id);
+                return;
+            }
+        }
+
+        self.server_health.keep_alive_sent = Some(Instant::now());
+        self.server_health.keep_alive_answered = false;
+        self.communication_server_list.queried(server_id);
+        let message_content = MessageContent::FromClient(ClientMessage::GetClientList);
+        let _ = self.generate_and_send_message(message_content, server_id);
+    }
+
+    /// Any message from the registered server answers the pending keep-alive.
+    pub(super) fn server_answered(&mut self, source_id: NodeId) {
+        self.communication_server_list.answered(source_id);
+        self.server_probe_answered(source_id);
+        if self.registered == Some(source_id) {
+            self.server_health.keep_alive_answered = true;
+            self.server_health.missed_keep_alives = 0;
+        }
+    }
+
+    /// Whether the registered server missed keep-alives or routing
+    /// attempts since it last answered.
+    pub(super) fn registration_degraded(&self) -> bool {
+        self.server_health.failures > 0 || self.server_health.missed_keep_alives > 0
+    }
+
+    fn server_unreachable(&mut self, server_id: NodeId) {
+        error!(
+            self,
+            "{} [ {} ]: [ CommunicationServer {} ] missed {} keep-alives, rediscovering the servers",
+            "✗".red(),
+            self.log_tag,
+            server_id,
+            self.server_health.missed_keep_alives
+        );
+
+        self.registered = None;
+        self.server_health = ServerHealth {
+            lost: Some(server_id),
+            ..ServerHealth::default()
+        };
+        self.send_event(ChatClientEvent::ServerUnreachable(server_id));
+        self.discover_servers();
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use wg_2024::network::NodeId;
+
+use super::ChatClient;
+
+/// Time after which a server that did not confirm its type again is
+/// forgotten, unless the client is registered to it.
+const SERVER_TTL: Duration = Duration::from_secs(90);
+
+/// Consecutive routing failures after which a known server is forgotten.
+const MAX_SERVER_FAILURES: u32 = 3;
+
+#[derive(Debug, Clone, Copy)]
+struct KnownServer {
+    confirmed_at: Instant,
+    failures: u32,
+}
+
+/// The communication servers the client discovered, each with the last
+/// time it answered a `GetServerType`.
+///
+/// The time servers take to answer the queries sent to them, and the
+/// number of clients they last listed, are kept to choose between them.
+#[derive(Debug, Default)]
+pub(super) struct ServerList {
+    servers: HashMap<NodeId, KnownServer>,
+    queried_at: HashMap<NodeId, Instant>,
+    latencies: HashMap<NodeId, Duration>,
+    clients: HashMap<NodeId, usize>,
+}
+
+impl ServerList {
+    pub(super) fn from_ids(ids: &[NodeId]) -> Self {
+        let now = Instant::now();
+        Self {
+            servers: ids
+                .iter()
+                .map(|&id| {
+                    (
+                        id,
+                        KnownServer {
+                            confirmed_at: now,
+                            failures: 0,
+                        },
+                    )
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Records that `id` is a communication server, returning whether it
+    /// was unknown until now.
+    pub(super) fn confirmed(&mut self, id: NodeId) -> bool {
+        self.servers
+            .insert(
+                id,
+                KnownServer {
+                    confirmed_at: Instant::now(),
+                    failures: 0,
+                },
+            )
+            .is_none()
+    }
+
+    pub(super) fn contains(&self, id: NodeId) -> bool {
+        self.servers.contains_key(&id)
+    }
+
+    pub(super) fn ids(&self) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = self.servers.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Records that a query was just sent to `id`, unless one is still
+    /// waiting for its answer.
+    pub(super) fn queried(&mut self, id: NodeId) {
+        self.queried_at.entry(id).or_insert_with(Instant::now);
+    }
+
+    /// Records that `id` answered, updating its latency with the time its
+    /// oldest pending query took.
+    pub(super) fn answered(&mut self, id: NodeId) {
+        let Some(queried_at) = self.queried_at.remove(&id) else {
+            return;
+        };
+        let sample = queried_at.elapsed();
+        self.latencies
+            .entry(id)
+            .and_modify(|latency| *latency = (*latency * 3 + sample) / 4)
+            .or_insert(sample);
+    }
+
+    pub(super) fn clients_listed(&mut self, id: NodeId, clients: usize) {
+        self.clients.insert(id, clients);
+    }
+
+    pub(super) fn latency(&self, id: NodeId) -> Option<Duration> {
+        self.latencies.get(&id).copied()
+    }
+
+    pub(super) fn clients(&self, id: NodeId) -> Option<usize> {
+        self.clients.get(&id).copied()
+    }
+
+    fn remove(&mut self, id: NodeId) {
+        self.servers.remove(&id);
+        self.queried_at.remove(&id);
+        self.latencies.remove(&id);
+        self.clients.remove(&id);
+    }
+}
+
+impl ChatClient {
+    /// Forgets the servers that were not confirmed within [`SERVER_TTL`].
+    pub(super) fn expire_servers(&mut self) {
+        let ttl = self.scaled(SERVER_TTL);
+        let expired: Vec<NodeId> = self
+            .communication_server_list
+            .servers
+            .iter()
+            .filter(|&(&id, server)| {
+                Some(id) != self.registered && server.confirmed_at.elapsed() >= ttl
+            })
+            .map(|(&id, _)| id)
+            .collect();
+
+        for id in expired {
+            self.communication_server_list.remove(id);
+            info!(
+                self,
+                "{} [ {} ]: Forgot [ CommunicationServer {} ], not confirmed for {:?}",
+                "ℹ".blue(),
+                self.log_tag,
+                id,
+                SERVER_TTL
+            );
+        }
+    }
+
+    /// Asks every server known to the `Router` for its type again, which
+    /// confirms the live communication servers.
+    pub(super) fn refresh_servers(&mut self) {
+        self.expire_servers();
+        if self.config.auto_discovery {
+            self.discover_servers();
+        } else {
+            self.query_communication_servers();
+        }
+    }
+
+    pub(super) fn server_unroutable(&mut self, id: NodeId) {
+        let Some(server) = self.communication_server_list.servers.get_mut(&id) else {
+            return;
+        };
+        server.failures += 1;
+
+        if server.failures >= MAX_SERVER_FAILURES && self.registered != Some(id) {
+            self.communication_server_list.remove(id);
+            warn!(
+                self,
+                "{} [ {} ]: Forgot [ CommunicationServer {} ] after {} routing failures",
+                "!!!".yellow(),
+                self.log_tag,
+                id,
+                MAX_SERVER_FAILURES
+            );
+        }
+    }
+
+    pub(super) fn server_routable(&mut self, id: NodeId) {
+        if let Some(server) = self.communication_server_list.servers.get_mut(&id) {
+            server.failures = 0;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::{collections::HashMap, time::Instant};
+
+use colored::Colorize;
+use messages::{
+    client_commands::ChatClientEvent,
+    high_level_messages::{ClientMessage, MessageContent},
+};
+use wg_2024::network::NodeId;
+
+use super::{time_scale::rescaled, ChatClient};
+
+/// A `GetServerType` query waiting for its answer.
+#[derive(Debug)]
+struct OutstandingQuery {
+    deadline: Instant,
+    retries: u32,
+}
+
+/// The `GetServerType` queries sent to servers that have not answered yet.
+#[derive(Debug, Default)]
+pub(super) struct ServerQueries {
+    outstanding: HashMap<NodeId, OutstandingQuery>,
+}
+
+impl ServerQueries {
+    /// Stretches the time left to answer every query by `ratio`.
+    pub(super) fn rescale(&mut self, now: Instant, ratio: f64) {
+        for query in self.outstanding.values_mut() {
+            query.deadline = rescaled(query.deadline, now, ratio);
+        }
+    }
+}
+
+impl ChatClient {
+    /// Sends a `GetServerType` query to `server_id`, giving it
+    /// `server_query_timeout` to answer.
+    pub(super) fn query_server_type(&mut self, server_id: NodeId, retries: u32) {
+        info!(
+            self,
+            "{} [ {} ]: Querying server [ Server {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            server_id
+        );
+        let deadline = Instant::now() + self.scaled(self.config.server_query_timeout);
+        self.server_queries
+            .outstanding
+            .insert(server_id, OutstandingQuery { deadline, retries });
+        self.communication_server_list.queried(server_id);
+        let message_content = MessageContent::FromClient(ClientMessage::GetServerType);
+        let _ = self.generate_and_send_message(message_content, server_id);
+    }
+
+    pub(super) fn server_type_received(&mut self, server_id: NodeId) {
+        self.server_queries.outstanding.remove(&server_id);
+    }
+
+    /// Retries the queries that timed out, and gives up on the servers
+    /// that did not answer any of them.
+    pub(super) fn check_server_queries(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<(NodeId, u32)> = self
+            .server_queries
+            .outstanding
+            .iter()
+            .filter(|(_, query)| query.deadline <= now)
+            .map(|(&server_id, query)| (server_id, query.retries))
+            .collect();
+
+        for (server_id, retries) in expired {
+            if retries < self.config.server_query_retries {
+                warn!(
+                    self,
+                    "{} [ {} ]: [ Server {} ] did not answer its type query, retrying",
+                    "!!!".yellow(),
+                    self.log_tag,
+                    server_id
+                );
+                self.query_server_type(server_id, retries + 1);
+            } else {
+                error!(
+                    self,
+                    "{} [ {} ]: [ Server {} ] did not answer {} type queries",
+                    "✗".red(),
+                    self.log_tag,
+                    server_id,
+                    retries + 1
+                );
+                self.server_queries.outstanding.remove(&server_id);
+                self.send_event(ChatClientEvent::ServerTypeQueryFailed(server_id));
+            }
+        }
+    }
+}
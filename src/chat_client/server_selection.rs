@@ -0,0 +1,244 @@
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::{config::ServerSelection, control_requests::ControlRequest, ChatClient};
+
+/// Minimum time between two automatic registration decisions, so that the
+/// client does not jump between servers of similar quality.
+const SELECTION_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time a server is given to confirm a registration before another one is
+/// chosen.
+const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times slower than the fastest other server the registered
+/// server must be before the client moves away from it.
+const LATENCY_DEGRADATION_FACTOR: u32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+struct PendingRegistration {
+    server: NodeId,
+    previous: Option<NodeId>,
+    sent_at: Instant,
+}
+
+/// The state of the automatic choice of a communication server.
+///
+/// A registration to the chosen server is sent before leaving the previous
+/// one, which is only logged out from once the new server confirms it.
+#[derive(Debug, Default)]
+pub(super) struct ServerSelector {
+    next_round_robin: usize,
+    pending: Option<PendingRegistration>,
+    decided_at: Option<Instant>,
+}
+
+impl ChatClient {
+    /// Registers to a discovered server when the client has none, and moves
+    /// to a better one when the registered server degrades.
+    pub(super) fn check_server_selection(&mut self) {
+        let Some(strategy) = self.config.auto_register else {
+            return;
+        };
+        if !self.running {
+            return;
+        }
+        if let Some(pending) = self.server_selector.pending {
+            if pending.sent_at.elapsed() < self.scaled(REGISTRATION_TIMEOUT) {
+                return;
+            }
+            warn!(
+                self,
+                "{} [ {} ]: [ CommunicationServer {} ] did not confirm the registration in time",
+                "!!!".yellow(),
+                self.log_tag,
+                pending.server
+            );
+            self.server_selector.pending = None;
+        }
+        if self
+            .server_selector
+            .decided_at
+            .is_some_and(|decided_at| decided_at.elapsed() < self.scaled(SELECTION_INTERVAL))
+        {
+            return;
+        }
+
+        let why = match self.registered {
+            None => "not registered".to_string(),
+            Some(current) => match self.degradation(strategy, current) {
+                Some(why) => why,
+                None => return,
+            },
+        };
+        self.server_selector.decided_at = Some(Instant::now());
+        if let Some((server, choice)) = self.select_server(strategy, self.registered) {
+            self.register_to_chosen(server, format!("{why}, {choice}"));
+        }
+    }
+
+    /// Registers to the best server according to the configured strategy,
+    /// or `ServerSelection::LowestLatency` if the client does not register
+    /// by itself.
+    ///
+    /// # Returns
+    ///
+    /// Whether a server could be reached.
+    pub(super) fn register_to_best_server(&mut self) -> bool {
+        let strategy = self.config.auto_register.unwrap_or_default();
+        match self.select_server(strategy, None) {
+            Some((server, _)) if self.registered == Some(server) => {
+                info!(
+                    self,
+                    "{} [ {} ]: Already registered to the best server [ CommunicationServer {} ]",
+                    "ℹ".blue(),
+                    self.log_tag,
+                    server
+                );
+            }
+            Some((server, choice)) => {
+                self.register_to_chosen(server, format!("requested by the controller, {choice}"));
+            }
+            None => return false,
+        }
+        true
+    }
+
+    /// Logs out from the server left behind once the chosen one confirmed
+    /// the registration.
+    pub(super) fn registration_confirmed(&mut self, server: NodeId) {
+        let Some(pending) = self.server_selector.pending else {
+            return;
+        };
+        if pending.server != server {
+            return;
+        }
+        self.server_selector.pending = None;
+
+        if let Some(previous) = pending.previous.filter(|&previous| previous != server) {
+            info!(
+                self,
+                "{} [ {} ]: Logging out from [ CommunicationServer {} ], now registered to [ CommunicationServer {} ]",
+                "ℹ".blue(),
+                self.log_tag,
+                previous,
+                server
+            );
+            // the previous server forgets the client once it stops hearing from it
+            let _ = self.send_control_request(previous, ControlRequest::Logout);
+        }
+    }
+
+    /// Registers to `server`, leaving the current server once it confirms.
+    fn register_to_chosen(&mut self, server: NodeId, reason: String) {
+        let previous = self.registered;
+        info!(
+            self,
+            "{} [ {} ]: Chose [ CommunicationServer {} ]: {}",
+            "ℹ".blue(),
+            self.log_tag,
+            server,
+            reason
+        );
+        self.send_event(ChatClientEvent::ServerSelected {
+            server,
+            previous,
+            reason,
+        });
+
+        self.server_selector.pending = Some(PendingRegistration {
+            server,
+            previous,
+            sent_at: Instant::now(),
+        });
+        if self
+            .send_control_request(server, ControlRequest::Registration)
+            .is_err()
+        {
+            self.server_selector.pending = None;
+        }
+    }
+
+    /// Why the client should leave `current`, if it should.
+    fn degradation(&mut self, strategy: ServerSelection, current: NodeId) -> Option<String> {
+        if self.registration_degraded() {
+            return Some(format!("[ CommunicationServer {current} ] is degraded"));
+        }
+
+        if strategy == ServerSelection::LowestLatency {
+            let latency = self.communication_server_list.latency(current)?;
+            let fastest = self
+                .candidate_servers(Some(current))
+                .into_iter()
+                .filter_map(|id| self.communication_server_list.latency(id))
+                .min()?;
+            if latency > fastest * LATENCY_DEGRADATION_FACTOR {
+                return Some(format!(
+                    "[ CommunicationServer {current} ] answers in {latency:?}, another in {fastest:?}"
+                ));
+            }
+        }
+        None
+    }
+
+    /// The discovered servers that can be routed to, but `excluded`.
+    pub(super) fn candidate_servers(&mut self, excluded: Option<NodeId>) -> Vec<NodeId> {
+        self.communication_server_list
+            .ids()
+            .into_iter()
+            .filter(|&id| Some(id) != excluded)
+            .filter(|&id| self.route_to(id).is_some())
+            .collect()
+    }
+
+    /// Chooses a server among the candidates.
+    ///
+    /// # Returns
+    ///
+    /// The server and a description of why it was chosen, `None` if no
+    /// server can be reached.
+    fn select_server(
+        &mut self,
+        strategy: ServerSelection,
+        excluded: Option<NodeId>,
+    ) -> Option<(NodeId, String)> {
+        let candidates = self.candidate_servers(excluded);
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let servers = &self.communication_server_list;
+        match strategy {
+            ServerSelection::RoundRobin => {
+                let server = candidates[self.server_selector.next_round_robin % candidates.len()];
+                self.server_selector.next_round_robin =
+                    self.server_selector.next_round_robin.wrapping_add(1);
+                Some((server, "next in turn".to_string()))
+            }
+            ServerSelection::LowestLatency => {
+                let &server = candidates
+                    .iter()
+                    .min_by_key(|&&id| servers.latency(id).unwrap_or(Duration::MAX))?;
+                let choice = servers.latency(server).map_or_else(
+                    || "no latency measured yet".to_string(),
+                    |latency| format!("lowest latency ({latency:?})"),
+                );
+                Some((server, choice))
+            }
+            ServerSelection::MostClients => {
+                let &server = candidates
+                    .iter()
+                    .rev()
+                    .max_by_key(|&&id| servers.clients(id).unwrap_or(0))?;
+                let choice = format!(
+                    "most reachable clients ({})",
+                    servers.clients(server).unwrap_or(0)
+                );
+                Some((server, choice))
+            }
+        }
+    }
+}
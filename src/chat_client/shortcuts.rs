@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use wg_2024::{
+    network::NodeId,
+    packet::{Packet, PacketType},
+};
+
+use super::ChatClient;
+
+/// How long a fragment handed to the controller may go without an Ack or
+/// a Nack before it is sent again over the network.
+const SHORTCUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fragments the client could not send itself and handed to the controller
+/// with a `ControllerShortcut`, keyed by destination, session id and
+/// fragment index.
+///
+/// The controller may or may not get them through, so each one is
+/// reconciled by the Ack or Nack it eventually gets, or resent once
+/// [`SHORTCUT_TIMEOUT`] expires without either.
+#[derive(Debug, Default)]
+pub(super) struct ShortcutRelays {
+    relayed: HashMap<(NodeId, u64, u64), Instant>,
+}
+
+impl ChatClient {
+    pub(super) fn relayed_by_controller(&mut self, packet: &Packet) {
+        if let (PacketType::MsgFragment(fragment), Some(peer)) =
+            (&packet.pack_type, packet.routing_header.destination())
+        {
+            self.shortcuts.relayed.insert(
+                (peer, packet.session_id, fragment.fragment_index),
+                Instant::now(),
+            );
+        }
+    }
+
+    /// Reconciles a fragment relayed by the controller with the Ack or Nack
+    /// it got.
+    pub(super) fn shortcut_settled(
+        &mut self,
+        peer: NodeId,
+        session_id: u64,
+        fragment_index: u64,
+        acked: bool,
+    ) {
+        if self
+            .shortcuts
+            .relayed
+            .remove(&(peer, session_id, fragment_index))
+            .is_some()
+        {
+            info!(
+                self,
+                "{} [ {} ]: Fragment {} of session {} relayed by the controller was {}",
+                "ℹ".blue(),
+                self.log_tag,
+                fragment_index,
+                session_id,
+                if acked { "acknowledged" } else { "Nacked" }
+            );
+        }
+    }
+
+    /// Sends again over the network the relayed fragments nothing was heard
+    /// of in time.
+    pub(super) fn check_shortcut_relays(&mut self) {
+        let timeout = self.scaled(SHORTCUT_TIMEOUT);
+        let expired: Vec<(NodeId, u64, u64)> = self
+            .shortcuts
+            .relayed
+            .iter()
+            .filter(|(_, relayed_at)| relayed_at.elapsed() >= timeout)
+            .map(|(&key, _)| key)
+            .collect();
+
+        for key in expired {
+            self.shortcuts.relayed.remove(&key);
+            let (peer, session_id, fragment_index) = key;
+            let Some(packet) = self.packet_cache.get(peer, session_id, fragment_index) else {
+                continue;
+            };
+
+            warn!(
+                self,
+                "{} [ {} ]: Nothing heard of fragment {} of session {} relayed by the controller, resending it",
+                "!!!".yellow(),
+                self.log_tag,
+                fragment_index,
+                session_id
+            );
+            let packet = packet.clone();
+            self.reroute_packet(packet);
+        }
+    }
+}
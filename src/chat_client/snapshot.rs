@@ -0,0 +1,104 @@
+use colored::Colorize;
+use messages::client_commands::PeerInfo;
+use serde::{Deserialize, Serialize};
+use wg_2024::network::NodeId;
+
+use super::{
+    peers::PeerRegistry,
+    pending_messages::SavedPendingMessage,
+    server_health::ServerHealth,
+    server_list::ServerList,
+    topology::{SavedTopology, Topology},
+    ChatClient,
+};
+
+/// Checkpoint of a `ChatClient`, taken with [`ChatClient::snapshot`] and
+/// applied with [`ChatClient::restore`].
+///
+/// It holds the registration, the known servers and clients, the blocked
+/// clients, the topology and the queued chat messages. Channels, routes
+/// and fragments in flight are left out: the restored client floods the
+/// network to rebuild its routes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientSnapshot {
+    id: NodeId,
+    running: bool,
+    registered: Option<NodeId>,
+    communication_server_list: Vec<NodeId>,
+    #[serde(default)]
+    peers: Vec<PeerInfo>,
+    #[serde(default)]
+    blocked_clients: Vec<NodeId>,
+    topology: SavedTopology,
+    pending_messages: Vec<SavedPendingMessage>,
+}
+
+impl ChatClient {
+    /// Captures the state needed to resume this client later, possibly on
+    /// another thread.
+    ///
+    /// # Returns
+    ///
+    /// A [`ClientSnapshot`] that can be serialized with `serde`.
+    #[must_use]
+    pub fn snapshot(&self) -> ClientSnapshot {
+        ClientSnapshot {
+            id: self.id,
+            running: self.running,
+            registered: self.registered,
+            communication_server_list: self.communication_server_list.ids(),
+            peers: self.peers.save(),
+            blocked_clients: {
+                let mut blocked: Vec<NodeId> = self.blocked_clients.iter().copied().collect();
+                blocked.sort_unstable();
+                blocked
+            },
+            topology: self.topology.save(),
+            pending_messages: self.save_pending_messages(),
+        }
+    }
+
+    /// Replaces the state of this client with the one in `snapshot`.
+    ///
+    /// Snapshots taken from a client with a different id are ignored. If
+    /// the restored client is running, a flood is started to rebuild the
+    /// routes, which also flushes the queued messages once it completes.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - A [`ClientSnapshot`] taken from a client with the same id.
+    pub fn restore(&mut self, snapshot: ClientSnapshot) {
+        if snapshot.id != self.id {
+            error!(
+                self,
+                "{} [ {} ]: Cannot restore a snapshot of [ ChatClient {} ]",
+                "✗".red(),
+                self.log_tag,
+                snapshot.id
+            );
+            return;
+        }
+
+        self.running = snapshot.running;
+        self.registered = snapshot.registered;
+        self.server_health = ServerHealth::default();
+        self.communication_server_list = ServerList::from_ids(&snapshot.communication_server_list);
+        self.peers = PeerRegistry::from_saved(snapshot.peers);
+        self.blocked_clients = snapshot.blocked_clients.into_iter().collect();
+        self.topology = Topology::from_saved(snapshot.topology);
+        self.restore_pending_messages(snapshot.pending_messages);
+        self.invalidate_routes();
+
+        info!(
+            self,
+            "{} [ {} ]: Restored from snapshot, registered to {:?}",
+            "✓".green(),
+            self.log_tag,
+            self.registered
+        );
+
+        if self.running && !self.packet_send.is_empty() {
+            self.start_flood();
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Instant;
+
+use messages::client_commands::ChatClientEvent;
+use wg_2024::packet::NackType;
+
+use super::ChatClient;
+
+impl ChatClient {
+    pub(super) fn count_nack(&mut self, nack_type: &NackType) {
+        let nacks = &mut self.stats.nacks;
+        match nack_type {
+            NackType::ErrorInRouting(_) => nacks.error_in_routing += 1,
+            NackType::DestinationIsDrone => nacks.destination_is_drone += 1,
+            NackType::Dropped => nacks.dropped += 1,
+            NackType::UnexpectedRecipient(_) => nacks.unexpected_recipient += 1,
+        }
+    }
+
+    pub(super) fn report_stats(&mut self) {
+        self.send_event(ChatClientEvent::Stats(self.stats));
+        self.report_round_trip_times();
+        self.stats_reported_at = Instant::now();
+    }
+
+    /// Sends the statistics to the controller every configured stats
+    /// interval, if periodic reports were enabled.
+    pub(super) fn check_stats_report(&mut self) {
+        if self
+            .config
+            .stats_interval
+            .is_some_and(|interval| self.stats_reported_at.elapsed() >= self.scaled(interval))
+        {
+            self.report_stats();
+        }
+    }
+}
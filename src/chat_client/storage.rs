@@ -0,0 +1,210 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use colored::Colorize;
+use messages::client_commands::HistoryEntry;
+
+use super::{snapshot::ClientSnapshot, ChatClient};
+
+/// Where a `ChatClient` keeps its message history and its state across
+/// restarts, so that embedders can bring their own database.
+///
+/// Set with `ChatClientBuilder::with_storage`. A [`MemoryStorage`] is used
+/// otherwise. Both are loaded when the client is built and saved when it
+/// shuts down.
+pub trait Storage: Send {
+    /// Stores the whole message history, replacing the stored one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the history cannot be stored.
+    fn save_history(&mut self, entries: &[HistoryEntry]) -> io::Result<()>;
+
+    /// The stored message history, empty if none was stored yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored history cannot be read.
+    fn load_history(&mut self) -> io::Result<Vec<HistoryEntry>>;
+
+    /// Stores the state of the client, replacing the stored one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state cannot be stored.
+    fn save_state(&mut self, snapshot: &ClientSnapshot) -> io::Result<()>;
+
+    /// The stored state of the client, `None` if none was stored yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored state cannot be read.
+    fn load_state(&mut self) -> io::Result<Option<ClientSnapshot>>;
+}
+
+#[derive(Debug, Default)]
+struct Stored {
+    history: Vec<HistoryEntry>,
+    state: Option<ClientSnapshot>,
+}
+
+/// Keeps the history and the state in memory.
+///
+/// Clones share what is stored, so a client built again with a clone of
+/// the storage of one that shut down resumes where it stopped.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    stored: Arc<Mutex<Stored>>,
+}
+
+impl Storage for MemoryStorage {
+    fn save_history(&mut self, entries: &[HistoryEntry]) -> io::Result<()> {
+        let mut stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+        stored.history = entries.to_vec();
+        Ok(())
+    }
+
+    fn load_history(&mut self) -> io::Result<Vec<HistoryEntry>> {
+        let stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(stored.history.clone())
+    }
+
+    fn save_state(&mut self, snapshot: &ClientSnapshot) -> io::Result<()> {
+        let mut stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+        stored.state = Some(snapshot.clone());
+        Ok(())
+    }
+
+    fn load_state(&mut self) -> io::Result<Option<ClientSnapshot>> {
+        let stored = self.stored.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(stored.state.clone())
+    }
+}
+
+/// Keeps the history, and optionally the state, in JSON files.
+///
+/// Files that do not exist yet are taken as empty.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    history: PathBuf,
+    state: Option<PathBuf>,
+}
+
+impl FileStorage {
+    /// Keeps the history in the file at `history`, and no state.
+    #[must_use]
+    pub fn new(history: impl Into<PathBuf>) -> Self {
+        Self {
+            history: history.into(),
+            state: None,
+        }
+    }
+
+    /// Also keeps the state of the client in the file at `path`.
+    #[must_use]
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state = Some(path.into());
+        self
+    }
+}
+
+impl Storage for FileStorage {
+    fn save_history(&mut self, entries: &[HistoryEntry]) -> io::Result<()> {
+        fs::write(&self.history, serde_json::to_string_pretty(entries)?)
+    }
+
+    fn load_history(&mut self) -> io::Result<Vec<HistoryEntry>> {
+        if !self.history.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.history)?)?)
+    }
+
+    fn save_state(&mut self, snapshot: &ClientSnapshot) -> io::Result<()> {
+        let Some(path) = &self.state else {
+            return Ok(());
+        };
+        fs::write(path, serde_json::to_string_pretty(snapshot)?)
+    }
+
+    fn load_state(&mut self) -> io::Result<Option<ClientSnapshot>> {
+        let Some(path) = self.state.as_ref().filter(|path| path.exists()) else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+}
+
+impl ChatClient {
+    /// Puts back the history and the state kept in the storage.
+    pub(super) fn load_storage(&mut self) {
+        match self.storage.load_history() {
+            Ok(entries) => {
+                if !entries.is_empty() {
+                    info!(
+                        self,
+                        "{} [ {} ]: Loaded {} history entries",
+                        "✓".green(),
+                        self.log_tag,
+                        entries.len()
+                    );
+                }
+                self.history.load(entries);
+            }
+            Err(e) => error!(
+                self,
+                "{} [ {} ]: Failed to load the history: {}",
+                "✗".red(),
+                self.log_tag,
+                e
+            ),
+        }
+
+        match self.storage.load_state() {
+            Ok(Some(snapshot)) => self.restore(snapshot),
+            Ok(None) => {}
+            Err(e) => error!(
+                self,
+                "{} [ {} ]: Failed to load the saved state: {}",
+                "✗".red(),
+                self.log_tag,
+                e
+            ),
+        }
+    }
+
+    /// Writes the history and the state to the storage.
+    pub(super) fn save_storage(&mut self) {
+        let entries = self.history.entries().len();
+        match self.storage.save_history(self.history.entries()) {
+            Ok(()) => info!(
+                self,
+                "{} [ {} ]: Saved {} history entries",
+                "✓".green(),
+                self.log_tag,
+                entries
+            ),
+            Err(e) => error!(
+                self,
+                "{} [ {} ]: Failed to save the history: {}",
+                "✗".red(),
+                self.log_tag,
+                e
+            ),
+        }
+
+        let snapshot = self.snapshot();
+        if let Err(e) = self.storage.save_state(&snapshot) {
+            error!(
+                self,
+                "{} [ {} ]: Failed to save the state: {}",
+                "✗".red(),
+                self.log_tag,
+                e
+            );
+        }
+    }
+}
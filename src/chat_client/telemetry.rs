@@ -0,0 +1,19 @@
+use messages::client_commands::{ChatClientEvent, TelemetryKind, TelemetryRecord};
+
+use super::{history::now_millis, ChatClient};
+
+impl ChatClient {
+    /// Reports `kind` to the controller as a `ChatClientEvent::Telemetry`,
+    /// if telemetry was enabled through the `ChatClientBuilder`.
+    pub(super) fn emit_telemetry(&self, kind: TelemetryKind) {
+        if !self.config.telemetry {
+            return;
+        }
+
+        self.send_event(ChatClientEvent::Telemetry(TelemetryRecord {
+            timestamp: now_millis(),
+            node: self.id,
+            kind,
+        }));
+    }
+}
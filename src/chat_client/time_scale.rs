@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use crossbeam_channel::tick;
+
+use super::{config::TimeScale, ChatClient, TICK_INTERVAL};
+
+/// `deadline` moved so that the time left before it is multiplied by
+/// `ratio`. Deadlines already past are left as they are.
+pub(super) fn rescaled(deadline: Instant, now: Instant, ratio: f64) -> Instant {
+    let left = deadline.saturating_duration_since(now);
+    Duration::try_from_secs_f64(left.as_secs_f64() * ratio)
+        .ok()
+        .and_then(|left| now.checked_add(left))
+        .unwrap_or(deadline)
+}
+
+impl ChatClient {
+    /// The real time a `duration` of simulated time lasts at the current
+    /// time scale.
+    pub(super) fn scaled(&self, duration: Duration) -> Duration {
+        self.config.time_scale.scale(duration)
+    }
+
+    /// Runs every timer of the client at `scale` from now on, as
+    /// `ChatClientCommand::SetTimeScale` does.
+    ///
+    /// The tick restarts at the new scale and the time left before every
+    /// pending deadline is stretched accordingly. Timers counting from a
+    /// past instant compare the real time elapsed since then with their
+    /// duration at the new scale.
+    pub(super) fn set_time_scale(&mut self, scale: TimeScale) {
+        let ratio = self.config.time_scale.factor() / scale.factor();
+        self.config.time_scale = scale;
+        self.tick = tick(self.scaled(TICK_INTERVAL));
+
+        let now = Instant::now();
+        self.backoff.rescale(now, ratio);
+        self.bot.rescale(now, ratio);
+        self.client_list_refresh.rescale(now, ratio);
+        self.server_queries.rescale(now, ratio);
+        #[cfg(feature = "fault-injection")]
+        self.faults.rescale(now, ratio);
+
+        info!(
+            self,
+            "{} [ {} ]: Timers now run {}x faster than real time",
+            "ℹ".blue(),
+            self.log_tag,
+            scale.factor()
+        );
+    }
+}
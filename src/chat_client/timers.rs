@@ -0,0 +1,31 @@
+use super::ChatClient;
+
+impl ChatClient {
+    pub(super) fn handle_tick(&mut self) {
+        self.check_flood_timeout();
+        self.check_flood_sessions();
+        self.prune_flood_requests_seen();
+        self.retransmit_due();
+        self.retransmit_ack_gaps();
+        self.check_shortcut_relays();
+        self.flush_due_acks();
+        self.check_presence();
+        self.check_keep_alive();
+        self.check_stats_report();
+        self.expire_cached_messages();
+        self.expire_servers();
+        self.check_server_queries();
+        self.check_server_selection();
+        self.check_client_list_refresh();
+        self.expire_control_requests();
+        self.expire_incoming_chunks();
+        self.expire_transfers();
+        self.expire_reassemblies();
+        self.expire_probes();
+        self.check_quarantine();
+        self.expire_rate_windows();
+        self.check_bot();
+        #[cfg(feature = "fault-injection")]
+        self.release_delayed_packets();
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use messages::client_commands::{TopologyLink, TopologySnapshot};
+use serde::{Deserialize, Serialize};
+use wg_2024::{network::NodeId, packet::NodeType};
+
+/// Fragments sent and dropped across a link.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct LinkStats {
+    sent: u64,
+    dropped: u64,
+}
+
+impl LinkStats {
+    #[allow(clippy::cast_precision_loss)]
+    fn reliability(self) -> f64 {
+        if self.sent == 0 {
+            1.0
+        } else {
+            1.0 - (self.dropped.min(self.sent) as f64 / self.sent as f64)
+        }
+    }
+}
+
+/// The client's own picture of the network, rebuilt from the flood
+/// responses it processes and annotated with the drops it observes.
+///
+/// The `Router` keeps its topology private, so this mirror is what the
+/// controller gets when it asks for a snapshot.
+#[derive(Debug, Default)]
+pub(super) struct Topology {
+    nodes: HashMap<NodeId, NodeType>,
+    links: HashMap<(NodeId, NodeId), LinkStats>,
+}
+
+/// A [`Topology`] as stored in a `ClientSnapshot`, with the maps flattened
+/// into lists since JSON objects only have string keys.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(super) struct SavedTopology {
+    nodes: Vec<(NodeId, NodeType)>,
+    links: Vec<((NodeId, NodeId), LinkStats)>,
+}
+
+/// Links are undirected, store them with the lower id first.
+fn link_key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+    (a.min(b), a.max(b))
+}
+
+impl Topology {
+    pub(super) fn save(&self) -> SavedTopology {
+        SavedTopology {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(&id, &node_type)| (id, node_type))
+                .collect(),
+            links: self
+                .links
+                .iter()
+                .map(|(&link, &stats)| (link, stats))
+                .collect(),
+        }
+    }
+
+    pub(super) fn from_saved(saved: SavedTopology) -> Self {
+        Self {
+            nodes: saved.nodes.into_iter().collect(),
+            links: saved.links.into_iter().collect(),
+        }
+    }
+
+    pub(super) fn contains(&self, id: NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub(super) fn is_drone(&self, id: NodeId) -> bool {
+        self.nodes.get(&id) == Some(&NodeType::Drone)
+    }
+
+    pub(super) fn add_path_trace(&mut self, path_trace: &[(NodeId, NodeType)]) {
+        for &(id, node_type) in path_trace {
+            self.nodes.insert(id, node_type);
+        }
+        for pair in path_trace.windows(2) {
+            self.links
+                .entry(link_key(pair[0].0, pair[1].0))
+                .or_default();
+        }
+    }
+
+    pub(super) fn add_link(&mut self, a: (NodeId, NodeType), b: (NodeId, NodeType)) {
+        self.add_path_trace(&[a, b]);
+    }
+
+    pub(super) fn remove_link(&mut self, a: NodeId, b: NodeId) {
+        self.links.remove(&link_key(a, b));
+    }
+
+    pub(super) fn remove_node(&mut self, id: NodeId) {
+        self.nodes.remove(&id);
+        self.links.retain(|&(a, b), _| a != id && b != id);
+    }
+
+    pub(super) fn fragment_sent(&mut self, hops: &[NodeId]) {
+        for pair in hops.windows(2) {
+            self.links
+                .entry(link_key(pair[0], pair[1]))
+                .or_default()
+                .sent += 1;
+        }
+    }
+
+    pub(super) fn fragment_dropped(&mut self, from: NodeId, to: NodeId) {
+        self.links.entry(link_key(from, to)).or_default().dropped += 1;
+    }
+
+    /// Probability that a fragment crosses every link of `hops`, estimated
+    /// from the drops observed so far.
+    pub(super) fn route_reliability(&self, hops: &[NodeId]) -> f64 {
+        hops.windows(2)
+            .map(|pair| {
+                self.links
+                    .get(&link_key(pair[0], pair[1]))
+                    .map_or(1.0, |stats| stats.reliability())
+            })
+            .product()
+    }
+
+    /// The intermediate drone of `hops` reached through the link that
+    /// dropped the most fragments, if any link of the route dropped one.
+    pub(super) fn least_reliable_drone(&self, hops: &[NodeId]) -> Option<NodeId> {
+        hops.windows(2)
+            .take(hops.len().saturating_sub(2))
+            .filter_map(|pair| {
+                let stats = self.links.get(&link_key(pair[0], pair[1]))?;
+                (stats.dropped > 0).then_some((pair[1], stats.reliability()))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(drone, _)| drone)
+    }
+
+    /// Whether every link of `hops` is known and every intermediate node is
+    /// a drone.
+    pub(super) fn is_known_path(&self, hops: &[NodeId]) -> bool {
+        hops.windows(2)
+            .all(|pair| self.links.contains_key(&link_key(pair[0], pair[1])))
+            && hops
+                .iter()
+                .skip(1)
+                .take(hops.len().saturating_sub(2))
+                .all(|id| self.nodes.get(id) == Some(&NodeType::Drone))
+    }
+
+    /// Shortest known path from `from` to `to` whose intermediate nodes are
+    /// all drones not listed in `excluded`.
+    pub(super) fn path_avoiding(
+        &self,
+        from: NodeId,
+        to: NodeId,
+        excluded: &HashSet<NodeId>,
+    ) -> Option<Vec<NodeId>> {
+        let mut previous: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(&prev) = previous.get(&current) {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for next in self.neighbors(node) {
+                let usable = next == to
+                    || (self.nodes.get(&next) == Some(&NodeType::Drone)
+                        && !excluded.contains(&next));
+                if usable && next != from && !previous.contains_key(&next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn neighbors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.links.keys().filter_map(move |&(a, b)| {
+            if a == id {
+                Some(b)
+            } else if b == id {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The known nodes and links, along with the `blacklisted` nodes routes
+    /// avoid.
+    pub(super) fn snapshot(&self, blacklisted: Vec<NodeId>) -> TopologySnapshot {
+        let mut nodes: Vec<(NodeId, NodeType)> = self
+            .nodes
+            .iter()
+            .map(|(&id, &node_type)| (id, node_type))
+            .collect();
+        nodes.sort_by_key(|&(id, _)| id);
+
+        let mut links: Vec<TopologyLink> = self
+            .links
+            .iter()
+            .map(|(&(a, b), stats)| TopologyLink {
+                nodes: (a, b),
+                reliability: stats.reliability(),
+            })
+            .collect();
+        links.sort_by_key(|link| link.nodes);
+
+        TopologySnapshot {
+            nodes,
+            links,
+            blacklisted,
+        }
+    }
+}
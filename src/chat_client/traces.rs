@@ -0,0 +1,96 @@
+use std::collections::{HashMap, VecDeque};
+
+use messages::client_commands::{MessageTrace, TelemetryKind, TraceEntry, TraceStep};
+
+use super::{history::now_millis, ChatClient};
+
+/// Messages whose timeline is kept, the oldest ones are forgotten first.
+const MAX_TRACES: usize = 256;
+
+/// Timelines of the messages sent and received by a `ChatClient`, keyed by
+/// the session id of their fragments.
+///
+/// Packet-level events only carry a session id, the registry is what ties
+/// them back to the logical message they belong to.
+#[derive(Debug, Default)]
+pub(super) struct TraceRegistry {
+    enabled: bool,
+    traces: HashMap<u64, MessageTrace>,
+    order: VecDeque<u64>,
+}
+
+impl TraceRegistry {
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn start(&mut self, message_id: u64) {
+        if self.traces.contains_key(&message_id) {
+            return;
+        }
+        if self.order.len() >= MAX_TRACES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.traces.remove(&oldest);
+            }
+        }
+
+        self.traces.insert(
+            message_id,
+            MessageTrace {
+                message_id,
+                trace_id: rand::random(),
+                entries: Vec::new(),
+            },
+        );
+        self.order.push_back(message_id);
+    }
+
+    fn record(&mut self, message_id: u64, step: TraceStep) -> Option<u64> {
+        let trace = self.traces.get_mut(&message_id)?;
+        trace.entries.push(TraceEntry {
+            timestamp: now_millis(),
+            step,
+        });
+        Some(trace.trace_id)
+    }
+
+    pub(super) fn get(&self, message_id: u64) -> Option<MessageTrace> {
+        self.traces.get(&message_id).cloned()
+    }
+}
+
+impl ChatClient {
+    /// Opens the timeline of a message, if tracing is enabled, and records
+    /// its first step.
+    pub(super) fn start_trace(&mut self, message_id: u64, step: TraceStep) {
+        if !self.traces.is_enabled() {
+            return;
+        }
+
+        self.traces.start(message_id);
+        self.trace(message_id, step);
+    }
+
+    /// Records a step of a message whose timeline was started, also
+    /// reporting it as telemetry.
+    pub(super) fn trace(&mut self, message_id: u64, step: TraceStep) {
+        if !self.traces.is_enabled() {
+            return;
+        }
+
+        if let Some(trace_id) = self.traces.record(message_id, step.clone()) {
+            self.emit_telemetry(TelemetryKind::TraceStep {
+                trace_id,
+                message_id,
+                step,
+            });
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::{payload::Payload, ChatClient};
+
+/// Bytes of file data carried by a single `Payload::FileChunk`.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Time after which a file that never completed is discarded.
+const TRANSFER_TTL: Duration = Duration::from_secs(300);
+
+/// Most incomplete files a single client may be sending at once.
+const MAX_OPEN_TRANSFERS: usize = 8;
+
+/// Most chunks of a file kept before its manifest arrives.
+const MAX_EARLY_CHUNKS: usize = 64;
+
+/// Description of a file sent ahead of its chunks.
+#[derive(Debug, Clone)]
+pub(super) struct FileManifest {
+    pub(super) name: String,
+    pub(super) size: u64,
+    pub(super) chunks: u32,
+    pub(super) checksum: u64,
+}
+
+/// A file being received from another client.
+///
+/// Chunks travel as separate high-level messages and may overtake the
+/// manifest, so they are kept until the manifest says the file is complete.
+/// Only chunks within the count the manifest announces are kept, and at
+/// most [`MAX_EARLY_CHUNKS`] of them before it arrives.
+#[derive(Debug)]
+pub(super) struct IncomingTransfer {
+    manifest: Option<FileManifest>,
+    chunks: HashMap<u32, Vec<u8>>,
+    started: Instant,
+}
+
+impl IncomingTransfer {
+    fn is_complete(&self) -> bool {
+        self.manifest.as_ref().is_some_and(|manifest| {
+            (0..manifest.chunks).all(|index| self.chunks.contains_key(&index))
+        })
+    }
+
+    /// Whether the chunk at `index` belongs to the file, as far as is known.
+    fn accepts(&self, index: u32) -> bool {
+        match &self.manifest {
+            Some(manifest) => index < manifest.chunks,
+            None => self.chunks.contains_key(&index) || self.chunks.len() < MAX_EARLY_CHUNKS,
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash of `data`, used to verify reassembled files and
+/// checked contents.
+pub(super) fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl ChatClient {
+    pub(super) fn send_file(&mut self, recipient_id: NodeId, path: &Path) {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+
+        if !self.peers.contains(recipient_id) {
+            error!(
+                self,
+                "{} [ {} ]: Cannot send {} to [ Client {} ], client is unreachable",
+                "✗".red(),
+                self.log_tag,
+                name,
+                recipient_id
+            );
+            self.send_event(ChatClientEvent::UnreachableClient(recipient_id));
+            return;
+        }
+
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Failed to read {}: {}",
+                    "✗".red(),
+                    self.log_tag,
+                    path.display(),
+                    e
+                );
+                self.send_event(ChatClientEvent::FileTransferFailed(recipient_id, name));
+                return;
+            }
+        };
+
+        let Ok(chunks) = u32::try_from(data.len().div_ceil(CHUNK_SIZE)) else {
+            error!(
+                self,
+                "{} [ {} ]: {} is too large to be sent",
+                "✗".red(),
+                self.log_tag,
+                name
+            );
+            self.send_event(ChatClientEvent::FileTransferFailed(recipient_id, name));
+            return;
+        };
+
+        let transfer_id = rand::random::<u64>();
+        info!(
+            self,
+            "{} [ {} ]: Sending {} ({} bytes, {} chunks) to [ Client {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            name,
+            data.len(),
+            chunks,
+            recipient_id
+        );
+
+        let manifest = Payload::FileManifest {
+            transfer_id,
+            name: name.clone(),
+            size: data.len() as u64,
+            chunks,
+            checksum: checksum(&data),
+        };
+        if self.send_payload(recipient_id, &manifest).is_err() {
+            self.send_event(ChatClientEvent::FileTransferFailed(recipient_id, name));
+            return;
+        }
+
+        for (index, chunk) in (0..chunks).zip(data.chunks(CHUNK_SIZE)) {
+            let payload = Payload::FileChunk {
+                transfer_id,
+                index,
+                data: chunk.to_vec(),
+            };
+            if self.send_payload(recipient_id, &payload).is_err() {
+                self.send_event(ChatClientEvent::FileTransferFailed(recipient_id, name));
+                return;
+            }
+            self.send_event(ChatClientEvent::FileTransferProgress(
+                recipient_id,
+                name.clone(),
+                index + 1,
+                chunks,
+            ));
+        }
+    }
+
+    pub(super) fn file_manifest_received(
+        &mut self,
+        sender_id: NodeId,
+        transfer_id: u64,
+        manifest: FileManifest,
+    ) {
+        info!(
+            self,
+            "{} [ {} ]: Receiving {} ({} bytes) from [ Client {} ]",
+            "ℹ".blue(),
+            self.log_tag,
+            manifest.name,
+            manifest.size,
+            sender_id
+        );
+
+        let Some(transfer) = self.open_transfer(sender_id, transfer_id) else {
+            self.send_event(ChatClientEvent::FileTransferFailed(
+                sender_id,
+                manifest.name,
+            ));
+            return;
+        };
+        transfer.chunks.retain(|&index, _| index < manifest.chunks);
+        transfer.manifest = Some(manifest);
+        self.check_transfer(sender_id, transfer_id);
+    }
+
+    pub(super) fn file_chunk_received(
+        &mut self,
+        sender_id: NodeId,
+        transfer_id: u64,
+        index: u32,
+        data: Vec<u8>,
+    ) {
+        let Some(transfer) = self
+            .open_transfer(sender_id, transfer_id)
+            .filter(|transfer| transfer.accepts(index))
+        else {
+            warn!(
+                self,
+                "{} [ {} ]: Ignoring chunk {} of transfer {} from [ Client {} ]",
+                "!!!".yellow(),
+                self.log_tag,
+                index,
+                transfer_id,
+                sender_id
+            );
+            return;
+        };
+        transfer.chunks.insert(index, data);
+
+        let progress = transfer.manifest.as_ref().map(|manifest| {
+            let received = u32::try_from(transfer.chunks.len()).unwrap_or(u32::MAX);
+            ChatClientEvent::FileTransferProgress(
+                sender_id,
+                manifest.name.clone(),
+                received,
+                manifest.chunks,
+            )
+        });
+        if let Some(progress) = progress {
+            self.send_event(progress);
+        }
+        self.check_transfer(sender_id, transfer_id);
+    }
+
+    /// The transfer `transfer_id` of `sender_id`, opened if it is new and
+    /// the sender has fewer than [`MAX_OPEN_TRANSFERS`] open.
+    fn open_transfer(
+        &mut self,
+        sender_id: NodeId,
+        transfer_id: u64,
+    ) -> Option<&mut IncomingTransfer> {
+        let key = (sender_id, transfer_id);
+        let open = self
+            .transfers
+            .keys()
+            .filter(|&&(sender, _)| sender == sender_id)
+            .count();
+        if !self.transfers.contains_key(&key) && open >= MAX_OPEN_TRANSFERS {
+            warn!(
+                self,
+                "{} [ {} ]: [ Client {} ] already sends {} files, refusing transfer {}",
+                "!!!".yellow(),
+                self.log_tag,
+                sender_id,
+                open,
+                transfer_id
+            );
+            return None;
+        }
+        Some(
+            self.transfers
+                .entry(key)
+                .or_insert_with(|| IncomingTransfer {
+                    manifest: None,
+                    chunks: HashMap::new(),
+                    started: Instant::now(),
+                }),
+        )
+    }
+
+    /// Discards the files that did not complete within [`TRANSFER_TTL`].
+    pub(super) fn expire_transfers(&mut self) {
+        let ttl = self.scaled(TRANSFER_TTL);
+        let expired: Vec<(NodeId, u64)> = self
+            .transfers
+            .iter()
+            .filter(|(_, transfer)| transfer.started.elapsed() >= ttl)
+            .map(|(&key, _)| key)
+            .collect();
+
+        for (sender_id, transfer_id) in expired {
+            let Some(transfer) = self.transfers.remove(&(sender_id, transfer_id)) else {
+                continue;
+            };
+            let name = transfer.manifest.map_or_else(
+                || format!("transfer-{transfer_id}"),
+                |manifest| manifest.name,
+            );
+            warn!(
+                self,
+                "{} [ {} ]: Discarded the incomplete file {} from [ Client {} ]",
+                "!!!".yellow(),
+                self.log_tag,
+                name,
+                sender_id
+            );
+            self.send_event(ChatClientEvent::FileTransferFailed(sender_id, name));
+        }
+    }
+
+    fn check_transfer(&mut self, sender_id: NodeId, transfer_id: u64) {
+        let key = (sender_id, transfer_id);
+        if !self
+            .transfers
+            .get(&key)
+            .is_some_and(IncomingTransfer::is_complete)
+        {
+            return;
+        }
+        let Some(IncomingTransfer {
+            manifest: Some(manifest),
+            mut chunks,
+        }) = self.transfers.remove(&key)
+        else {
+            return;
+        };
+
+        let data: Vec<u8> = (0..manifest.chunks)
+            .filter_map(|index| chunks.remove(&index))
+            .flatten()
+            .collect();
+
+        if data.len() as u64 != manifest.size || checksum(&data) != manifest.checksum {
+            error!(
+                self,
+                "{} [ {} ]: {} from [ Client {} ] is corrupted, discarding it",
+                "✗".red(),
+                self.log_tag,
+                manifest.name,
+                sender_id
+            );
+            self.send_event(ChatClientEvent::FileTransferFailed(
+                sender_id,
+                manifest.name,
+            ));
+            return;
+        }
+
+        let path = self.download_path(&manifest.name, transfer_id);
+        match fs::write(&path, data) {
+            Ok(()) => {
+                info!(
+                    self,
+                    "{} [ {} ]: Received {} from [ Client {} ], saved to {}",
+                    "✓".green(),
+                    self.log_tag,
+                    manifest.name,
+                    sender_id,
+                    path.display()
+                );
+                self.send_event(ChatClientEvent::FileReceived(sender_id, path));
+            }
+            Err(e) => {
+                error!(
+                    self,
+                    "{} [ {} ]: Failed to save {} to {}: {}",
+                    "✗".red(),
+                    self.log_tag,
+                    manifest.name,
+                    path.display(),
+                    e
+                );
+                self.send_event(ChatClientEvent::FileTransferFailed(
+                    sender_id,
+                    manifest.name,
+                ));
+            }
+        }
+    }
+
+    /// Where a received file is stored. Only the final component of the
+    /// name chosen by the sender is kept, so that it cannot escape the
+    /// download directory.
+    fn download_path(&self, name: &str, transfer_id: u64) -> PathBuf {
+        let file_name = Path::new(name).file_name().map_or_else(
+            || format!("transfer-{transfer_id}").into(),
+            ToOwned::to_owned,
+        );
+        self.config.download_dir.join(file_name)
+    }
+}
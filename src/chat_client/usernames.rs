@@ -0,0 +1,61 @@
+use colored::Colorize;
+use messages::client_commands::ChatClientEvent;
+use wg_2024::network::NodeId;
+
+use super::{payload::Payload, ChatClient};
+
+impl ChatClient {
+    pub(super) fn set_username(&mut self, username: String) {
+        info!(
+            self,
+            "{} [ {} ]: Username set to {}",
+            "ℹ".blue(),
+            self.log_tag,
+            username
+        );
+        self.username = Some(username);
+
+        let peers = self.peers.ids();
+        self.announce_username(&peers);
+        self.send_username_list();
+    }
+
+    /// Tells `peers` the client's username, if it has one.
+    pub(super) fn announce_username(&mut self, peers: &[NodeId]) {
+        let Some(username) = self.username.clone() else {
+            return;
+        };
+
+        let payload = Payload::Username { username };
+        for &peer in peers {
+            let _ = self.send_payload(peer, &payload);
+        }
+    }
+
+    pub(super) fn username_received(&mut self, sender_id: NodeId, username: &str) {
+        if !self.peers.set_username(sender_id, username) {
+            return;
+        }
+
+        info!(
+            self,
+            "{} [ {} ]: [ Client {} ] is now known as {}",
+            "ℹ".blue(),
+            self.log_tag,
+            sender_id,
+            username
+        );
+        self.send_username_list();
+    }
+
+    pub(super) fn send_username_list(&self) {
+        let mut usernames: Vec<(NodeId, String)> = self
+            .peers
+            .usernames()
+            .map(|(id, name)| (id, name.clone()))
+            .chain(self.username.clone().map(|name| (self.id, name)))
+            .collect();
+        usernames.sort_unstable();
+        self.send_event(ChatClientEvent::UsernameList(self.id, usernames));
+    }
+}
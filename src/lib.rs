@@ -1,3 +1,6 @@
 mod chat_client;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
 pub use chat_client::*;
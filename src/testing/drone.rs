@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodRequest, Nack, NackType, NodeType, Packet, PacketType},
+};
+
+use super::{flood_response, route_back};
+
+/// A drone that follows the protocol rules and drops fragments with the
+/// configured probability.
+///
+/// Ack, Nack and flood response packets that cannot be delivered are
+/// discarded, there is no simulation controller to shortcut them to.
+#[derive(Debug)]
+pub struct MockDrone {
+    id: NodeId,
+    pdr: f32,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    stop: Receiver<()>,
+    floods_seen: HashSet<(u64, NodeId)>,
+    running: bool,
+}
+
+impl MockDrone {
+    /// Creates a new `MockDrone`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The `NodeId` of the drone.
+    /// * `pdr` - The probability, between 0 and 1, of dropping a fragment.
+    /// * `packet_recv` - A `Receiver` for the packets sent to the drone.
+    /// * `packet_send` - A `HashMap` mapping each neighbour to its `Sender`.
+    /// * `stop` - A `Receiver` whose disconnection stops the drone.
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        pdr: f32,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        stop: Receiver<()>,
+    ) -> Self {
+        Self {
+            id,
+            pdr,
+            packet_recv,
+            packet_send,
+            stop,
+            floods_seen: HashSet::new(),
+            running: true,
+        }
+    }
+
+    /// Forwards packets until `stop` or every packet sender is dropped.
+    pub fn run(&mut self) {
+        while self.running {
+            select_biased! {
+                recv(self.stop) -> _ => self.running = false,
+                recv(self.packet_recv) -> packet => {
+                    if let Ok(packet) = packet {
+                        self.handle_packet(packet);
+                    } else {
+                        self.running = false;
+                    }
+                },
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, mut packet: Packet) {
+        if let PacketType::FloodRequest(flood_request) = packet.pack_type {
+            self.handle_flood_request(flood_request, packet.session_id);
+            return;
+        }
+
+        let position = packet.routing_header.hop_index;
+        if packet.routing_header.current_hop() != Some(self.id) {
+            self.nack(&packet, position, NackType::UnexpectedRecipient(self.id));
+            return;
+        }
+
+        packet.routing_header.increase_hop_index();
+        let Some(next_hop) = packet.routing_header.current_hop() else {
+            self.nack(&packet, position, NackType::DestinationIsDrone);
+            return;
+        };
+
+        if matches!(packet.pack_type, PacketType::MsgFragment(_))
+            && rand::random::<f32>() < self.pdr
+        {
+            self.nack(&packet, position, NackType::Dropped);
+            return;
+        }
+
+        let sent = self
+            .packet_send
+            .get(&next_hop)
+            .is_some_and(|sender| sender.send(packet.clone()).is_ok());
+        if !sent {
+            self.nack(&packet, position, NackType::ErrorInRouting(next_hop));
+        }
+    }
+
+    fn handle_flood_request(&mut self, mut flood_request: FloodRequest, session_id: u64) {
+        let previous = flood_request.path_trace.last().map(|&(id, _)| id);
+        flood_request.path_trace.push((self.id, NodeType::Drone));
+
+        let first_time = self
+            .floods_seen
+            .insert((flood_request.flood_id, flood_request.initiator_id));
+        let next_hops: Vec<NodeId> = self
+            .packet_send
+            .keys()
+            .copied()
+            .filter(|&id| Some(id) != previous)
+            .collect();
+
+        if !first_time || next_hops.is_empty() {
+            self.send_back(flood_response(&flood_request, session_id));
+            return;
+        }
+
+        for next_hop in next_hops {
+            let packet = Packet {
+                routing_header: SourceRoutingHeader::empty_route(),
+                session_id,
+                pack_type: PacketType::FloodRequest(flood_request.clone()),
+            };
+            if let Some(sender) = self.packet_send.get(&next_hop) {
+                let _ = sender.send(packet);
+            }
+        }
+    }
+
+    /// Answers a fragment with a Nack, other packets are only discarded.
+    fn nack(&self, packet: &Packet, position: usize, nack_type: NackType) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
+
+        self.send_back(Packet {
+            routing_header: route_back(self.id, &packet.routing_header, position),
+            session_id: packet.session_id,
+            pack_type: PacketType::Nack(Nack {
+                fragment_index: fragment.fragment_index,
+                nack_type,
+            }),
+        });
+    }
+
+    fn send_back(&self, packet: Packet) {
+        if let Some(sender) = packet
+            .routing_header
+            .current_hop()
+            .and_then(|next_hop| self.packet_send.get(&next_hop))
+        {
+            let _ = sender.send(packet);
+        }
+    }
+}
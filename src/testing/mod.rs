@@ -0,0 +1,59 @@
+//! Mock nodes to run a `ChatClient` end to end without the full simulation.
+//!
+//! Enabled by the `testing` feature. [`MockNetwork`] wires [`MockDrone`]s,
+//! [`MockCommunicationServer`]s and `ChatClient`s over crossbeam channels,
+//! each on its own thread, and hands back a [`MockController`] per client
+//! to send commands and wait for events. A [`Scenario`] scripts a whole
+//! exchange over such a network and checks that no node panicked, that
+//! every message was delivered or reported as failed, and that the clients
+//! only received packets following the protocol.
+
+mod drone;
+mod network;
+mod scenario;
+mod server;
+
+pub use drone::MockDrone;
+pub use network::{MockController, MockNetwork, RunningNetwork};
+pub use scenario::{Scenario, ScenarioReport, SentMessage, Step};
+pub use server::MockCommunicationServer;
+
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodRequest, FloodResponse, Packet, PacketType},
+};
+
+/// The route from `id`, found at `position` in `routing_header`, back to
+/// the source of the packet.
+fn route_back(
+    id: NodeId,
+    routing_header: &SourceRoutingHeader,
+    position: usize,
+) -> SourceRoutingHeader {
+    let mut hops: Vec<NodeId> = routing_header.hops[..position.min(routing_header.hops.len())]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    hops.insert(0, id);
+    SourceRoutingHeader::with_first_hop(hops)
+}
+
+/// The `FloodResponse` to a request whose path trace already ends with the
+/// responding node.
+fn flood_response(flood_request: &FloodRequest, session_id: u64) -> Packet {
+    let hops = flood_request
+        .path_trace
+        .iter()
+        .rev()
+        .map(|&(id, _)| id)
+        .collect();
+    Packet {
+        routing_header: SourceRoutingHeader::with_first_hop(hops),
+        session_id,
+        pack_type: PacketType::FloodResponse(FloodResponse {
+            flood_id: flood_request.flood_id,
+            path_trace: flood_request.path_trace.clone(),
+        }),
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{unbounded, Receiver, Select, Sender};
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::{network::NodeId, packet::Packet};
+
+use super::{MockCommunicationServer, MockDrone};
+use crate::{ChatClientBuilder, ConformanceMode};
+
+/// Adjusts the builder of a client before it is built.
+type Configure = fn(ChatClientBuilder) -> ChatClientBuilder;
+
+#[derive(Debug, Clone, Copy)]
+enum MockNode {
+    Drone { pdr: f32 },
+    Server,
+    Client { configure: Configure },
+}
+
+/// A small topology of mock drones, mock servers and `ChatClient`s.
+///
+/// # Methods
+///
+/// * `new` - Creates an empty `MockNetwork`.
+/// * `with_drone` - Adds a `MockDrone` with a packet drop rate.
+/// * `with_server` - Adds a `MockCommunicationServer`.
+/// * `with_client` - Adds a `ChatClient`.
+/// * `with_configured_client` - Adds a `ChatClient` with its own configuration.
+/// * `with_link` - Connects two nodes in both directions.
+/// * `spawn` - Starts every node on its own thread.
+#[derive(Debug, Default)]
+pub struct MockNetwork {
+    nodes: HashMap<NodeId, MockNode>,
+    links: Vec<(NodeId, NodeId)>,
+}
+
+impl MockNetwork {
+    /// Creates an empty `MockNetwork`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `MockDrone` dropping fragments with probability `pdr`.
+    #[must_use]
+    pub fn with_drone(mut self, id: NodeId, pdr: f32) -> Self {
+        self.nodes.insert(id, MockNode::Drone { pdr });
+        self
+    }
+
+    /// Adds a `MockCommunicationServer`.
+    #[must_use]
+    pub fn with_server(mut self, id: NodeId) -> Self {
+        self.nodes.insert(id, MockNode::Server);
+        self
+    }
+
+    /// Adds a `ChatClient` with the default configuration, auditing the
+    /// packets it receives with `ConformanceMode::Audit`.
+    #[must_use]
+    pub fn with_client(self, id: NodeId) -> Self {
+        self.with_configured_client(id, |builder| builder)
+    }
+
+    /// Adds a `ChatClient` whose builder is passed through `configure`
+    /// after the settings of [`MockNetwork::with_client`] are applied.
+    #[must_use]
+    pub fn with_configured_client(mut self, id: NodeId, configure: Configure) -> Self {
+        self.nodes.insert(id, MockNode::Client { configure });
+        self
+    }
+
+    /// Connects `a` and `b` in both directions.
+    #[must_use]
+    pub fn with_link(mut self, a: NodeId, b: NodeId) -> Self {
+        self.links.push((a, b));
+        self
+    }
+
+    /// Creates the channels and starts every node on its own thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a link refers to a node that was not added.
+    #[must_use]
+    pub fn spawn(self) -> RunningNetwork {
+        let channels: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)> =
+            self.nodes.keys().map(|&id| (id, unbounded())).collect();
+
+        let mut neighbors: HashMap<NodeId, HashMap<NodeId, Sender<Packet>>> =
+            self.nodes.keys().map(|&id| (id, HashMap::new())).collect();
+        for &(a, b) in &self.links {
+            assert!(
+                channels.contains_key(&a) && channels.contains_key(&b),
+                "link {a} - {b} refers to an unknown node"
+            );
+            neighbors
+                .get_mut(&a)
+                .unwrap()
+                .insert(b, channels[&b].0.clone());
+            neighbors
+                .get_mut(&b)
+                .unwrap()
+                .insert(a, channels[&a].0.clone());
+        }
+
+        let mut network = RunningNetwork {
+            controllers: HashMap::new(),
+            stops: HashMap::new(),
+            threads: Vec::new(),
+        };
+
+        for (id, node) in self.nodes {
+            let packet_recv = channels[&id].1.clone();
+            let packet_send = neighbors.remove(&id).unwrap_or_default();
+
+            let thread = match node {
+                MockNode::Drone { pdr } => {
+                    let (stop_send, stop) = unbounded();
+                    network.stops.insert(id, stop_send);
+                    thread::spawn(move || {
+                        MockDrone::new(id, pdr, packet_recv, packet_send, stop).run();
+                    })
+                }
+                MockNode::Server => {
+                    let (stop_send, stop) = unbounded();
+                    network.stops.insert(id, stop_send);
+                    thread::spawn(move || {
+                        MockCommunicationServer::new(id, packet_recv, packet_send, stop).run();
+                    })
+                }
+                MockNode::Client { configure } => {
+                    let (commands, controller_recv) = unbounded();
+                    let (controller_send, events) = unbounded();
+                    network
+                        .controllers
+                        .insert(id, MockController { commands, events });
+                    thread::spawn(move || {
+                        let builder = ChatClientBuilder::new(
+                            id,
+                            controller_send,
+                            controller_recv,
+                            packet_recv,
+                        )
+                        .with_initial_neighbors(packet_send)
+                        .with_conformance(ConformanceMode::Audit);
+                        configure(builder).build().run();
+                    })
+                }
+            };
+            network.threads.push((id, thread));
+        }
+
+        network
+    }
+}
+
+/// The channels a simulation controller would hold for one `ChatClient`.
+#[derive(Debug)]
+pub struct MockController {
+    commands: Sender<ChatClientCommand>,
+    events: Receiver<ChatClientEvent>,
+}
+
+impl MockController {
+    /// Sends `command` to the client, ignoring a client that already stopped.
+    pub fn send(&self, command: ChatClientCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// Waits up to `timeout` for an event matching `predicate`, discarding
+    /// the events that do not match.
+    ///
+    /// # Returns
+    ///
+    /// The first matching event, or `None` if none arrived in time.
+    pub fn wait_for<F>(&self, timeout: Duration, mut predicate: F) -> Option<ChatClientEvent>
+    where
+        F: FnMut(&ChatClientEvent) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.events.recv_timeout(remaining) {
+                Ok(event) if predicate(&event) => return Some(event),
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+
+    /// Takes the events received so far without waiting.
+    #[must_use]
+    pub fn drain(&self) -> Vec<ChatClientEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+/// A [`MockNetwork`] whose nodes are running.
+#[derive(Debug)]
+pub struct RunningNetwork {
+    controllers: HashMap<NodeId, MockController>,
+    stops: HashMap<NodeId, Sender<()>>,
+    threads: Vec<(NodeId, JoinHandle<()>)>,
+}
+
+impl RunningNetwork {
+    /// The controller of the client `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not a client of the network.
+    #[must_use]
+    pub fn client(&self, id: NodeId) -> &MockController {
+        self.controllers
+            .get(&id)
+            .unwrap_or_else(|| panic!("{id} is not a client of the network"))
+    }
+
+    /// Stops the drone or server `id`, its neighbours then fail to send to it.
+    pub fn crash(&mut self, id: NodeId) {
+        self.stops.remove(&id);
+    }
+
+    /// Waits up to `timeout` for the next event of any client.
+    ///
+    /// # Returns
+    ///
+    /// The client and its event, or `None` if none arrived in time or a
+    /// client stopped.
+    pub(super) fn next_event(&self, timeout: Duration) -> Option<(NodeId, ChatClientEvent)> {
+        let controllers: Vec<(NodeId, &MockController)> = self
+            .controllers
+            .iter()
+            .map(|(&id, controller)| (id, controller))
+            .collect();
+        let mut select = Select::new();
+        for (_, controller) in &controllers {
+            select.recv(&controller.events);
+        }
+
+        let operation = select.select_timeout(timeout).ok()?;
+        let (id, controller) = controllers[operation.index()];
+        operation
+            .recv(&controller.events)
+            .ok()
+            .map(|event| (id, event))
+    }
+
+    /// The ids of the clients of the network.
+    #[must_use]
+    pub fn clients(&self) -> Vec<NodeId> {
+        let mut ids: Vec<NodeId> = self.controllers.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Shuts every client down, stops every other node and waits for all
+    /// the threads to finish.
+    ///
+    /// # Returns
+    ///
+    /// The ids of the nodes whose thread panicked.
+    #[must_use]
+    pub fn shutdown(mut self) -> Vec<NodeId> {
+        for controller in self.controllers.values() {
+            controller.send(ChatClientCommand::Shutdown);
+        }
+        self.stops.clear();
+        let mut panicked: Vec<NodeId> = self
+            .threads
+            .drain(..)
+            .filter_map(|(id, thread)| thread.join().is_err().then_some(id))
+            .collect();
+        panicked.sort_unstable();
+        panicked
+    }
+}
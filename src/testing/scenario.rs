@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use messages::client_commands::{ChatClientCommand, ChatClientEvent, ProtocolViolation};
+use wg_2024::network::NodeId;
+
+use super::{MockNetwork, RunningNetwork};
+
+/// One action of a [`Scenario`], played by the scripted controller.
+#[derive(Debug)]
+pub enum Step {
+    /// Sends a command to a client.
+    Command(NodeId, ChatClientCommand),
+    /// Sends a chat message and tracks whether it is delivered.
+    Send {
+        from: NodeId,
+        to: NodeId,
+        content: String,
+    },
+    /// Stops a drone or server, possibly in the middle of a transfer.
+    Crash(NodeId),
+    /// Lets the network run for a while.
+    Wait(Duration),
+    /// Waits up to the given time for an event of a client matching the
+    /// predicate, recording a timeout otherwise. Events received since the
+    /// previous match of the client count, none are discarded.
+    WaitFor(NodeId, Duration, fn(&ChatClientEvent) -> bool),
+}
+
+/// A chat message sent during a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMessage {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub content: String,
+}
+
+/// What happened while a [`Scenario`] ran.
+#[derive(Debug, Default)]
+pub struct ScenarioReport {
+    /// Messages the recipient received.
+    pub delivered: Vec<SentMessage>,
+    /// Messages the sender was told could not be delivered.
+    pub failed: Vec<SentMessage>,
+    /// Messages neither delivered nor reported as failed.
+    pub lost: Vec<SentMessage>,
+    /// Indices of the `Step::WaitFor` steps that timed out.
+    pub timeouts: Vec<usize>,
+    /// Nodes whose thread panicked.
+    pub panicked: Vec<NodeId>,
+    /// Packets breaking the protocol, by the client that received them.
+    pub violations: Vec<(NodeId, ProtocolViolation)>,
+    /// Every event each client sent to the controller, in order.
+    pub events: HashMap<NodeId, Vec<ChatClientEvent>>,
+}
+
+impl ScenarioReport {
+    /// Whether no node panicked and every message was either delivered or
+    /// reported as failed.
+    #[must_use]
+    pub fn holds_invariants(&self) -> bool {
+        self.panicked.is_empty() && self.lost.is_empty()
+    }
+
+    /// Whether every packet the clients received followed the protocol.
+    #[must_use]
+    pub fn is_conformant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// A scripted exchange between the clients of a [`MockNetwork`].
+///
+/// # Methods
+///
+/// * `new` - Creates a scenario over a network.
+/// * `with_step` - Appends a step to the script.
+/// * `with_settle_time` - Sets how long messages may take to settle after the last step.
+/// * `run` - Plays the script and reports what happened.
+#[derive(Debug)]
+pub struct Scenario {
+    network: MockNetwork,
+    steps: Vec<Step>,
+    settle_time: Duration,
+}
+
+impl Scenario {
+    /// Creates a scenario over `network`, with no steps yet.
+    #[must_use]
+    pub fn new(network: MockNetwork) -> Self {
+        Self {
+            network,
+            steps: Vec::new(),
+            settle_time: Duration::from_secs(5),
+        }
+    }
+
+    /// Appends `step` to the script.
+    #[must_use]
+    pub fn with_step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Sets how long the messages sent may take, after the last step, to be
+    /// delivered or reported as failed before the others count as lost.
+    /// Defaults to 5 seconds.
+    #[must_use]
+    pub fn with_settle_time(mut self, settle_time: Duration) -> Self {
+        self.settle_time = settle_time;
+        self
+    }
+
+    /// Spawns the network, plays every step in order, waits for the messages
+    /// sent to settle, then shuts the network down.
+    #[must_use]
+    pub fn run(self) -> ScenarioReport {
+        let mut network = self.network.spawn();
+        let mut report = ScenarioReport::default();
+        let mut sent = Vec::new();
+        let mut matched = HashMap::new();
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            match step {
+                Step::Command(client, command) => network.client(client).send(command),
+                Step::Send { from, to, content } => {
+                    network
+                        .client(from)
+                        .send(ChatClientCommand::SendMessageTo(to, content.clone()));
+                    sent.push(SentMessage { from, to, content });
+                }
+                Step::Crash(node) => network.crash(node),
+                Step::Wait(duration) => thread::sleep(duration),
+                Step::WaitFor(client, timeout, predicate) => {
+                    collect_events(&network, &mut report);
+                    if !wait_for(
+                        &network,
+                        &mut report,
+                        &mut matched,
+                        client,
+                        timeout,
+                        predicate,
+                    ) {
+                        report.timeouts.push(index);
+                    }
+                }
+            }
+            collect_events(&network, &mut report);
+        }
+
+        settle(&network, &mut report, &sent, self.settle_time);
+        report.panicked = network.shutdown();
+        report.violations = violations(&report.events);
+
+        for message in sent {
+            if is_delivered(&report.events, &message) {
+                report.delivered.push(message);
+            } else if is_failed(&report.events, &message) {
+                report.failed.push(message);
+            } else {
+                report.lost.push(message);
+            }
+        }
+        report
+    }
+}
+
+fn collect_events(network: &RunningNetwork, report: &mut ScenarioReport) {
+    for client in network.clients() {
+        report
+            .events
+            .entry(client)
+            .or_default()
+            .extend(network.client(client).drain());
+    }
+}
+
+/// Collects the events of the clients until one of `client` after the
+/// previous match, whose position is kept in `matched`, satisfies
+/// `predicate`, or until `timeout` elapsed.
+///
+/// # Returns
+///
+/// Whether a matching event arrived in time.
+fn wait_for(
+    network: &RunningNetwork,
+    report: &mut ScenarioReport,
+    matched: &mut HashMap<NodeId, usize>,
+    client: NodeId,
+    timeout: Duration,
+    predicate: fn(&ChatClientEvent) -> bool,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let events = report.events.entry(client).or_default();
+        let from = matched.get(&client).copied().unwrap_or(0);
+        if let Some(position) = events[from..].iter().position(predicate) {
+            matched.insert(client, from + position + 1);
+            return true;
+        }
+
+        let Some((other, event)) = deadline
+            .checked_duration_since(Instant::now())
+            .and_then(|remaining| network.next_event(remaining))
+        else {
+            return false;
+        };
+        report.events.entry(other).or_default().push(event);
+    }
+}
+
+/// Collects the events of the clients until every message in `sent` is
+/// delivered or reported as failed, or until `settle_time` elapsed.
+fn settle(
+    network: &RunningNetwork,
+    report: &mut ScenarioReport,
+    sent: &[SentMessage],
+    settle_time: Duration,
+) {
+    let deadline = Instant::now() + settle_time;
+    collect_events(network, report);
+    while sent.iter().any(|message| {
+        !is_delivered(&report.events, message) && !is_failed(&report.events, message)
+    }) {
+        let Some((client, event)) = deadline
+            .checked_duration_since(Instant::now())
+            .and_then(|remaining| network.next_event(remaining))
+        else {
+            break;
+        };
+        report.events.entry(client).or_default().push(event);
+    }
+    collect_events(network, report);
+}
+
+fn violations(events: &HashMap<NodeId, Vec<ChatClientEvent>>) -> Vec<(NodeId, ProtocolViolation)> {
+    let mut violations: Vec<(NodeId, ProtocolViolation)> = events
+        .iter()
+        .flat_map(|(&client, events)| {
+            events.iter().filter_map(move |event| match event {
+                ChatClientEvent::ProtocolViolation(violation) => Some((client, violation.clone())),
+                _ => None,
+            })
+        })
+        .collect();
+    violations.sort_by_key(|&(client, _)| client);
+    violations
+}
+
+fn is_delivered(events: &HashMap<NodeId, Vec<ChatClientEvent>>, message: &SentMessage) -> bool {
+    events.get(&message.to).is_some_and(|events| {
+        events.iter().any(|event| {
+            matches!(event, ChatClientEvent::MessageReceived(from, _, content, _)
+                if *from == message.from && *content == message.content)
+        })
+    })
+}
+
+fn is_failed(events: &HashMap<NodeId, Vec<ChatClientEvent>>, message: &SentMessage) -> bool {
+    let Some(events) = events.get(&message.from) else {
+        return false;
+    };
+
+    let message_ids: Vec<u64> = events
+        .iter()
+        .filter_map(|event| match event {
+            ChatClientEvent::MessageSent(to, id) if *to == message.to => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    events.iter().any(|event| match event {
+        ChatClientEvent::UnreachableClient(to) => *to == message.to,
+        ChatClientEvent::QueuedMessageExpired(to, content) => {
+            *to == message.to && *content == message.content
+        }
+        ChatClientEvent::MessageExpired(id)
+        | ChatClientEvent::MessageCancelled(id)
+        | ChatClientEvent::MessageDeliveryFailed(id, _) => message_ids.contains(id),
+        _ => false,
+    })
+}
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use assembler::HighLevelMessageFactory;
+use crossbeam_channel::{select_biased, Receiver, Sender};
+use messages::high_level_messages::{
+    ClientMessage, Message, MessageContent, ServerMessage, ServerType,
+};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Ack, NackType, NodeType, Packet, PacketType},
+};
+
+use super::{flood_response, route_back};
+
+/// A `CommunicationServer` that registers clients and relays their chat
+/// messages.
+///
+/// It never floods: every reply follows the reverse of the route the
+/// client last used to reach it, so a client must have sent something
+/// before it can receive messages.
+#[derive(Debug)]
+pub struct MockCommunicationServer {
+    id: NodeId,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    stop: Receiver<()>,
+    msgfactory: HighLevelMessageFactory,
+    routes: HashMap<NodeId, SourceRoutingHeader>,
+    registered: Vec<NodeId>,
+    sent: HashMap<(u64, u64), Packet>,
+    floods_seen: HashSet<(u64, NodeId)>,
+    running: bool,
+}
+
+impl MockCommunicationServer {
+    /// Creates a new `MockCommunicationServer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The `NodeId` of the server.
+    /// * `packet_recv` - A `Receiver` for the packets sent to the server.
+    /// * `packet_send` - A `HashMap` mapping each neighbour to its `Sender`.
+    /// * `stop` - A `Receiver` whose disconnection stops the server.
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        stop: Receiver<()>,
+    ) -> Self {
+        Self {
+            id,
+            packet_recv,
+            packet_send,
+            stop,
+            msgfactory: HighLevelMessageFactory::new(id, NodeType::Server),
+            routes: HashMap::new(),
+            registered: Vec::new(),
+            sent: HashMap::new(),
+            floods_seen: HashSet::new(),
+            running: true,
+        }
+    }
+
+    /// Serves the clients until `stop` or every packet sender is dropped.
+    pub fn run(&mut self) {
+        while self.running {
+            select_biased! {
+                recv(self.stop) -> _ => self.running = false,
+                recv(self.packet_recv) -> packet => {
+                    if let Ok(packet) = packet {
+                        self.handle_packet(packet);
+                    } else {
+                        self.running = false;
+                    }
+                },
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet) {
+        match packet.pack_type {
+            PacketType::FloodRequest(mut flood_request) => {
+                flood_request.path_trace.push((self.id, NodeType::Server));
+                if self
+                    .floods_seen
+                    .insert((flood_request.flood_id, flood_request.initiator_id))
+                {
+                    self.send(flood_response(&flood_request, packet.session_id));
+                }
+            }
+            PacketType::MsgFragment(fragment) => {
+                let position = packet.routing_header.hop_index;
+                let route = route_back(self.id, &packet.routing_header, position);
+                self.send(Packet {
+                    routing_header: route.clone(),
+                    session_id: packet.session_id,
+                    pack_type: PacketType::Ack(Ack {
+                        fragment_index: fragment.fragment_index,
+                    }),
+                });
+
+                let Some(source_id) = packet.routing_header.source() else {
+                    return;
+                };
+                self.routes.insert(source_id, route);
+                if let Some(message) =
+                    self.msgfactory
+                        .received_fragment(fragment, packet.session_id, source_id)
+                {
+                    self.handle_message(message);
+                }
+            }
+            PacketType::Ack(ack) => {
+                self.sent.remove(&(packet.session_id, ack.fragment_index));
+            }
+            PacketType::Nack(nack) => {
+                let key = (packet.session_id, nack.fragment_index);
+                if nack.nack_type == NackType::Dropped {
+                    if let Some(resent) = self.sent.get(&key).cloned() {
+                        self.send(resent);
+                    }
+                } else {
+                    self.sent.remove(&key);
+                }
+            }
+            PacketType::FloodResponse(_) => {}
+        }
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        let MessageContent::FromClient(client_message) = message.content else {
+            return;
+        };
+        let client_id = message.source_id;
+
+        match client_message {
+            ClientMessage::GetServerType => {
+                self.reply(client_id, ServerMessage::ServerType(ServerType::Chat));
+            }
+            ClientMessage::RegisterToChat => {
+                if !self.registered.contains(&client_id) {
+                    self.registered.push(client_id);
+                }
+                self.reply(client_id, ServerMessage::SuccessfulRegistration);
+            }
+            ClientMessage::GetClientList => {
+                self.reply(
+                    client_id,
+                    ServerMessage::ClientList(self.registered.clone()),
+                );
+            }
+            ClientMessage::SendMessage {
+                recipient_id,
+                content,
+            } => {
+                if self.registered.contains(&recipient_id)
+                    && self.routes.contains_key(&recipient_id)
+                {
+                    self.reply(
+                        recipient_id,
+                        ServerMessage::MessageReceived {
+                            sender_id: client_id,
+                            content,
+                        },
+                    );
+                } else {
+                    self.reply(client_id, ServerMessage::UnreachableClient(recipient_id));
+                }
+            }
+            ClientMessage::Logout => {
+                self.registered.retain(|&id| id != client_id);
+                self.reply(client_id, ServerMessage::SuccessfullLogOut);
+            }
+            _ => {}
+        }
+    }
+
+    fn reply(&mut self, client_id: NodeId, server_message: ServerMessage) {
+        let Some(route) = self.routes.get(&client_id).cloned() else {
+            return;
+        };
+
+        for packet in self.msgfactory.get_message_from_message_content(
+            MessageContent::FromServer(server_message),
+            &route,
+            client_id,
+        ) {
+            if let PacketType::MsgFragment(fragment) = &packet.pack_type {
+                self.sent
+                    .insert((packet.session_id, fragment.fragment_index), packet.clone());
+            }
+            self.send(packet);
+        }
+    }
+
+    fn send(&self, packet: Packet) {
+        if let Some(sender) = packet
+            .routing_header
+            .current_hop()
+            .and_then(|next_hop| self.packet_send.get(&next_hop))
+        {
+            let _ = sender.send(packet);
+        }
+    }
+}
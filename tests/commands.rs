@@ -0,0 +1,58 @@
+//! The events a client sends back for the commands it cannot carry out.
+
+use std::time::Duration;
+
+use chat_client::testing::MockNetwork;
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::network::NodeId;
+
+const CLIENT: NodeId = 1;
+const PEER: NodeId = 2;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn a_stopped_client_reports_the_command_it_cannot_run() {
+    let network = MockNetwork::new()
+        .with_client(CLIENT)
+        .with_drone(10, 0.0)
+        .with_link(CLIENT, 10)
+        .spawn();
+
+    network.client(CLIENT).send(ChatClientCommand::WithId(
+        7,
+        Box::new(ChatClientCommand::SendMessageTo(PEER, "hello".to_string())),
+    ));
+
+    let rejected = network.client(CLIENT).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::CommandRejected { .. })
+    });
+    assert!(
+        matches!(
+            &rejected,
+            Some(ChatClientEvent::CommandRejected { id: Some(7), .. })
+        ),
+        "the client answered {rejected:?}"
+    );
+
+    let not_running = network.client(CLIENT).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::ErrorNotRunning(_))
+    });
+    let Some(ChatClientEvent::ErrorNotRunning(context)) = not_running else {
+        panic!("the client did not report that it is not running");
+    };
+    assert_eq!(context.client_id, CLIENT);
+    assert_eq!(context.command, "SendMessageTo");
+    assert_eq!(context.destination, Some(PEER));
+
+    let failed = network.client(CLIENT).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::CommandFailed { .. })
+    });
+    assert!(
+        matches!(&failed, Some(ChatClientEvent::CommandFailed { command, .. })
+            if command.command == "SendMessageTo"),
+        "the client reported {failed:?}"
+    );
+
+    let panicked = network.shutdown();
+    assert!(panicked.is_empty(), "nodes {panicked:?} panicked");
+}
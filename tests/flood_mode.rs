@@ -0,0 +1,162 @@
+//! How a client takes part in the floods of other nodes, in each
+//! `FloodMode`.
+
+use std::{
+    collections::HashMap,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use chat_client::{ChatClientBuilder, FloodMode};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{FloodRequest, NodeType, Packet, PacketType},
+};
+
+const CLIENT: NodeId = 1;
+const INITIATOR: NodeId = 50;
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a neighbour is watched to check that it receives nothing.
+const QUIET: Duration = Duration::from_millis(300);
+
+/// A client on its own thread, with the channels of its neighbours.
+struct Client {
+    commands: Sender<ChatClientCommand>,
+    // dropping it would make the client shut down
+    _events: Receiver<ChatClientEvent>,
+    packets: Sender<Packet>,
+    neighbors: HashMap<NodeId, Receiver<Packet>>,
+    thread: JoinHandle<()>,
+}
+
+impl Client {
+    fn spawn(flood_mode: FloodMode, neighbors: &[NodeId]) -> Self {
+        let (commands, controller_recv) = unbounded();
+        let (controller_send, events) = unbounded();
+        let (packets, packet_recv) = unbounded();
+        let (senders, neighbors): (HashMap<_, _>, HashMap<_, _>) = neighbors
+            .iter()
+            .map(|&id| {
+                let (sender, receiver) = unbounded();
+                ((id, sender), (id, receiver))
+            })
+            .unzip();
+
+        let thread = thread::spawn(move || {
+            ChatClientBuilder::new(CLIENT, controller_send, controller_recv, packet_recv)
+                .with_initial_neighbors(senders)
+                .with_flood_mode(flood_mode)
+                .build()
+                .run();
+        });
+        Self {
+            commands,
+            _events: events,
+            packets,
+            neighbors,
+            thread,
+        }
+    }
+
+    /// Sends the client the `FloodRequest` of flood `flood_id`, arriving
+    /// from `neighbor`.
+    fn flood_request(&self, flood_id: u64, neighbor: NodeId) {
+        let request = FloodRequest {
+            flood_id,
+            initiator_id: INITIATOR,
+            path_trace: vec![(INITIATOR, NodeType::Client), (neighbor, NodeType::Drone)],
+        };
+        self.packets
+            .send(Packet {
+                routing_header: SourceRoutingHeader::empty_route(),
+                session_id: flood_id,
+                pack_type: PacketType::FloodRequest(request),
+            })
+            .unwrap();
+    }
+
+    /// The next flood packet `neighbor` receives within `timeout`.
+    fn flood_packet(&self, neighbor: NodeId, timeout: Duration) -> Option<PacketType> {
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let packet = self.neighbors[&neighbor].recv_timeout(remaining).ok()?;
+            if matches!(
+                packet.pack_type,
+                PacketType::FloodRequest(_) | PacketType::FloodResponse(_)
+            ) {
+                return Some(packet.pack_type);
+            }
+        }
+        None
+    }
+
+    fn shutdown(self) {
+        self.commands.send(ChatClientCommand::Shutdown).unwrap();
+        self.thread.join().unwrap();
+    }
+}
+
+/// Whether `packet` is a `FloodResponse` whose path trace ends with the
+/// client.
+fn is_response(packet: Option<&PacketType>) -> bool {
+    matches!(packet, Some(PacketType::FloodResponse(response))
+        if matches!(response.path_trace.last(), Some(&(CLIENT, NodeType::Client))))
+}
+
+/// Whether `packet` is a `FloodRequest` whose path trace ends with the
+/// client.
+fn is_forwarded(packet: Option<&PacketType>) -> bool {
+    matches!(packet, Some(PacketType::FloodRequest(request))
+        if matches!(request.path_trace.last(), Some(&(CLIENT, NodeType::Client))))
+}
+
+#[test]
+fn respond_only_answers_the_first_request() {
+    let client = Client::spawn(FloodMode::RespondOnly, &[10, 11]);
+
+    client.flood_request(1, 10);
+    assert!(is_response(client.flood_packet(10, TIMEOUT).as_ref()));
+    assert!(client.flood_packet(11, QUIET).is_none());
+
+    client.flood_request(1, 11);
+    assert!(client.flood_packet(11, QUIET).is_none());
+
+    client.shutdown();
+}
+
+#[test]
+fn per_spec_forwards_the_first_request_to_the_other_neighbors() {
+    let client = Client::spawn(FloodMode::PerSpec, &[10, 11, 12]);
+
+    client.flood_request(1, 10);
+    assert!(is_forwarded(client.flood_packet(11, TIMEOUT).as_ref()));
+    assert!(is_forwarded(client.flood_packet(12, TIMEOUT).as_ref()));
+    assert!(client.flood_packet(10, QUIET).is_none());
+
+    client.shutdown();
+}
+
+#[test]
+fn per_spec_answers_duplicates() {
+    let client = Client::spawn(FloodMode::PerSpec, &[10, 11]);
+
+    client.flood_request(1, 10);
+    assert!(is_forwarded(client.flood_packet(11, TIMEOUT).as_ref()));
+
+    client.flood_request(1, 11);
+    assert!(is_response(client.flood_packet(11, TIMEOUT).as_ref()));
+
+    client.shutdown();
+}
+
+#[test]
+fn per_spec_answers_requests_from_the_only_neighbor() {
+    let client = Client::spawn(FloodMode::PerSpec, &[10]);
+
+    client.flood_request(1, 10);
+    assert!(is_response(client.flood_packet(10, TIMEOUT).as_ref()));
+
+    client.shutdown();
+}
@@ -0,0 +1,66 @@
+//! End-to-end exchanges between clients over the mock nodes of the
+//! `testing` feature.
+
+use std::time::Duration;
+
+use chat_client::{testing::MockNetwork, ChatClientBuilder, ServerSelection};
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A client that discovers the server and registers to it once started.
+fn self_registering(builder: ChatClientBuilder) -> ChatClientBuilder {
+    builder
+        .with_auto_discovery(true)
+        .with_auto_register(ServerSelection::default())
+}
+
+/// Two clients, each behind a drone of its own, around one server.
+fn network() -> MockNetwork {
+    MockNetwork::new()
+        .with_configured_client(1, self_registering)
+        .with_configured_client(2, self_registering)
+        .with_drone(10, 0.0)
+        .with_drone(11, 0.0)
+        .with_server(20)
+        .with_link(1, 10)
+        .with_link(10, 20)
+        .with_link(20, 11)
+        .with_link(11, 2)
+}
+
+#[test]
+fn registered_clients_exchange_a_message() {
+    let network = network().spawn();
+
+    for client in [1, 2] {
+        network
+            .client(client)
+            .send(ChatClientCommand::StartChatClient);
+        let registered = network.client(client).wait_for(TIMEOUT, |event| {
+            matches!(event, ChatClientEvent::SuccessfulRegistration(20))
+        });
+        assert!(registered.is_some(), "client {client} did not register");
+    }
+
+    network.client(1).send(ChatClientCommand::GetClientList);
+    let listed = network.client(1).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::ClientList(_, peers)
+            if peers.iter().any(|peer| peer.id == 2))
+    });
+    assert!(listed.is_some(), "client 2 was not listed");
+
+    network
+        .client(1)
+        .send(ChatClientCommand::SendMessageTo(2, "hello".to_string()));
+    let received = network.client(2).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::MessageReceived(..))
+    });
+    assert!(
+        matches!(&received, Some(ChatClientEvent::MessageReceived(1, 2, content, _)) if content == "hello"),
+        "client 2 received {received:?}"
+    );
+
+    let panicked = network.shutdown();
+    assert!(panicked.is_empty(), "nodes {panicked:?} panicked");
+}
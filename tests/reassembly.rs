@@ -0,0 +1,117 @@
+//! Messages whose fragments arrive interleaved are read in the order they
+//! complete.
+
+use std::time::Duration;
+
+use assembler::HighLevelMessageFactory;
+use chat_client::{
+    testing::{MockNetwork, RunningNetwork},
+    ChatClientBuilder, ServerSelection,
+};
+use messages::{
+    client_commands::{ChatClientCommand, ChatClientEvent},
+    high_level_messages::{MessageContent, ServerMessage},
+};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{NodeType, Packet},
+};
+
+const CLIENT: NodeId = 2;
+const SENDER: NodeId = 1;
+const SERVER: NodeId = 20;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn self_registering(builder: ChatClientBuilder) -> ChatClientBuilder {
+    builder
+        .with_auto_discovery(true)
+        .with_auto_register(ServerSelection::default())
+}
+
+/// A client registered to a server, behind a drone.
+fn registered_client() -> RunningNetwork {
+    let network = MockNetwork::new()
+        .with_configured_client(CLIENT, self_registering)
+        .with_drone(11, 0.0)
+        .with_server(SERVER)
+        .with_link(CLIENT, 11)
+        .with_link(11, SERVER)
+        .spawn();
+
+    network
+        .client(CLIENT)
+        .send(ChatClientCommand::StartChatClient);
+    let registered = network.client(CLIENT).wait_for(TIMEOUT, |event| {
+        matches!(event, ChatClientEvent::SuccessfulRegistration(SERVER))
+    });
+    assert!(registered.is_some(), "the client did not register");
+    network
+}
+
+/// The fragments of `content`, relayed by the server from the sender, as
+/// they reach the client.
+fn relayed(factory: &mut HighLevelMessageFactory, content: &str) -> Vec<Packet> {
+    let route = SourceRoutingHeader::with_first_hop(vec![SERVER, 11, CLIENT]);
+    let mut fragments = factory.get_message_from_message_content(
+        MessageContent::FromServer(ServerMessage::MessageReceived {
+            sender_id: SENDER,
+            content: content.to_string(),
+        }),
+        &route,
+        CLIENT,
+    );
+    for fragment in &mut fragments {
+        fragment.routing_header.hop_index = fragment.routing_header.hops.len() - 1;
+    }
+    assert!(fragments.len() > 1, "the message fits in one fragment");
+    fragments
+}
+
+fn inject(network: &RunningNetwork, packet: Packet) {
+    network
+        .client(CLIENT)
+        .send(ChatClientCommand::InjectPacket(packet));
+}
+
+/// The contents of the next `count` messages the client receives.
+fn received(network: &RunningNetwork, count: usize) -> Vec<String> {
+    (0..count)
+        .filter_map(|_| {
+            network.client(CLIENT).wait_for(TIMEOUT, |event| {
+                matches!(event, ChatClientEvent::MessageReceived(SENDER, ..))
+            })
+        })
+        .filter_map(|event| match event {
+            ChatClientEvent::MessageReceived(_, _, content, _) => Some(content),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn interleaved_messages_are_read_in_completion_order() {
+    let network = registered_client();
+    let mut factory = HighLevelMessageFactory::new(SERVER, NodeType::Server);
+    let first = "first ".repeat(50);
+    let second = "second ".repeat(50);
+    let mut first_fragments = relayed(&mut factory, &first);
+    let mut second_fragments = relayed(&mut factory, &second);
+
+    // the second message completes first: its last fragment goes before
+    // the last one of the first message
+    let first_last = first_fragments.pop().unwrap();
+    let second_last = second_fragments.pop().unwrap();
+    for index in 0..first_fragments.len().max(second_fragments.len()) {
+        for fragments in [&first_fragments, &second_fragments] {
+            if let Some(packet) = fragments.get(index) {
+                inject(&network, packet.clone());
+            }
+        }
+    }
+    inject(&network, second_last);
+    inject(&network, first_last);
+
+    assert_eq!(received(&network, 2), vec![second, first]);
+    let panicked = network.shutdown();
+    assert!(panicked.is_empty(), "nodes {panicked:?} panicked");
+}
@@ -0,0 +1,72 @@
+//! Scripted exchanges between clients, played with `Scenario`.
+
+use std::time::Duration;
+
+use chat_client::{
+    testing::{MockNetwork, Scenario, SentMessage, Step},
+    ChatClientBuilder, ServerSelection,
+};
+use messages::client_commands::{ChatClientCommand, ChatClientEvent};
+use wg_2024::network::NodeId;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A client that discovers the server and registers to it once started.
+fn self_registering(builder: ChatClientBuilder) -> ChatClientBuilder {
+    builder
+        .with_auto_discovery(true)
+        .with_auto_register(ServerSelection::default())
+}
+
+/// Starts `client` and waits for its registration to the server.
+fn register(scenario: Scenario, client: NodeId) -> Scenario {
+    scenario
+        .with_step(Step::Command(client, ChatClientCommand::StartChatClient))
+        .with_step(Step::WaitFor(client, TIMEOUT, |event| {
+            matches!(event, ChatClientEvent::SuccessfulRegistration(20))
+        }))
+}
+
+#[test]
+fn message_between_registered_clients_is_delivered() {
+    let network = MockNetwork::new()
+        .with_configured_client(1, self_registering)
+        .with_configured_client(2, self_registering)
+        .with_drone(10, 0.0)
+        .with_drone(11, 0.0)
+        .with_server(20)
+        .with_link(1, 10)
+        .with_link(10, 20)
+        .with_link(20, 11)
+        .with_link(11, 2);
+
+    let scenario = register(register(Scenario::new(network), 1), 2)
+        .with_step(Step::Command(1, ChatClientCommand::GetClientList))
+        .with_step(Step::WaitFor(1, TIMEOUT, |event| {
+            matches!(event, ChatClientEvent::ClientList(_, peers)
+                if peers.iter().any(|peer| peer.id == 2))
+        }))
+        .with_step(Step::Send {
+            from: 1,
+            to: 2,
+            content: "hello".to_string(),
+        })
+        .with_settle_time(TIMEOUT);
+    let report = scenario.run();
+
+    assert!(
+        report.timeouts.is_empty(),
+        "steps {:?} timed out",
+        report.timeouts
+    );
+    assert!(report.holds_invariants(), "{report:?}");
+    assert!(report.is_conformant(), "{:?}", report.violations);
+    assert_eq!(
+        report.delivered,
+        vec![SentMessage {
+            from: 1,
+            to: 2,
+            content: "hello".to_string(),
+        }]
+    );
+}